@@ -0,0 +1,270 @@
+use std::str::FromStr;
+
+use anyhow::Context;
+
+use rand::rngs::OsRng;
+
+use snarkvm::ledger::store::ConsensusStore;
+use snarkvm::ledger::store::helpers::memory::ConsensusMemory;
+use snarkvm::prelude::*;
+
+use snorkle_oracle_interface::FeeOptions;
+use snorkle_oracle_interface::fees::estimate_base_fee;
+
+use crate::{Bet, BetStatus, ORACLE_PROGRAM_ID, PROGRAM_ID, Wager, WagerEntry};
+
+/// Builds and proves transactions for `proto_snorkle_bet_000.aleo`, and reads its mappings
+pub struct BetClient<N: Network> {
+    key: PrivateKey<N>,
+    vm: VM<N, ConsensusMemory<N>>,
+    /// REST endpoint of the node, including the network, e.g.,
+    /// `https://api.explorer.provable.com/v1/testnet`
+    endpoint: String,
+}
+
+impl<N: Network> BetClient<N> {
+    /// Create a client that signs with `key`, and load the program and its imports
+    pub fn new(key: PrivateKey<N>, endpoint: &str) -> anyhow::Result<Self> {
+        let vm = VM::<N, ConsensusMemory<N>>::from(
+            ConsensusStore::open(0).with_context(|| "Failed to initialize the consensus store")?,
+        )
+        .with_context(|| "Failed to initialize the VM")?;
+
+        {
+            let process = vm.process();
+            let mut process = process.write();
+            for source in [
+                include_str!("../../resources/proto_snorkle_oracle_000.aleo"),
+                include_str!("../../resources/proto_snorkle_bet_000.aleo"),
+            ] {
+                let program = Program::<N>::from_str(source)?;
+                process
+                    .add_program(&program)
+                    .with_context(|| format!("Failed to load {}", program.id()))?;
+            }
+        }
+
+        Ok(Self {
+            key,
+            vm,
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// The address of the signing key
+    pub fn address(&self) -> anyhow::Result<Address<N>> {
+        Address::try_from(&self.key)
+    }
+
+    /// Deposit public credits of the signer into the program
+    pub fn deposit(&self, amount: u64, fee: &FeeOptions) -> anyhow::Result<Transaction<N>> {
+        self.execute("deposit", &[Literal::U64(U64::new(amount)).into()], fee)
+    }
+
+    /// Withdraw credits of the signer from the program
+    pub fn withdraw(&self, amount: u64, fee: &FeeOptions) -> anyhow::Result<Transaction<N>> {
+        self.execute("withdraw", &[Literal::U64(U64::new(amount)).into()], fee)
+    }
+
+    /// Match two signed wagers into a bet, with the signer as the facilitator
+    ///
+    /// The signatures and wagers are checked before proving, so that an invalid bet fails
+    /// here instead of in the VM. Returns the transaction and the bet it creates.
+    #[allow(clippy::too_many_arguments)]
+    pub fn make_bet(
+        &self,
+        bettor_one: Address<N>,
+        wager_one: &Wager<N>,
+        signature_one: Signature<N>,
+        bettor_two: Address<N>,
+        wager_two: &Wager<N>,
+        signature_two: Signature<N>,
+        fee: &FeeOptions,
+    ) -> anyhow::Result<(Transaction<N>, Bet<N>)> {
+        if !wager_one.verify(&bettor_one, &signature_one)? {
+            anyhow::bail!("Invalid signature of {bettor_one} on the first wager");
+        }
+        if !wager_two.verify(&bettor_two, &signature_two)? {
+            anyhow::bail!("Invalid signature of {bettor_two} on the second wager");
+        }
+
+        let bet = Bet::new(
+            bettor_one,
+            wager_one,
+            bettor_two,
+            wager_two,
+            self.address()?,
+        )?;
+
+        let inputs = [
+            Literal::Address(bettor_one).into(),
+            Literal::Address(bettor_two).into(),
+            wager_one.to_plaintext()?.into(),
+            wager_two.to_plaintext()?.into(),
+            Literal::Signature(Box::new(signature_one)).into(),
+            Literal::Signature(Box::new(signature_two)).into(),
+        ];
+        let transaction = self.execute("make_bet", &inputs, fee)?;
+
+        Ok((transaction, bet))
+    }
+
+    /// Pay out a bet once its event was emitted by the oracle
+    pub fn resolve_bet(
+        &self,
+        bet_id: Field<N>,
+        fee: &FeeOptions,
+    ) -> anyhow::Result<Transaction<N>> {
+        self.execute("resolve_bet", &[Literal::Field(bet_id).into()], fee)
+    }
+
+    fn execute(
+        &self,
+        transition: &str,
+        inputs: &[Value<N>],
+        fee: &FeeOptions,
+    ) -> anyhow::Result<Transaction<N>> {
+        let authorization = self
+            .vm
+            .authorize(&self.key, PROGRAM_ID, transition, inputs.iter(), &mut OsRng)
+            .with_context(|| format!("Failed to authorize {PROGRAM_ID}/{transition}"))?;
+        let base_fee = estimate_base_fee(&self.vm.process().read(), &authorization)?;
+        let fee_record = self.prepare_fee(base_fee, fee)?;
+
+        let execution_id = authorization.to_execution_id()?;
+        let fee_authorization = match fee_record {
+            Some(record) => self.vm.authorize_fee_private(
+                &self.key,
+                record,
+                base_fee,
+                fee.priority_fee,
+                execution_id,
+                &mut OsRng,
+            ),
+            None => self.vm.authorize_fee_public(
+                &self.key,
+                base_fee,
+                fee.priority_fee,
+                execution_id,
+                &mut OsRng,
+            ),
+        }
+        .with_context(|| "Failed to authorize the fee")?;
+
+        let query = Some(snarkvm::prelude::query::Query::from(self.endpoint.as_str()));
+        self.vm
+            .execute_authorization(authorization, Some(fee_authorization), query, &mut OsRng)
+            .with_context(|| format!("Failed to execute {PROGRAM_ID}/{transition}"))
+    }
+
+    /// Make sure the estimated fee is within `fee.max_fee`, and parse the fee record (if any)
+    fn prepare_fee(
+        &self,
+        base_fee: u64,
+        fee: &FeeOptions,
+    ) -> anyhow::Result<Option<Record<N, Plaintext<N>>>> {
+        let required = base_fee.saturating_add(fee.priority_fee);
+
+        if let Some(max_fee) = fee.max_fee
+            && required > max_fee
+        {
+            anyhow::bail!(
+                "Estimated fee of {required} microcredits exceeds the maximum of {max_fee}"
+            );
+        }
+
+        fee.fee_record
+            .as_deref()
+            .map(Record::<N, Plaintext<N>>::from_str)
+            .transpose()
+    }
+
+    /// Fetch the value stored under `key` in a mapping of a program
+    fn get_mapping_value(
+        &self,
+        program: &str,
+        mapping: &str,
+        key: &str,
+    ) -> anyhow::Result<Option<Plaintext<N>>> {
+        let url = format!(
+            "{}/program/{program}/mapping/{mapping}/{key}",
+            self.endpoint
+        );
+        let value: Option<String> = ureq::get(url).call()?.body_mut().read_json()?;
+
+        value.map(|value| Plaintext::from_str(&value)).transpose()
+    }
+
+    /// The balance of `owner` held by the program, in microcredits
+    pub fn balance(&self, owner: &Address<N>) -> anyhow::Result<u64> {
+        match self.get_mapping_value(PROGRAM_ID, "balances", &owner.to_string())? {
+            None => Ok(0),
+            Some(Plaintext::Literal(Literal::U64(balance), _)) => Ok(*balance),
+            Some(other) => anyhow::bail!("Invalid balance: {other}"),
+        }
+    }
+
+    /// Look up a bet and whether it was resolved
+    pub fn bet(&self, bet_id: &Field<N>) -> anyhow::Result<Option<(Bet<N>, BetStatus)>> {
+        let Some(bet) = self.get_mapping_value(PROGRAM_ID, "all_bets", &bet_id.to_string())? else {
+            return Ok(None);
+        };
+        let bet = Bet::from_plaintext(&bet)?;
+
+        let status = if self.is_resolved(bet_id)? {
+            BetStatus::Resolved
+        } else {
+            BetStatus::Open
+        };
+
+        Ok(Some((bet, status)))
+    }
+
+    /// Look up a wager that was used in a bet, by the hash of its [`crate::WagerKey`]
+    pub fn confirmed_wager(&self, wager_key: &Field<N>) -> anyhow::Result<Option<WagerEntry<N>>> {
+        self.get_mapping_value(PROGRAM_ID, "confirmed_wagers", &wager_key.to_string())?
+            .map(|entry| WagerEntry::from_plaintext(&entry))
+            .transpose()
+    }
+}
+
+/// Access to the chain beyond the mappings of the betting program
+impl<N: Network> BetClient<N> {
+    /// Has the bet been paid out?
+    pub fn is_resolved(&self, bet_id: &Field<N>) -> anyhow::Result<bool> {
+        match self.get_mapping_value(PROGRAM_ID, "resolved_bets", &bet_id.to_string())? {
+            None => Ok(false),
+            Some(Plaintext::Literal(Literal::Boolean(resolved), _)) => Ok(*resolved),
+            Some(other) => anyhow::bail!("Invalid resolution status: {other}"),
+        }
+    }
+
+    /// Has the oracle emitted the event? Bets on it can only be resolved afterwards.
+    pub fn event_exists(&self, event_id: &Field<N>) -> anyhow::Result<bool> {
+        let event = self.get_mapping_value(ORACLE_PROGRAM_ID, "events", &event_id.to_string())?;
+        Ok(event.is_some())
+    }
+
+    /// Fetch the height of the latest block
+    pub fn latest_height(&self) -> anyhow::Result<u32> {
+        let url = format!("{}/block/height/latest", self.endpoint);
+        Ok(ureq::get(url).call()?.body_mut().read_json()?)
+    }
+
+    /// Fetch the confirmed transactions of a block
+    pub fn transactions(&self, height: u32) -> anyhow::Result<Transactions<N>> {
+        let url = format!("{}/block/{height}/transactions", self.endpoint);
+        Ok(ureq::get(url).call()?.body_mut().read_json()?)
+    }
+
+    /// Broadcast a transaction and return its ID
+    pub fn broadcast(&self, transaction: &Transaction<N>) -> anyhow::Result<String> {
+        let url = format!("{}/transaction/broadcast", self.endpoint);
+        let transaction_id: String = ureq::post(url)
+            .send_json(transaction)?
+            .body_mut()
+            .read_json()?;
+
+        Ok(transaction_id)
+    }
+}
@@ -0,0 +1,23 @@
+//! Client for the `proto_snorkle_bet_000.aleo` betting program
+//!
+//! Mirrors the structs of the program, signs and verifies wagers the way `make_bet` checks
+//! them, and builds transactions for its transitions.
+
+mod client;
+pub use client::BetClient;
+
+mod types;
+pub use types::{Bet, Wager, WagerEntry, WagerKey};
+
+/// ID of the betting program
+pub const PROGRAM_ID: &str = "proto_snorkle_bet_000.aleo";
+
+/// ID of the oracle program whose events the bets are placed on
+pub const ORACLE_PROGRAM_ID: &str = "proto_snorkle_oracle_000.aleo";
+
+/// Whether a bet was paid out yet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BetStatus {
+    Open,
+    Resolved,
+}
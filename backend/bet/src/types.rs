@@ -0,0 +1,424 @@
+use std::str::FromStr;
+
+use rand::{CryptoRng, Rng};
+
+use serde::{Deserialize, Serialize};
+
+use snarkvm::prelude::*;
+
+/// Build an Aleo struct from its members, in declaration order
+fn to_struct<N: Network>(members: Vec<(&str, Plaintext<N>)>) -> anyhow::Result<Plaintext<N>> {
+    let members = members
+        .into_iter()
+        .map(|(name, value)| Ok((Identifier::from_str(name)?, value)))
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(Plaintext::Struct(members, Default::default()))
+}
+
+fn member<'a, N: Network>(value: &'a Plaintext<N>, name: &str) -> anyhow::Result<&'a Plaintext<N>> {
+    let Plaintext::Struct(members, _) = value else {
+        anyhow::bail!("Expected a struct but got {value}");
+    };
+
+    members
+        .get(&Identifier::from_str(name)?)
+        .ok_or_else(|| anyhow::anyhow!("Missing member `{name}`"))
+}
+
+fn literal<N: Network>(value: &Plaintext<N>, name: &str) -> anyhow::Result<Literal<N>> {
+    match member(value, name)? {
+        Plaintext::Literal(literal, _) => Ok(literal.clone()),
+        other => anyhow::bail!("Member `{name}` should be a literal, got {other}"),
+    }
+}
+
+fn field<N: Network>(value: &Plaintext<N>, name: &str) -> anyhow::Result<Field<N>> {
+    match literal(value, name)? {
+        Literal::Field(field) => Ok(field),
+        other => anyhow::bail!("Member `{name}` should be a field, got {other}"),
+    }
+}
+
+fn address<N: Network>(value: &Plaintext<N>, name: &str) -> anyhow::Result<Address<N>> {
+    match literal(value, name)? {
+        Literal::Address(address) => Ok(address),
+        other => anyhow::bail!("Member `{name}` should be an address, got {other}"),
+    }
+}
+
+fn u64_member<N: Network>(value: &Plaintext<N>, name: &str) -> anyhow::Result<u64> {
+    match literal(value, name)? {
+        Literal::U64(value) => Ok(*value),
+        other => anyhow::bail!("Member `{name}` should be a u64, got {other}"),
+    }
+}
+
+/// `BHP1024::hash_to_field`, as computed by `hash.bhp1024 ... as field`
+fn hash_to_field<N: Network>(value: &Plaintext<N>) -> anyhow::Result<Field<N>> {
+    N::hash_bhp1024(&value.to_bits_le())
+}
+
+/// Separates signed cancellations from signed wagers
+const CANCELLATION_DOMAIN: u64 = u64::from_le_bytes(*b"cancel\0\0");
+
+/// One side of a bet, signed by the bettor and handed to a facilitator
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Wager<N: Network> {
+    /// ID of the event in `proto_snorkle_oracle_000.aleo/events`
+    pub event_id: Field<N>,
+    /// Microcredits put up by the bettor
+    pub bet: u64,
+    /// Microcredits paid to the winner; the same for both sides
+    pub to_win: u64,
+    /// Microcredits of `bet` that go to the facilitator
+    pub vig: u64,
+    /// Makes the wager unique, so that it can only be used once
+    pub nonce: Field<N>,
+}
+
+impl<N: Network> Wager<N> {
+    pub fn to_plaintext(&self) -> anyhow::Result<Plaintext<N>> {
+        to_struct(vec![
+            ("event_id", Literal::Field(self.event_id).into()),
+            ("bet", Literal::U64(U64::new(self.bet)).into()),
+            ("to_win", Literal::U64(U64::new(self.to_win)).into()),
+            ("vig", Literal::U64(U64::new(self.vig)).into()),
+            ("nonce", Literal::Field(self.nonce).into()),
+        ])
+    }
+
+    pub fn from_plaintext(value: &Plaintext<N>) -> anyhow::Result<Self> {
+        Ok(Self {
+            event_id: field(value, "event_id")?,
+            bet: u64_member(value, "bet")?,
+            to_win: u64_member(value, "to_win")?,
+            vig: u64_member(value, "vig")?,
+            nonce: field(value, "nonce")?,
+        })
+    }
+
+    /// Sign the wager, so that `make_bet` accepts it on behalf of the key's owner
+    pub fn sign<R: Rng + CryptoRng>(
+        &self,
+        key: &PrivateKey<N>,
+        rng: &mut R,
+    ) -> anyhow::Result<Signature<N>> {
+        key.sign(&self.to_plaintext()?.to_fields()?, rng)
+    }
+
+    /// Check a signature the way `sign.verify` in `make_bet` does
+    pub fn verify(&self, bettor: &Address<N>, signature: &Signature<N>) -> anyhow::Result<bool> {
+        Ok(signature.verify(bettor, &self.to_plaintext()?.to_fields()?))
+    }
+
+    /// Make sure the wager is well-formed on its own
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.vig > self.bet {
+            anyhow::bail!("vig ({}) exceeds the bet ({})", self.vig, self.bet);
+        }
+        if self.bet > self.to_win {
+            anyhow::bail!("bet ({}) exceeds the payout ({})", self.bet, self.to_win);
+        }
+
+        Ok(())
+    }
+}
+
+/// Identifies a wager once it is used in a bet
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WagerKey<N: Network> {
+    pub bettor: Address<N>,
+    pub nonce: Field<N>,
+}
+
+impl<N: Network> WagerKey<N> {
+    pub fn to_plaintext(&self) -> anyhow::Result<Plaintext<N>> {
+        to_struct(vec![
+            ("bettor", Literal::Address(self.bettor).into()),
+            ("nonce", Literal::Field(self.nonce).into()),
+        ])
+    }
+
+    /// The key of the wager in the `confirmed_wagers` mapping
+    pub fn hash(&self) -> anyhow::Result<Field<N>> {
+        hash_to_field(&self.to_plaintext()?)
+    }
+
+    fn cancellation_message(&self) -> anyhow::Result<Vec<Field<N>>> {
+        Ok(vec![Field::from_u64(CANCELLATION_DOMAIN), self.hash()?])
+    }
+
+    /// Sign a request to withdraw the wager from a facilitator before it is matched
+    ///
+    /// This does not invalidate the signature on the wager itself.
+    pub fn sign_cancellation<R: Rng + CryptoRng>(
+        &self,
+        key: &PrivateKey<N>,
+        rng: &mut R,
+    ) -> anyhow::Result<Signature<N>> {
+        key.sign(&self.cancellation_message()?, rng)
+    }
+
+    /// Check that the bettor signed the cancellation
+    pub fn verify_cancellation(&self, signature: &Signature<N>) -> anyhow::Result<bool> {
+        Ok(signature.verify(&self.bettor, &self.cancellation_message()?))
+    }
+}
+
+/// Two matched wagers, as stored in the `all_bets` mapping
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bet<N: Network> {
+    pub event_id: Field<N>,
+    /// The bettor that wins if `left_is_winner` is set
+    pub bettor_one: Address<N>,
+    pub bettor_two: Address<N>,
+    pub wager_key_one: Field<N>,
+    pub wager_key_two: Field<N>,
+    pub total_to_win: u64,
+    pub total_vig: u64,
+    /// The account that submitted the bet, and collects the vig
+    pub facilitator: Address<N>,
+}
+
+impl<N: Network> Bet<N> {
+    /// Match two wagers, applying the same checks as `make_bet`
+    ///
+    /// The first wager must have plus odds, and the second one minus odds.
+    pub fn new(
+        bettor_one: Address<N>,
+        wager_one: &Wager<N>,
+        bettor_two: Address<N>,
+        wager_two: &Wager<N>,
+        facilitator: Address<N>,
+    ) -> anyhow::Result<Self> {
+        wager_one.check()?;
+        wager_two.check()?;
+
+        if wager_one.event_id != wager_two.event_id {
+            anyhow::bail!("The wagers are on different events");
+        }
+
+        let total_vig = wager_one
+            .vig
+            .checked_add(wager_two.vig)
+            .ok_or_else(|| anyhow::anyhow!("Total vig overflows"))?;
+        let total_bet = wager_one
+            .bet
+            .checked_add(wager_two.bet)
+            .ok_or_else(|| anyhow::anyhow!("Total bet overflows"))?;
+        let total_to_win = total_bet - total_vig;
+
+        if wager_one.to_win != total_to_win || wager_two.to_win != total_to_win {
+            anyhow::bail!(
+                "Both wagers must pay out the total bet minus the vig ({total_to_win}), got {} and {}",
+                wager_one.to_win,
+                wager_two.to_win
+            );
+        }
+        if wager_one.bet == 0 || wager_two.bet == 0 {
+            anyhow::bail!("Both wagers must bet a non-zero amount");
+        }
+        if total_to_win / wager_one.bet < 1 {
+            anyhow::bail!("The first wager must have plus odds");
+        }
+        if total_to_win / wager_two.bet > 1 {
+            anyhow::bail!("The second wager must have minus odds");
+        }
+
+        // The program derives both wager keys from the nonce of the first wager
+        let wager_key_one = WagerKey {
+            bettor: bettor_one,
+            nonce: wager_one.nonce,
+        }
+        .hash()?;
+        let wager_key_two = WagerKey {
+            bettor: bettor_two,
+            nonce: wager_one.nonce,
+        }
+        .hash()?;
+
+        Ok(Self {
+            event_id: wager_one.event_id,
+            bettor_one,
+            bettor_two,
+            wager_key_one,
+            wager_key_two,
+            total_to_win,
+            total_vig,
+            facilitator,
+        })
+    }
+
+    pub fn to_plaintext(&self) -> anyhow::Result<Plaintext<N>> {
+        to_struct(vec![
+            ("event_id", Literal::Field(self.event_id).into()),
+            ("bettor_one", Literal::Address(self.bettor_one).into()),
+            ("bettor_two", Literal::Address(self.bettor_two).into()),
+            ("wager_key_one", Literal::Field(self.wager_key_one).into()),
+            ("wager_key_two", Literal::Field(self.wager_key_two).into()),
+            (
+                "total_to_win",
+                Literal::U64(U64::new(self.total_to_win)).into(),
+            ),
+            ("total_vig", Literal::U64(U64::new(self.total_vig)).into()),
+            ("facilitator", Literal::Address(self.facilitator).into()),
+        ])
+    }
+
+    pub fn from_plaintext(value: &Plaintext<N>) -> anyhow::Result<Self> {
+        Ok(Self {
+            event_id: field(value, "event_id")?,
+            bettor_one: address(value, "bettor_one")?,
+            bettor_two: address(value, "bettor_two")?,
+            wager_key_one: field(value, "wager_key_one")?,
+            wager_key_two: field(value, "wager_key_two")?,
+            total_to_win: u64_member(value, "total_to_win")?,
+            total_vig: u64_member(value, "total_vig")?,
+            facilitator: address(value, "facilitator")?,
+        })
+    }
+
+    /// The key of the bet in the `all_bets` mapping
+    pub fn id(&self) -> anyhow::Result<Field<N>> {
+        hash_to_field(&self.to_plaintext()?)
+    }
+}
+
+/// A wager that was used in a bet, as stored in the `confirmed_wagers` mapping
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WagerEntry<N: Network> {
+    pub wager: Wager<N>,
+    pub bet_id: Field<N>,
+}
+
+impl<N: Network> WagerEntry<N> {
+    pub fn from_plaintext(value: &Plaintext<N>) -> anyhow::Result<Self> {
+        Ok(Self {
+            wager: Wager::from_plaintext(member(value, "wager")?)?,
+            bet_id: field(value, "bet_id")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    type N = TestnetV0;
+
+    fn wager(bet: u64, to_win: u64, vig: u64, nonce: u64) -> Wager<N> {
+        Wager {
+            event_id: Field::from_u64(7),
+            bet,
+            to_win,
+            vig,
+            nonce: Field::from_u64(nonce),
+        }
+    }
+
+    fn addresses() -> (Address<N>, Address<N>, Address<N>) {
+        let address = || Address::try_from(PrivateKey::<N>::new(&mut OsRng).unwrap()).unwrap();
+        (address(), address(), address())
+    }
+
+    #[test]
+    fn signed_wagers_verify_for_their_bettor_only() {
+        let key = PrivateKey::<N>::new(&mut OsRng).unwrap();
+        let bettor = Address::try_from(&key).unwrap();
+        let (other, _, _) = addresses();
+        let wager = wager(45, 95, 5, 1);
+
+        let signature = wager.sign(&key, &mut OsRng).unwrap();
+        assert!(wager.verify(&bettor, &signature).unwrap());
+        assert!(!wager.verify(&other, &signature).unwrap());
+
+        let tampered = Wager { bet: 46, ..wager };
+        assert!(!tampered.verify(&bettor, &signature).unwrap());
+    }
+
+    #[test]
+    fn cancellations_verify_for_their_bettor_only() {
+        let key = PrivateKey::<N>::new(&mut OsRng).unwrap();
+        let (other, _, _) = addresses();
+        let wager_key = WagerKey {
+            bettor: Address::try_from(&key).unwrap(),
+            nonce: Field::from_u64(1),
+        };
+
+        let signature = wager_key.sign_cancellation(&key, &mut OsRng).unwrap();
+        assert!(wager_key.verify_cancellation(&signature).unwrap());
+
+        let forged = WagerKey {
+            bettor: other,
+            ..wager_key.clone()
+        };
+        assert!(!forged.verify_cancellation(&signature).unwrap());
+
+        // A signed wager is not a signed cancellation
+        let wager_signature = wager(45, 95, 5, 1).sign(&key, &mut OsRng).unwrap();
+        assert!(!wager_key.verify_cancellation(&wager_signature).unwrap());
+    }
+
+    #[test]
+    fn even_wagers_make_a_bet() {
+        let (one, two, facilitator) = addresses();
+        let bet = Bet::new(
+            one,
+            &wager(45, 95, 5, 1),
+            two,
+            &wager(60, 95, 5, 2),
+            facilitator,
+        )
+        .unwrap();
+
+        assert_eq!(bet.total_to_win, 95);
+        assert_eq!(bet.total_vig, 10);
+        assert_eq!(bet.facilitator, facilitator);
+        // Both keys use the nonce of the first wager, like `make_bet`
+        let key = |bettor| {
+            WagerKey {
+                bettor,
+                nonce: Field::from_u64(1),
+            }
+            .hash()
+            .unwrap()
+        };
+        assert_eq!(bet.wager_key_one, key(one));
+        assert_eq!(bet.wager_key_two, key(two));
+
+        let roundtrip = Bet::from_plaintext(&bet.to_plaintext().unwrap()).unwrap();
+        assert_eq!(roundtrip, bet);
+        assert_eq!(roundtrip.id().unwrap(), bet.id().unwrap());
+    }
+
+    #[test]
+    fn bets_apply_the_checks_of_make_bet() {
+        let (one, two, facilitator) = addresses();
+        let new =
+            |first: Wager<N>, second: Wager<N>| Bet::new(one, &first, two, &second, facilitator);
+
+        // vig exceeds the bet
+        assert!(new(wager(45, 95, 46, 1), wager(60, 95, 5, 2)).is_err());
+        // bet exceeds the payout
+        assert!(new(wager(45, 95, 5, 1), wager(100, 95, 5, 2)).is_err());
+        // different events
+        let other_event = Wager {
+            event_id: Field::from_u64(8),
+            ..wager(60, 95, 5, 2)
+        };
+        assert!(new(wager(45, 95, 5, 1), other_event).is_err());
+        // payouts differ from the total bet minus the vig
+        assert!(new(wager(45, 96, 5, 1), wager(60, 96, 5, 2)).is_err());
+        assert!(new(wager(45, 95, 5, 1), wager(60, 94, 5, 2)).is_err());
+        // minus odds on the first side, plus odds on the second
+        assert!(new(wager(60, 95, 5, 1), wager(45, 95, 5, 2)).is_err());
+        // nothing at stake
+        assert!(new(wager(0, 0, 0, 1), wager(0, 0, 0, 2)).is_err());
+        // overflowing totals
+        assert!(new(wager(u64::MAX, u64::MAX, 0, 1), wager(1, u64::MAX, 0, 2)).is_err());
+    }
+}
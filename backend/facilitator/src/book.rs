@@ -0,0 +1,462 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Local};
+
+use serde::{Deserialize, Serialize};
+
+use snarkvm::prelude::{Address, Signature};
+
+use snorkle_bet::{Bet, Wager};
+
+use crate::CurrentNetwork;
+
+/// Which bettor a wager is for
+///
+/// `make_bet` pays `bettor_one` if the event's `left_is_winner` is set, and requires that
+/// `bettor_one` has plus odds and `bettor_two` minus odds. The signed wager does not say
+/// which side it is on, so bettors declare it when submitting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    /// Wins if the left side wins; plus odds
+    One,
+    /// Wins if the right side wins; minus odds
+    Two,
+}
+
+impl Side {
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::One => Self::Two,
+            Self::Two => Self::One,
+        }
+    }
+}
+
+/// A signed wager waiting for a counterparty
+#[derive(Clone, Debug)]
+pub struct Order {
+    /// The hash of the wager key
+    pub id: String,
+    pub bettor: Address<CurrentNetwork>,
+    pub side: Side,
+    pub wager: Wager<CurrentNetwork>,
+    pub signature: Signature<CurrentNetwork>,
+    pub time: DateTime<Local>,
+}
+
+/// An order as shown in the order book; without the signature, so that nobody else can
+/// submit it
+#[derive(Clone, Debug, Serialize)]
+pub struct OrderView {
+    pub id: String,
+    pub bettor: String,
+    pub side: Side,
+    pub event_id: String,
+    pub bet: u64,
+    pub to_win: u64,
+    pub vig: u64,
+    pub time: DateTime<Local>,
+}
+
+impl From<&Order> for OrderView {
+    fn from(order: &Order) -> Self {
+        Self {
+            id: order.id.clone(),
+            bettor: order.bettor.to_string(),
+            side: order.side,
+            event_id: order.wager.event_id.to_string(),
+            bet: order.wager.bet,
+            to_win: order.wager.to_win,
+            vig: order.wager.vig,
+            time: order.time,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum MatchStatus {
+    /// `make_bet` is being proven
+    Pending,
+    Broadcast {
+        transaction_id: String,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+/// Two wagers that were matched into a bet
+#[derive(Clone, Debug, Serialize)]
+pub struct Match {
+    pub bet_id: String,
+    pub event_id: String,
+    /// IDs of the wagers of `bettor_one` and `bettor_two`
+    pub wagers: [String; 2],
+    /// Keys the bet stores the wagers under in `confirmed_wagers`; both are derived from the
+    /// nonce of the first wager, so the second one differs from the ID of its wager
+    pub wager_keys: [String; 2],
+    pub time: DateTime<Local>,
+    #[serde(flatten)]
+    pub status: MatchStatus,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum WagerStatus {
+    Open,
+    Matched {
+        bet_id: String,
+    },
+    Cancelled,
+    /// The wager was used in a bet by somebody else, and can never be matched
+    Failed {
+        error: String,
+    },
+}
+
+/// Open orders and the outcome of every wager the facilitator has seen
+#[derive(Default)]
+pub struct OrderBook {
+    /// Open orders, oldest first
+    orders: Vec<Order>,
+    /// Status of every wager, by ID; IDs cannot be reused
+    statuses: HashMap<String, WagerStatus>,
+    matches: Vec<Match>,
+    /// Orders of matches whose bet is being proven, by bet ID
+    pending: HashMap<String, [Order; 2]>,
+    /// Wager keys of all matches that did not fail; `make_bet` refuses to use a key twice
+    used_keys: HashSet<String>,
+}
+
+impl OrderBook {
+    pub fn status(&self, id: &str) -> Option<&WagerStatus> {
+        self.statuses.get(id)
+    }
+
+    pub fn order(&self, id: &str) -> Option<&Order> {
+        self.orders.iter().find(|order| order.id == id)
+    }
+
+    /// Open orders, optionally only those on one event
+    pub fn orders(&self, event_id: Option<&str>) -> Vec<OrderView> {
+        self.orders
+            .iter()
+            .filter(|order| event_id.is_none_or(|id| order.wager.event_id.to_string() == id))
+            .map(OrderView::from)
+            .collect()
+    }
+
+    pub fn matches(&self) -> &[Match] {
+        &self.matches
+    }
+
+    /// Microcredits the bettor has put up in open orders and in bets that are being proven
+    pub fn reserved(&self, bettor: &Address<CurrentNetwork>) -> u64 {
+        self.orders
+            .iter()
+            .chain(self.pending.values().flatten())
+            .filter(|order| order.bettor == *bettor)
+            .map(|order| order.wager.bet)
+            .sum()
+    }
+
+    /// Match the order with the oldest compatible opposing order, or add it to the book
+    ///
+    /// Returns the match and its orders, ordered as `make_bet` expects.
+    pub fn place(
+        &mut self,
+        order: Order,
+        facilitator: Address<CurrentNetwork>,
+    ) -> anyhow::Result<Option<(Match, [Order; 2])>> {
+        if self.statuses.contains_key(&order.id) {
+            anyhow::bail!("wager {} was already submitted", order.id);
+        }
+
+        let counterparty = self.orders.iter().enumerate().find_map(|(index, other)| {
+            if other.side != order.side.opposite() || other.bettor == order.bettor {
+                return None;
+            }
+
+            let (one, two) = match order.side {
+                Side::One => (&order, other),
+                Side::Two => (other, &order),
+            };
+            let bet = Bet::new(one.bettor, &one.wager, two.bettor, &two.wager, facilitator).ok()?;
+
+            // Another match may already use the key of the second wager
+            let keys = [bet.wager_key_one, bet.wager_key_two];
+            if keys
+                .iter()
+                .any(|key| self.used_keys.contains(&key.to_string()))
+            {
+                return None;
+            }
+
+            Some((index, bet))
+        });
+
+        let Some((index, bet)) = counterparty else {
+            self.statuses.insert(order.id.clone(), WagerStatus::Open);
+            self.orders.push(order);
+            return Ok(None);
+        };
+
+        let other = self.orders.remove(index);
+        let orders = match order.side {
+            Side::One => [order, other],
+            Side::Two => [other, order],
+        };
+
+        let bet_id = bet.id()?.to_string();
+        for order in &orders {
+            self.statuses.insert(
+                order.id.clone(),
+                WagerStatus::Matched {
+                    bet_id: bet_id.clone(),
+                },
+            );
+        }
+
+        let wager_keys = [bet.wager_key_one.to_string(), bet.wager_key_two.to_string()];
+        self.used_keys.extend(wager_keys.iter().cloned());
+
+        let matched = Match {
+            bet_id: bet_id.clone(),
+            event_id: bet.event_id.to_string(),
+            wagers: [orders[0].id.clone(), orders[1].id.clone()],
+            wager_keys,
+            time: Local::now(),
+            status: MatchStatus::Pending,
+        };
+        self.matches.push(matched.clone());
+        self.pending.insert(bet_id, orders.clone());
+
+        Ok(Some((matched, orders)))
+    }
+
+    /// Remove an open order from the book
+    pub fn cancel(&mut self, id: &str) -> bool {
+        let Some(index) = self.orders.iter().position(|order| order.id == id) else {
+            return false;
+        };
+
+        self.orders.remove(index);
+        self.statuses.insert(id.to_string(), WagerStatus::Cancelled);
+        true
+    }
+
+    /// Record that the bet of a match was broadcast
+    pub fn broadcast_match(&mut self, bet_id: &str, transaction_id: String) {
+        self.pending.remove(bet_id);
+        if let Some(matched) = self.matches.iter_mut().find(|m| m.bet_id == bet_id) {
+            matched.status = MatchStatus::Broadcast { transaction_id };
+        }
+    }
+
+    /// Record that the bet of a match could not be submitted
+    ///
+    /// Orders whose wager key is in `used` were used in a bet on chain, and are marked
+    /// failed. The others go back into the book, to be matched again or cancelled.
+    pub fn fail_match(&mut self, bet_id: &str, error: String, used: &[String]) {
+        let Some(matched) = self.matches.iter_mut().find(|m| m.bet_id == bet_id) else {
+            return;
+        };
+        matched.status = MatchStatus::Failed {
+            error: error.clone(),
+        };
+        for key in &matched.wager_keys {
+            self.used_keys.remove(key);
+        }
+
+        let Some(orders) = self.pending.remove(bet_id) else {
+            return;
+        };
+        for order in orders {
+            if used.contains(&order.id) {
+                self.statuses.insert(
+                    order.id.clone(),
+                    WagerStatus::Failed {
+                        error: error.clone(),
+                    },
+                );
+                continue;
+            }
+
+            self.statuses.insert(order.id.clone(), WagerStatus::Open);
+            // Keep the book ordered oldest first
+            let index = self
+                .orders
+                .partition_point(|other| other.time <= order.time);
+            self.orders.insert(index, order);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use snarkvm::prelude::{Field, PrivateKey};
+
+    use snorkle_bet::WagerKey;
+
+    use super::*;
+
+    struct Bettor {
+        key: PrivateKey<CurrentNetwork>,
+        address: Address<CurrentNetwork>,
+    }
+
+    fn bettor() -> Bettor {
+        let key = PrivateKey::new(&mut OsRng).unwrap();
+        let address = Address::try_from(&key).unwrap();
+        Bettor { key, address }
+    }
+
+    fn order(bettor: &Bettor, side: Side, bet: u64, nonce: u64) -> Order {
+        let wager = Wager {
+            event_id: Field::from_u64(7),
+            bet,
+            to_win: 95,
+            vig: 5,
+            nonce: Field::from_u64(nonce),
+        };
+        let id = WagerKey {
+            bettor: bettor.address,
+            nonce: wager.nonce,
+        }
+        .hash()
+        .unwrap()
+        .to_string();
+
+        Order {
+            id,
+            bettor: bettor.address,
+            side,
+            signature: wager.sign(&bettor.key, &mut OsRng).unwrap(),
+            wager,
+            time: Local::now(),
+        }
+    }
+
+    fn ids(book: &OrderBook) -> Vec<String> {
+        book.orders(None)
+            .into_iter()
+            .map(|order| order.id)
+            .collect()
+    }
+
+    #[test]
+    fn pending_matches_stay_reserved_until_broadcast() {
+        let (one, two, facilitator) = (bettor(), bettor(), bettor().address);
+        let mut book = OrderBook::default();
+
+        assert!(
+            book.place(order(&one, Side::One, 45, 1), facilitator)
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(book.reserved(&one.address), 45);
+
+        let (matched, orders) = book
+            .place(order(&two, Side::Two, 60, 2), facilitator)
+            .unwrap()
+            .unwrap();
+        assert_eq!(matched.wagers, [orders[0].id.clone(), orders[1].id.clone()]);
+        assert!(book.orders(None).is_empty());
+        assert_eq!(book.reserved(&one.address), 45);
+        assert_eq!(book.reserved(&two.address), 60);
+
+        book.broadcast_match(&matched.bet_id, "at1".to_string());
+        assert_eq!(book.reserved(&one.address), 0);
+        assert_eq!(book.reserved(&two.address), 0);
+        assert!(matches!(
+            book.matches()[0].status,
+            MatchStatus::Broadcast { .. }
+        ));
+    }
+
+    #[test]
+    fn failed_matches_return_their_orders_to_the_book() {
+        let (one, two, facilitator) = (bettor(), bettor(), bettor().address);
+        let mut book = OrderBook::default();
+
+        let first = order(&one, Side::One, 45, 1);
+        let second = order(&two, Side::Two, 60, 2);
+        let (first_id, second_id) = (first.id.clone(), second.id.clone());
+        book.place(first, facilitator).unwrap();
+        let (matched, _) = book.place(second, facilitator).unwrap().unwrap();
+
+        book.fail_match(&matched.bet_id, "out of fees".to_string(), &[]);
+        assert_eq!(ids(&book), [first_id.clone(), second_id.clone()]);
+        assert!(matches!(book.status(&first_id), Some(WagerStatus::Open)));
+        assert!(matches!(
+            book.matches()[0].status,
+            MatchStatus::Failed { .. }
+        ));
+        // The orders can be cancelled again
+        assert!(book.cancel(&second_id));
+        assert_eq!(book.reserved(&one.address), 45);
+        assert_eq!(book.reserved(&two.address), 0);
+    }
+
+    #[test]
+    fn orders_whose_key_was_used_on_chain_are_marked_failed() {
+        let (one, two, facilitator) = (bettor(), bettor(), bettor().address);
+        let mut book = OrderBook::default();
+
+        book.place(order(&one, Side::One, 45, 1), facilitator)
+            .unwrap();
+        let (matched, orders) = book
+            .place(order(&two, Side::Two, 60, 2), facilitator)
+            .unwrap()
+            .unwrap();
+
+        // The key of the first wager is its ID
+        assert_eq!(matched.wager_keys[0], orders[0].id);
+        book.fail_match(
+            &matched.bet_id,
+            "already used".to_string(),
+            &[matched.wager_keys[0].clone()],
+        );
+        assert_eq!(ids(&book), [orders[1].id.clone()]);
+        assert!(matches!(
+            book.status(&orders[0].id),
+            Some(WagerStatus::Failed { .. })
+        ));
+        assert_eq!(book.reserved(&one.address), 0);
+    }
+
+    #[test]
+    fn wager_keys_are_not_used_twice() {
+        let (one, other, two, facilitator) = (bettor(), bettor(), bettor(), bettor().address);
+        let mut book = OrderBook::default();
+
+        // Both bettors on side one pick the same nonce; a bettor on side two that matches
+        // both would get the same wager key twice
+        book.place(order(&one, Side::One, 45, 1), facilitator)
+            .unwrap();
+        let other_order = order(&other, Side::One, 45, 1);
+        let other_id = other_order.id.clone();
+        book.place(other_order, facilitator).unwrap();
+
+        let (matched, _) = book
+            .place(order(&two, Side::Two, 60, 2), facilitator)
+            .unwrap()
+            .unwrap();
+        let second = order(&two, Side::Two, 60, 3);
+        let second_id = second.id.clone();
+        assert!(book.place(second, facilitator).unwrap().is_none());
+        assert_eq!(ids(&book), [other_id.clone(), second_id.clone()]);
+
+        // Once the first match failed, its keys are free again
+        book.fail_match(&matched.bet_id, "out of fees".to_string(), &[]);
+        let (_, orders) = book
+            .place(order(&two, Side::Two, 60, 4), facilitator)
+            .unwrap()
+            .unwrap();
+        assert_eq!(orders[0].bettor, one.address);
+    }
+}
@@ -0,0 +1,413 @@
+//! Matches signed wagers into bets of `proto_snorkle_bet_000.aleo`
+//!
+//! Bettors submit signed wagers over HTTP. The facilitator checks them, matches each one
+//! with the oldest compatible opposing wager on the same event, and submits `make_bet`,
+//! collecting the vig of both wagers.
+
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query},
+    http::StatusCode,
+    routing::get,
+};
+
+use clap::Parser;
+
+use chrono::Local;
+
+use serde::{Deserialize, Serialize};
+
+use snarkvm::prelude::{Address, Field, PrivateKey, Signature, TestnetV0};
+
+use snorkle_bet::{BetClient, Wager, WagerKey};
+use snorkle_oracle_interface::FeeOptions;
+
+mod book;
+use book::{Match, Order, OrderBook, OrderView, Side, WagerStatus};
+
+type CurrentNetwork = TestnetV0;
+
+/// Command line arguments of the facilitator
+#[derive(Debug, Parser)]
+#[command(version, about = "Matches signed wagers into bets", long_about = None)]
+struct Args {
+    /// Private key that submits bets and collects the vig
+    #[arg(long, env = "SNORKLE_FACILITATOR_PRIVATE_KEY", hide_env_values = true)]
+    private_key: String,
+
+    /// REST endpoint of the node, including the network
+    #[arg(
+        long,
+        env = "SNORKLE_NODE_ENDPOINT",
+        default_value = "https://api.explorer.provable.com/v1/testnet"
+    )]
+    endpoint: String,
+
+    /// Address the HTTP server binds to
+    #[arg(long, env = "SNORKLE_FACILITATOR_BIND", default_value = "0.0.0.0:3100")]
+    bind: SocketAddr,
+
+    /// Additional fee (in microcredits) paid for every bet
+    #[arg(long, env = "SNORKLE_PRIORITY_FEE", default_value_t = 0)]
+    priority_fee: u64,
+
+    /// Refuse to submit bets with a higher estimated fee (in microcredits)
+    #[arg(long, env = "SNORKLE_MAX_FEE")]
+    max_fee: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WagerRequest {
+    bettor: Address<CurrentNetwork>,
+    side: Side,
+    wager: Wager<CurrentNetwork>,
+    /// Signature of the bettor on the wager, as checked by `make_bet`
+    signature: Signature<CurrentNetwork>,
+}
+
+#[derive(Debug, Serialize)]
+struct WagerResponse {
+    id: String,
+    #[serde(flatten)]
+    status: WagerStatus,
+}
+
+#[derive(Debug, Deserialize)]
+struct CancelRequest {
+    /// Signature of the bettor on the cancellation (see `WagerKey::sign_cancellation`)
+    signature: Signature<CurrentNetwork>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BookQuery {
+    event_id: Option<String>,
+}
+
+type HandlerError = (StatusCode, String);
+
+fn bad_request(msg: impl ToString) -> HandlerError {
+    (StatusCode::BAD_REQUEST, msg.to_string())
+}
+
+fn internal_error(err: anyhow::Error) -> HandlerError {
+    log::error!("{err:#}");
+    (StatusCode::INTERNAL_SERVER_ERROR, format!("{err:#}"))
+}
+
+struct Facilitator {
+    client: Arc<BetClient<CurrentNetwork>>,
+    address: Address<CurrentNetwork>,
+    fee: FeeOptions,
+    book: Mutex<OrderBook>,
+    /// Bets are proven one at a time
+    proving: tokio::sync::Mutex<()>,
+}
+
+impl Facilitator {
+    /// Run a blocking call of the client on the blocking thread pool
+    async fn blocking<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&BetClient<CurrentNetwork>) -> anyhow::Result<T> + Send + 'static,
+    ) -> anyhow::Result<T> {
+        let client = self.client.clone();
+        tokio::task::spawn_blocking(move || f(&client)).await?
+    }
+
+    /// Check the wager on its own, before looking at the chain
+    fn validate(request: &WagerRequest) -> Result<String, HandlerError> {
+        let wager = &request.wager;
+
+        wager.check().map_err(bad_request)?;
+        if wager.bet == 0 {
+            return Err(bad_request("bet must not be zero"));
+        }
+        if request.side == Side::Two && wager.to_win / wager.bet > 1 {
+            return Err(bad_request(
+                "wagers on side two must have minus odds (to_win below twice the bet)",
+            ));
+        }
+
+        let valid = wager
+            .verify(&request.bettor, &request.signature)
+            .map_err(internal_error)?;
+        if !valid {
+            return Err(bad_request("invalid signature"));
+        }
+
+        let key = WagerKey {
+            bettor: request.bettor,
+            nonce: wager.nonce,
+        };
+        Ok(key.hash().map_err(internal_error)?.to_string())
+    }
+
+    async fn submit_handler(
+        self: Arc<Self>,
+        Json(request): Json<WagerRequest>,
+    ) -> Result<(StatusCode, Json<WagerResponse>), HandlerError> {
+        let id = Self::validate(&request)?;
+
+        if self.book.lock().unwrap().status(&id).is_some() {
+            return Err((
+                StatusCode::CONFLICT,
+                format!("wager {id} was already submitted"),
+            ));
+        }
+
+        let event_id = request.wager.event_id;
+        let bettor = request.bettor;
+        let wager_key = Field::from_str(&id).map_err(internal_error)?;
+        let (emitted, used, balance) = self
+            .blocking(move |client| {
+                Ok((
+                    client.event_exists(&event_id)?,
+                    client.confirmed_wager(&wager_key)?.is_some(),
+                    client.balance(&bettor)?,
+                ))
+            })
+            .await
+            .map_err(|err| {
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    format!("Failed to query the chain: {err:#}"),
+                )
+            })?;
+
+        if emitted {
+            return Err((
+                StatusCode::CONFLICT,
+                format!("event {event_id} was already emitted"),
+            ));
+        }
+        if used {
+            return Err((
+                StatusCode::CONFLICT,
+                format!("wager {id} was already used in a bet"),
+            ));
+        }
+
+        let order = Order {
+            id: id.clone(),
+            bettor,
+            side: request.side,
+            wager: request.wager,
+            signature: request.signature,
+            time: Local::now(),
+        };
+
+        let placed = {
+            let mut book = self.book.lock().unwrap();
+
+            // Open orders of the bettor, and those in bets that are not broadcast yet, must be
+            // covered by their balance in the program
+            let reserved = book.reserved(&bettor);
+            if reserved.saturating_add(order.wager.bet) > balance {
+                return Err(bad_request(format!(
+                    "balance of {balance} microcredits does not cover pending wagers of {reserved} and this bet of {}",
+                    order.wager.bet
+                )));
+            }
+
+            book.place(order, self.address)
+                .map_err(|err| (StatusCode::CONFLICT, err.to_string()))?
+        };
+
+        let status = match placed {
+            None => {
+                log::info!("Added wager {id} to the book");
+                WagerStatus::Open
+            }
+            Some((matched, orders)) => {
+                log::info!(
+                    "Matched wagers {} and {} into bet {}",
+                    matched.wagers[0],
+                    matched.wagers[1],
+                    matched.bet_id
+                );
+                let bet_id = matched.bet_id.clone();
+                tokio::spawn(self.clone().make_bet(matched, orders));
+                WagerStatus::Matched { bet_id }
+            }
+        };
+
+        Ok((StatusCode::CREATED, Json(WagerResponse { id, status })))
+    }
+
+    /// Prove and broadcast `make_bet` for a match
+    async fn make_bet(self: Arc<Self>, matched: Match, orders: [Order; 2]) {
+        let _guard = self.proving.lock().await;
+
+        // The key of the second wager is derived from the nonce of the first one, so it can
+        // only be checked against the keys used on chain once the wagers are matched
+        let keys = matched.wager_keys.clone();
+        let used = self
+            .blocking(move |client| {
+                let mut used = vec![];
+                for key in keys {
+                    let field = Field::<CurrentNetwork>::from_str(&key)?;
+                    if client.confirmed_wager(&field)?.is_some() {
+                        used.push(key);
+                    }
+                }
+                Ok(used)
+            })
+            .await;
+
+        let result = match used {
+            Ok(used) if !used.is_empty() => Err((
+                anyhow::anyhow!("wager keys {} were already used in a bet", used.join(", ")),
+                used,
+            )),
+            Ok(_) => {
+                let fee = self.fee.clone();
+                let [one, two] = orders;
+                self.blocking(move |client| {
+                    let (transaction, _) = client.make_bet(
+                        one.bettor,
+                        &one.wager,
+                        one.signature,
+                        two.bettor,
+                        &two.wager,
+                        two.signature,
+                        &fee,
+                    )?;
+                    client.broadcast(&transaction)
+                })
+                .await
+                .map_err(|err| (err, vec![]))
+            }
+            Err(err) => Err((err, vec![])),
+        };
+
+        let mut book = self.book.lock().unwrap();
+        match result {
+            Ok(transaction_id) => {
+                log::info!(
+                    "Broadcast bet {} in transaction {transaction_id}",
+                    matched.bet_id
+                );
+                book.broadcast_match(&matched.bet_id, transaction_id);
+            }
+            Err((err, used)) => {
+                log::error!("Failed to submit bet {}: {err:#}", matched.bet_id);
+                book.fail_match(&matched.bet_id, format!("{err:#}"), &used);
+            }
+        }
+    }
+
+    async fn status_handler(
+        &self,
+        Path(id): Path<String>,
+    ) -> Result<Json<WagerResponse>, HandlerError> {
+        let book = self.book.lock().unwrap();
+        let Some(status) = book.status(&id) else {
+            return Err((StatusCode::NOT_FOUND, format!("unknown wager {id}")));
+        };
+
+        Ok(Json(WagerResponse {
+            id,
+            status: status.clone(),
+        }))
+    }
+
+    async fn cancel_handler(
+        &self,
+        Path(id): Path<String>,
+        Json(request): Json<CancelRequest>,
+    ) -> Result<Json<WagerResponse>, HandlerError> {
+        let mut book = self.book.lock().unwrap();
+
+        let Some(order) = book.order(&id) else {
+            return match book.status(&id) {
+                Some(_) => Err((StatusCode::CONFLICT, format!("wager {id} is not open"))),
+                None => Err((StatusCode::NOT_FOUND, format!("unknown wager {id}"))),
+            };
+        };
+
+        let key = WagerKey {
+            bettor: order.bettor,
+            nonce: order.wager.nonce,
+        };
+        if !key
+            .verify_cancellation(&request.signature)
+            .map_err(internal_error)?
+        {
+            return Err((StatusCode::FORBIDDEN, "invalid signature".to_string()));
+        }
+
+        book.cancel(&id);
+        log::info!("Cancelled wager {id}");
+
+        Ok(Json(WagerResponse {
+            id,
+            status: WagerStatus::Cancelled,
+        }))
+    }
+
+    async fn book_handler(&self, Query(query): Query<BookQuery>) -> Json<Vec<OrderView>> {
+        Json(self.book.lock().unwrap().orders(query.event_id.as_deref()))
+    }
+
+    async fn matches_handler(&self) -> Json<Vec<Match>> {
+        Json(self.book.lock().unwrap().matches().to_vec())
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::init();
+
+    let args = Args::parse();
+
+    let key = PrivateKey::<CurrentNetwork>::from_str(&args.private_key)
+        .with_context(|| "Invalid private key")?;
+    let client = BetClient::new(key, &args.endpoint)?;
+    let address = client.address()?;
+
+    let obj = Arc::new(Facilitator {
+        client: Arc::new(client),
+        address,
+        fee: FeeOptions {
+            priority_fee: args.priority_fee,
+            fee_record: None,
+            max_fee: args.max_fee,
+        },
+        book: Default::default(),
+        proving: Default::default(),
+    });
+    let obj1 = obj.clone();
+    let obj2 = obj.clone();
+    let obj3 = obj.clone();
+    let obj4 = obj.clone();
+    let obj5 = obj.clone();
+
+    let app = Router::new()
+        .route(
+            "/wagers",
+            axum::routing::post(async move |payload| obj1.clone().submit_handler(payload).await),
+        )
+        .route(
+            "/wagers/{id}",
+            get(async move |id| obj2.status_handler(id).await)
+                .delete(async move |id, payload| obj3.cancel_handler(id, payload).await),
+        )
+        .route(
+            "/book",
+            get(async move |query| obj4.book_handler(query).await),
+        )
+        .route("/matches", get(async move || obj5.matches_handler().await));
+
+    let listener = tokio::net::TcpListener::bind(args.bind)
+        .await
+        .with_context(|| format!("Failed to bind to {}", args.bind))?;
+    log::info!("Facilitator {address} listening at http://{}", args.bind);
+
+    axum::serve(listener, app).await?;
+    Ok(())
+}
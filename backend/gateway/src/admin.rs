@@ -0,0 +1,53 @@
+use axum::{
+    Json,
+    extract::Query,
+    http::{HeaderMap, StatusCode},
+};
+
+use serde::Deserialize;
+
+use crate::Gateway;
+use crate::config::{Config, Scope};
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterParams {
+    /// The program to register with; the default program if not set
+    program: Option<String>,
+}
+
+impl Gateway {
+    /// Register the oracle with a program
+    ///
+    /// Returns the transaction id of the registration
+    pub async fn admin_register_handler(
+        &self,
+        headers: HeaderMap,
+        Query(params): Query<RegisterParams>,
+    ) -> Result<Json<String>, (StatusCode, String)> {
+        let client = self
+            .authorize(&headers, Scope::Admin)
+            .map_err(|status| (status, status.to_string()))?;
+        let program = self.resolve_program(params.program.as_deref())?;
+        log::info!(
+            "Registration requested by `{}`",
+            client.as_deref().unwrap_or("anonymous")
+        );
+
+        match self.register(&program).await {
+            Ok(transaction_id) => Ok(Json(transaction_id)),
+            Err(err) => {
+                log::error!("Failed to register oracle with {}: {err}", program.id);
+                Err((StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+            }
+        }
+    }
+
+    /// Show the active configuration, without secrets
+    pub async fn admin_config_handler(
+        &self,
+        headers: HeaderMap,
+    ) -> Result<Json<Config>, StatusCode> {
+        self.authorize(&headers, Scope::Admin)?;
+        Ok(Json(self.config.redacted()))
+    }
+}
@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use axum::http::{HeaderMap, StatusCode, header};
+
+use chrono::{NaiveDate, Utc};
+
+use snorkle_oracle_interface::constant_time_eq;
+
+use crate::Gateway;
+use crate::config::{ApiKeyConfig, AuthConfig, Scope};
+use crate::metrics;
+
+/// Usage of a single API key
+struct Usage {
+    /// Available requests in the token bucket
+    tokens: f64,
+    last_refill: Instant,
+    /// The (UTC) day the quota counter belongs to
+    day: NaiveDate,
+    requests_today: u64,
+}
+
+/// Tracks rate limits and quotas of all API keys
+#[derive(Default)]
+pub struct RateLimiter {
+    usage: Mutex<HashMap<String, Usage>>,
+}
+
+impl RateLimiter {
+    /// Account for a new request by `key` that counts as `cost` requests
+    ///
+    /// Returns the reason if the request exceeds the key's limits.
+    fn check(&self, key: &ApiKeyConfig, cost: u32) -> Result<(), &'static str> {
+        // Such a request would never be allowed, however long the client waits
+        if key.rate_limit.is_some_and(|limit| cost > limit)
+            || key.daily_quota.is_some_and(|quota| u64::from(cost) > quota)
+        {
+            return Err("too_large");
+        }
+
+        let now = Instant::now();
+        let today = Utc::now().date_naive();
+
+        let mut usage = self.usage.lock().unwrap();
+        let usage = usage.entry(key.name.clone()).or_insert_with(|| Usage {
+            tokens: key.rate_limit.unwrap_or_default() as f64,
+            last_refill: now,
+            day: today,
+            requests_today: 0,
+        });
+
+        if usage.day != today {
+            usage.day = today;
+            usage.requests_today = 0;
+        }
+
+        if let Some(quota) = key.daily_quota
+            && usage.requests_today + u64::from(cost) > quota
+        {
+            return Err("quota_exceeded");
+        }
+
+        if let Some(rate_limit) = key.rate_limit {
+            // Refill the bucket at `rate_limit` tokens per minute, up to a burst of `rate_limit`
+            let elapsed = now.duration_since(usage.last_refill).as_secs_f64();
+            let capacity = rate_limit as f64;
+            usage.tokens = (usage.tokens + elapsed * capacity / 60.0).min(capacity);
+            usage.last_refill = now;
+
+            if usage.tokens < f64::from(cost) {
+                return Err("rate_limited");
+            }
+            usage.tokens -= f64::from(cost);
+        }
+
+        usage.requests_today += u64::from(cost);
+        Ok(())
+    }
+}
+
+/// Check the `Authorization: Bearer <key>` header of a request that requires `scope` and
+/// counts as `cost` requests
+///
+/// Returns the name of the client, or `None` if the operator opted into open mode. Rejections
+/// carry the reason that is reported in the metrics.
+fn authenticate(
+    auth: &AuthConfig,
+    rate_limiter: &RateLimiter,
+    headers: &HeaderMap,
+    scope: Scope,
+    cost: u32,
+) -> Result<Option<String>, (&'static str, StatusCode)> {
+    if auth.keys.is_empty() {
+        if auth.open {
+            return Ok(None);
+        }
+        log::warn!("Rejected request: no API keys are configured");
+        return Err(("no_keys", StatusCode::UNAUTHORIZED));
+    }
+
+    let Some(token) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        log::warn!("Rejected request without API key");
+        return Err(("missing_key", StatusCode::UNAUTHORIZED));
+    };
+
+    let Some(key) = auth
+        .keys
+        .iter()
+        .find(|entry| constant_time_eq(entry.key.as_bytes(), token.trim().as_bytes()))
+    else {
+        log::warn!("Rejected request with unknown API key");
+        return Err(("invalid_key", StatusCode::UNAUTHORIZED));
+    };
+
+    if !key.scope.allows(scope) {
+        log::warn!(
+            "Rejected request by `{}`: missing {scope:?} scope",
+            key.name
+        );
+        return Err(("forbidden", StatusCode::FORBIDDEN));
+    }
+
+    if let Err(reason) = rate_limiter.check(key, cost) {
+        log::warn!("Rejected request by `{}`: {reason}", key.name);
+        let status = match reason {
+            "too_large" => StatusCode::BAD_REQUEST,
+            _ => StatusCode::TOO_MANY_REQUESTS,
+        };
+        return Err((reason, status));
+    }
+
+    Ok(Some(key.name.clone()))
+}
+
+impl Gateway {
+    /// Check the `Authorization: Bearer <key>` header against the configured API keys,
+    /// and enforce the key's scope, rate limit, and quota
+    ///
+    /// Returns the name of the client, or `None` if authentication is disabled with
+    /// `auth.open`. Without any keys, every request is rejected otherwise.
+    pub fn authorize(
+        &self,
+        headers: &HeaderMap,
+        scope: Scope,
+    ) -> Result<Option<String>, StatusCode> {
+        self.authorize_weighted(headers, scope, 1)
+    }
+
+    /// Like [`Self::authorize`], but the request counts as `cost` requests against the rate
+    /// limit and quota
+    pub fn authorize_weighted(
+        &self,
+        headers: &HeaderMap,
+        scope: Scope,
+        cost: u32,
+    ) -> Result<Option<String>, StatusCode> {
+        authenticate(&self.config.auth, &self.rate_limiter, headers, scope, cost).map_err(
+            |(reason, status)| {
+                metrics::REJECTED_REQUESTS
+                    .with_label_values(&[reason])
+                    .inc();
+                status
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn key(
+        name: &str,
+        scope: Scope,
+        rate_limit: Option<u32>,
+        daily_quota: Option<u64>,
+    ) -> ApiKeyConfig {
+        ApiKeyConfig {
+            name: name.to_string(),
+            key: format!("{name}-0123456789abcdef"),
+            scope,
+            rate_limit,
+            daily_quota,
+        }
+    }
+
+    fn bearer(key: &ApiKeyConfig) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", key.key)).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn rate_limit_allows_a_burst_of_its_capacity() {
+        let limiter = RateLimiter::default();
+        let key = key("client", Scope::Submit, Some(3), None);
+
+        for _ in 0..3 {
+            assert_eq!(limiter.check(&key, 1), Ok(()));
+        }
+        assert_eq!(limiter.check(&key, 1), Err("rate_limited"));
+    }
+
+    #[test]
+    fn rate_limit_refills_over_time() {
+        let limiter = RateLimiter::default();
+        let key = key("client", Scope::Submit, Some(60), None);
+
+        assert_eq!(limiter.check(&key, 60), Ok(()));
+        assert_eq!(limiter.check(&key, 1), Err("rate_limited"));
+
+        // 60 requests per minute refill one token per second
+        limiter
+            .usage
+            .lock()
+            .unwrap()
+            .get_mut("client")
+            .unwrap()
+            .last_refill -= Duration::from_secs(2);
+
+        assert_eq!(limiter.check(&key, 2), Ok(()));
+        assert_eq!(limiter.check(&key, 1), Err("rate_limited"));
+    }
+
+    #[test]
+    fn weighted_requests_count_against_the_quota() {
+        let limiter = RateLimiter::default();
+        let key = key("client", Scope::Submit, None, Some(10));
+
+        assert_eq!(limiter.check(&key, 8), Ok(()));
+        assert_eq!(limiter.check(&key, 3), Err("quota_exceeded"));
+        assert_eq!(limiter.check(&key, 2), Ok(()));
+        assert_eq!(limiter.check(&key, 1), Err("quota_exceeded"));
+    }
+
+    #[test]
+    fn requests_beyond_the_capacity_are_too_large() {
+        let limiter = RateLimiter::default();
+        let limited = key("limited", Scope::Submit, Some(5), None);
+        let quota = key("quota", Scope::Submit, None, Some(5));
+
+        assert_eq!(limiter.check(&limited, 6), Err("too_large"));
+        assert_eq!(limiter.check(&quota, 6), Err("too_large"));
+        // Nothing was used up
+        assert_eq!(limiter.check(&limited, 5), Ok(()));
+        assert_eq!(limiter.check(&quota, 5), Ok(()));
+
+        let auth = AuthConfig {
+            keys: vec![limited.clone()],
+            open: false,
+        };
+        assert_eq!(
+            authenticate(&auth, &limiter, &bearer(&limited), Scope::Submit, 6),
+            Err(("too_large", StatusCode::BAD_REQUEST))
+        );
+    }
+
+    #[test]
+    fn keys_are_limited_independently() {
+        let limiter = RateLimiter::default();
+        let first = key("first", Scope::Submit, Some(1), None);
+        let second = key("second", Scope::Submit, Some(1), None);
+
+        assert_eq!(limiter.check(&first, 1), Ok(()));
+        assert_eq!(limiter.check(&first, 1), Err("rate_limited"));
+        assert_eq!(limiter.check(&second, 1), Ok(()));
+    }
+
+    #[test]
+    fn admin_scope_allows_everything() {
+        assert!(Scope::Admin.allows(Scope::Admin));
+        assert!(Scope::Admin.allows(Scope::Submit));
+        assert!(Scope::Submit.allows(Scope::Submit));
+        assert!(!Scope::Submit.allows(Scope::Admin));
+    }
+
+    #[test]
+    fn submit_keys_cannot_access_admin_endpoints() {
+        let submitter = key("submitter", Scope::Submit, None, None);
+        let admin = key("admin", Scope::Admin, None, None);
+        let auth = AuthConfig {
+            keys: vec![submitter.clone(), admin.clone()],
+            open: false,
+        };
+        let limiter = RateLimiter::default();
+
+        let check = |headers: &HeaderMap, scope| {
+            authenticate(&auth, &limiter, headers, scope, 1).map_err(|(reason, _)| reason)
+        };
+
+        assert_eq!(
+            check(&bearer(&submitter), Scope::Submit),
+            Ok(Some("submitter".to_string()))
+        );
+        assert_eq!(check(&bearer(&submitter), Scope::Admin), Err("forbidden"));
+        assert_eq!(
+            check(&bearer(&admin), Scope::Admin),
+            Ok(Some("admin".to_string()))
+        );
+        assert_eq!(check(&HeaderMap::new(), Scope::Submit), Err("missing_key"));
+
+        let unknown = key("unknown", Scope::Admin, None, None);
+        assert_eq!(check(&bearer(&unknown), Scope::Admin), Err("invalid_key"));
+    }
+
+    #[test]
+    fn no_keys_fail_closed_unless_open() {
+        let limiter = RateLimiter::default();
+        let closed = AuthConfig::default();
+        let open = AuthConfig {
+            keys: vec![],
+            open: true,
+        };
+
+        for scope in [Scope::Submit, Scope::Admin] {
+            assert_eq!(
+                authenticate(&closed, &limiter, &HeaderMap::new(), scope, 1),
+                Err(("no_keys", StatusCode::UNAUTHORIZED))
+            );
+            assert_eq!(
+                authenticate(&open, &limiter, &HeaderMap::new(), scope, 1),
+                Ok(None)
+            );
+        }
+    }
+}
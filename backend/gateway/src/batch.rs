@@ -0,0 +1,227 @@
+use axum::{
+    Json,
+    http::{HeaderMap, StatusCode},
+};
+
+use serde::{Deserialize, Serialize};
+
+use snorkle_oracle_interface::{BatchItem, OracleError, ProgramInfo};
+
+use crate::config::Scope;
+use crate::{Gateway, Generated, Prepared, SubmitRequest, SubmitResult};
+
+/// Maximum number of games in a batch
+const MAX_BATCH_SIZE: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct BatchGame {
+    game_id: String,
+    /// Submit a new event for a game that was already settled, e.g., to correct its score
+    #[serde(default)]
+    correction: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchRequest {
+    games: Vec<BatchGame>,
+    /// The program to submit to; the oracle's default program if not set
+    #[serde(default)]
+    program: Option<String>,
+    /// URL that is notified once each submission is confirmed or rejected
+    #[serde(default)]
+    callback_url: Option<String>,
+}
+
+/// The outcome of one game of a batch
+#[derive(Debug, Serialize)]
+pub struct BatchItemResult {
+    game_id: String,
+    /// The status code `/submit` would have returned for this game
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<SubmitResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl BatchItemResult {
+    fn new(game_id: String, result: Result<SubmitResult, (StatusCode, String)>) -> Self {
+        match result {
+            Ok(result) => Self {
+                game_id,
+                status: StatusCode::OK.as_u16(),
+                result: Some(result),
+                error: None,
+            },
+            Err((status, error)) => Self {
+                game_id,
+                status: status.as_u16(),
+                result: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchResponse {
+    /// One result per game, in the order of the request
+    results: Vec<BatchItemResult>,
+}
+
+impl Gateway {
+    /// Submit several games at once
+    ///
+    /// The oracle fetches and proves the games in parallel. Each game succeeds or fails on
+    /// its own; the response only fails if the batch as a whole is invalid.
+    pub async fn submit_batch_handler(
+        &self,
+        headers: HeaderMap,
+        Json(request): Json<BatchRequest>,
+    ) -> Result<Json<BatchResponse>, (StatusCode, String)> {
+        if request.games.is_empty() || request.games.len() > MAX_BATCH_SIZE {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("a batch must contain between 1 and {MAX_BATCH_SIZE} games"),
+            ));
+        }
+
+        // Every game counts against the rate limit and quota
+        let client = self
+            .authorize_weighted(&headers, Scope::Submit, request.games.len() as u32)
+            .map_err(|status| {
+                let message = match status {
+                    StatusCode::BAD_REQUEST => format!(
+                        "a batch of {} games exceeds the rate limit or daily quota of the API key",
+                        request.games.len()
+                    ),
+                    _ => status.to_string(),
+                };
+                (status, message)
+            })?;
+        if let Some(client) = client {
+            log::info!(
+                "Got batch of {} submissions from `{client}`",
+                request.games.len()
+            );
+        }
+
+        let program = self.resolve_program(request.program.as_deref())?;
+        self.check_callback(request.callback_url.as_deref()).await?;
+
+        let requests: Vec<SubmitRequest> = request
+            .games
+            .into_iter()
+            .map(|game| SubmitRequest {
+                game_id: game.game_id,
+                program: Some(program.id.clone()),
+                correction: game.correction,
+                fee_record: None,
+                callback_url: request.callback_url.clone(),
+            })
+            .collect();
+
+        let mut results: Vec<Option<Result<SubmitResult, (StatusCode, String)>>> =
+            requests.iter().map(|_| None).collect();
+        let mut claimed = vec![];
+        let mut pending = vec![];
+
+        for (index, request) in requests.iter().enumerate() {
+            // Only one submission per game and program at a time, also within the batch
+            let Some(claim) = self.claim(&program, &request.game_id) else {
+                results[index] = Some(Err((
+                    StatusCode::CONFLICT,
+                    "submission already in progress".to_string(),
+                )));
+                continue;
+            };
+            claimed.push(claim);
+
+            match self.prepare_submission(&program, request) {
+                Ok(Prepared::Existing(result)) => results[index] = Some(Ok(result)),
+                Ok(Prepared::Revision(revision)) => pending.push((index, revision)),
+                Err(err) => results[index] = Some(Err(err)),
+            }
+        }
+
+        if !pending.is_empty() {
+            let games = pending
+                .iter()
+                .map(|&(index, revision)| BatchItem {
+                    game_id: requests[index].game_id.clone(),
+                    revision,
+                })
+                .collect();
+
+            match self
+                .generate_submissions(&program, games, self.fee_options(None))
+                .await
+            {
+                Ok(generated) => {
+                    let broadcasts =
+                        pending
+                            .iter()
+                            .zip(generated)
+                            .map(|(&(index, revision), generated)| {
+                                self.complete_batch_item(
+                                    &program,
+                                    &requests[index],
+                                    revision,
+                                    generated,
+                                )
+                            });
+                    let completed = futures::future::join_all(broadcasts).await;
+
+                    for (&(index, _), result) in pending.iter().zip(completed) {
+                        results[index] = Some(result);
+                    }
+                }
+                Err(err) => {
+                    // The whole batch failed; report the error for every game
+                    let err = err
+                        .downcast::<OracleError>()
+                        .unwrap_or_else(|err| OracleError::Other(format!("{err:#}")));
+                    for &(index, _) in &pending {
+                        results[index] = Some(Err(self.submission_failed(
+                            &program,
+                            &requests[index],
+                            err.clone().into(),
+                        )));
+                    }
+                }
+            }
+        }
+
+        drop(claimed);
+
+        let results = requests
+            .into_iter()
+            .zip(results)
+            .map(|(request, result)| {
+                BatchItemResult::new(
+                    request.game_id,
+                    result.expect("every game of the batch has a result"),
+                )
+            })
+            .collect();
+
+        Ok(Json(BatchResponse { results }))
+    }
+
+    /// Broadcast a submission generated as part of a batch, or record its failure
+    async fn complete_batch_item(
+        &self,
+        program: &ProgramInfo,
+        request: &SubmitRequest,
+        revision: u32,
+        generated: anyhow::Result<Generated>,
+    ) -> Result<SubmitResult, (StatusCode, String)> {
+        match generated {
+            Ok(generated) => {
+                self.broadcast_submission(program, request, revision, generated)
+                    .await
+            }
+            Err(err) => Err(self.submission_failed(program, request, err)),
+        }
+    }
+}
@@ -0,0 +1,728 @@
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use clap::{Parser, ValueEnum};
+
+use serde::{Deserialize, Serialize};
+
+use snarkvm::prelude::{CanaryV0, MainnetV0, Network, TestnetV0};
+
+use snorkle_oracle_interface::ORACLE_PORT;
+
+use crate::CurrentNetwork;
+
+/// Command line arguments of the gateway
+///
+/// Every argument overrides the corresponding setting of the configuration file.
+#[derive(Debug, Parser)]
+#[command(version, about = "HTTP gateway for the snorkle oracle", long_about = None)]
+pub struct Args {
+    /// Path to the TOML configuration file
+    #[arg(long, short, env = "SNORKLE_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Address of the oracle, e.g., `localhost:54541`
+    #[arg(long, env = "SNORKLE_ORACLE")]
+    pub oracle: Option<String>,
+
+    /// Address the HTTP server binds to
+    #[arg(long, env = "SNORKLE_BIND")]
+    pub bind: Option<SocketAddr>,
+
+    /// Aleo network to operate on; only `testnet` is supported
+    #[arg(long, env = "SNORKLE_NETWORK")]
+    pub network: Option<NetworkName>,
+
+    /// Base URL of the node's REST API (without the network)
+    #[arg(long, env = "SNORKLE_NODE_ENDPOINT")]
+    pub node_endpoint: Option<String>,
+
+    /// Base URL used to broadcast transactions (without the network)
+    #[arg(long, env = "SNORKLE_BROADCAST_ENDPOINT")]
+    pub broadcast_endpoint: Option<String>,
+
+    /// When to register the oracle on startup
+    #[arg(long, env = "SNORKLE_REGISTRATION")]
+    pub registration: Option<RegistrationPolicy>,
+
+    /// File to persist the submission history to
+    #[arg(long, env = "SNORKLE_HISTORY")]
+    pub history: Option<PathBuf>,
+
+    /// Additional fee (in microcredits) paid for every transaction
+    #[arg(long, env = "SNORKLE_PRIORITY_FEE")]
+    pub priority_fee: Option<u64>,
+
+    /// Where submissions are proven
+    #[arg(long, env = "SNORKLE_PROVING")]
+    pub proving: Option<ProvingMode>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkName {
+    Mainnet,
+    #[default]
+    Testnet,
+    Canary,
+}
+
+impl NetworkName {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Mainnet => "mainnet",
+            Self::Testnet => "testnet",
+            Self::Canary => "canary",
+        }
+    }
+}
+
+/// When the gateway registers the oracle with the contract
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum RegistrationPolicy {
+    /// Register on every startup
+    #[default]
+    Always,
+    /// Register only if the oracle is not registered or its registration expired
+    IfNeeded,
+    /// Never register; the oracle must be registered by other means
+    Never,
+}
+
+/// Where submissions are proven
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ProvingMode {
+    /// The oracle signs and proves
+    #[default]
+    Oracle,
+    /// The oracle only signs; the gateway proves
+    Gateway,
+    /// The oracle only signs; a prover service at `prover.url` proves
+    Remote,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ProverConfig {
+    pub mode: ProvingMode,
+    /// URL of the prover service (see `snorkle-prover`); required if `mode` is `remote`
+    pub url: Option<String>,
+    /// Token of the prover service; required if `mode` is `remote`
+    pub token: Option<String>,
+    /// Seconds until connecting to the prover service fails
+    pub connect_timeout: u64,
+    /// Seconds until a request to the prover service fails, including proving
+    pub timeout: u64,
+}
+
+impl Default for ProverConfig {
+    fn default() -> Self {
+        Self {
+            mode: ProvingMode::default(),
+            url: None,
+            token: None,
+            connect_timeout: 10,
+            timeout: 600,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct NetworkConfig {
+    /// The network is fixed at compile time, like that of the oracle, so any other network
+    /// is rejected
+    pub name: NetworkName,
+    /// Base URL of the node's REST API (without the network)
+    pub node_endpoint: String,
+    /// Base URL used to broadcast transactions; defaults to `node_endpoint`
+    pub broadcast_endpoint: Option<String>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            name: NetworkName::default(),
+            node_endpoint: "https://api.explorer.provable.com/v1".to_string(),
+            broadcast_endpoint: None,
+        }
+    }
+}
+
+/// Path segment of the gateway's network in the node's REST API
+///
+/// The network is fixed at compile time, like that of the oracle.
+fn network_path() -> &'static str {
+    match CurrentNetwork::ID {
+        MainnetV0::ID => "mainnet",
+        TestnetV0::ID => "testnet",
+        CanaryV0::ID => "canary",
+        _ => unreachable!("Unsupported network: {}", CurrentNetwork::NAME),
+    }
+}
+
+impl NetworkConfig {
+    /// The node's REST endpoint, including the network
+    pub fn node_url(&self) -> String {
+        format!(
+            "{}/{}",
+            self.node_endpoint.trim_end_matches('/'),
+            network_path()
+        )
+    }
+
+    /// The broadcast endpoint, including the network
+    pub fn broadcast_url(&self) -> String {
+        let endpoint = self
+            .broadcast_endpoint
+            .as_deref()
+            .unwrap_or(&self.node_endpoint);
+        format!("{}/{}", endpoint.trim_end_matches('/'), network_path())
+    }
+}
+
+/// What an API key may access
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Request new submissions
+    #[default]
+    Submit,
+    /// Everything, including registration and configuration endpoints
+    Admin,
+}
+
+impl Scope {
+    /// Can a key with this scope access an endpoint that requires `required`?
+    pub fn allows(self, required: Scope) -> bool {
+        self == Scope::Admin || self == required
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeyConfig {
+    /// Human-readable name of the client; used for logging
+    pub name: String,
+    pub key: String,
+    #[serde(default)]
+    pub scope: Scope,
+    /// Maximum number of requests per minute; unlimited if unset
+    pub rate_limit: Option<u32>,
+    /// Maximum number of requests per (UTC) day; unlimited if unset
+    pub daily_quota: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct AuthConfig {
+    /// Keys accepted on protected endpoints
+    pub keys: Vec<ApiKeyConfig>,
+    /// Allow anyone to access protected endpoints, including the admin endpoints, if there
+    /// are no keys; protected endpoints reject every request without keys otherwise
+    pub open: bool,
+}
+
+/// What to do when the oracle's public balance drops below `min_balance`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LowBalancePolicy {
+    /// Log a warning
+    #[default]
+    Warn,
+    /// Refuse submissions that are paid from the public balance
+    Refuse,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct FeeConfig {
+    /// Additional fee (in microcredits) paid for every transaction
+    pub priority_fee: u64,
+    /// Refuse to prove transactions whose estimated fee (in microcredits) is higher
+    pub max_fee: Option<u64>,
+    /// Public balance (in microcredits) below which `low_balance` applies
+    pub min_balance: Option<u64>,
+    pub low_balance: LowBalancePolicy,
+    /// Seconds between balance checks
+    pub balance_interval: u64,
+}
+
+impl Default for FeeConfig {
+    fn default() -> Self {
+        Self {
+            priority_fee: 0,
+            max_fee: None,
+            min_balance: None,
+            low_balance: LowBalancePolicy::default(),
+            balance_interval: 60,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct PriceFeedConfig {
+    /// Asset pairs (as `BASE/QUOTE`) to push prices for; the price feed is disabled if empty
+    pub pairs: Vec<String>,
+    /// Seconds between price updates
+    pub interval: u64,
+    /// The program to push prices to; the oracle's default program if unset
+    pub program: Option<String>,
+}
+
+impl Default for PriceFeedConfig {
+    fn default() -> Self {
+        Self {
+            pairs: vec![],
+            interval: 300,
+            program: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct WatcherConfig {
+    /// Poll the scores API and submit games automatically when they finish
+    pub enabled: bool,
+    /// Seconds between polls
+    pub interval: u64,
+    /// Only watch games of these leagues; all leagues if empty
+    pub leagues: Vec<String>,
+    /// Only watch games in which one of these teams plays; all teams if empty
+    pub teams: Vec<String>,
+    /// Game statuses (case-insensitive) that mark a game as final
+    pub final_statuses: Vec<String>,
+    /// Also submit games that were already final when the gateway started
+    pub backfill: bool,
+    /// Give up on a game after this many failed submissions
+    pub max_attempts: u32,
+    /// The program to submit to; the oracle's default program if unset
+    pub program: Option<String>,
+}
+
+impl Default for WatcherConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 60,
+            leagues: vec![],
+            teams: vec![],
+            final_statuses: vec!["final".to_string()],
+            backfill: false,
+            max_attempts: 5,
+            program: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct IndexerConfig {
+    /// Index the events and oracles of programs from the chain
+    pub enabled: bool,
+    /// Seconds between passes
+    pub interval: u64,
+    /// The programs to index; the oracle's default program if empty
+    pub programs: Vec<String>,
+}
+
+impl Default for IndexerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 30,
+            programs: vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct MetricsConfig {
+    /// Seconds between refreshes of the metrics that require the node or the oracle
+    pub interval: u64,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self { interval: 15 }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct WebhookConfig {
+    /// URLs notified about every submission
+    pub urls: Vec<String>,
+    /// Key for the HMAC-SHA256 signature of notifications; required for webhooks and callbacks
+    pub secret: Option<String>,
+    /// Let callback URLs in requests point to private, loopback, and link-local addresses
+    pub allow_private_callbacks: bool,
+    /// Seconds until a delivery attempt fails
+    pub timeout: u64,
+    /// Attempts per notification before giving up
+    pub max_attempts: u32,
+    /// Seconds before the first retry; doubled after every attempt
+    pub retry_delay: u64,
+    /// Seconds to wait for a transaction to be confirmed before reporting it as unconfirmed
+    pub confirmation_timeout: u64,
+    /// Seconds between checks for confirmation
+    pub poll_interval: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            urls: vec![],
+            secret: None,
+            allow_private_callbacks: false,
+            timeout: 10,
+            max_attempts: 5,
+            retry_delay: 5,
+            confirmation_timeout: 600,
+            poll_interval: 10,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct PersistenceConfig {
+    /// File to persist the submission history to (`history.json` by default); kept in
+    /// memory only if unset
+    ///
+    /// The history is what stops games from being submitted twice, also across restarts.
+    pub history: Option<PathBuf>,
+    /// File to persist the chain index to; rebuilt from scratch on startup if unset
+    pub index: Option<PathBuf>,
+    /// File to persist the games the watcher is done with to (`watcher.json` by default);
+    /// games that finished while the gateway was down are missed if unset
+    pub watcher: Option<PathBuf>,
+    /// Directory to persist proof-of-fetch bundles to; only the latest are kept in memory
+    /// if unset
+    pub proofs: Option<PathBuf>,
+}
+
+impl Default for PersistenceConfig {
+    fn default() -> Self {
+        Self {
+            history: Some(PathBuf::from("history.json")),
+            index: None,
+            watcher: Some(PathBuf::from("watcher.json")),
+            proofs: None,
+        }
+    }
+}
+
+/// Configuration of the gateway
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct Config {
+    /// Address of the oracle
+    pub oracle: String,
+    /// Address the HTTP server binds to
+    pub bind: SocketAddr,
+    pub registration: RegistrationPolicy,
+    pub network: NetworkConfig,
+    pub auth: AuthConfig,
+    pub fees: FeeConfig,
+    pub prover: ProverConfig,
+    pub prices: PriceFeedConfig,
+    pub watcher: WatcherConfig,
+    pub indexer: IndexerConfig,
+    pub metrics: MetricsConfig,
+    pub webhooks: WebhookConfig,
+    pub persistence: PersistenceConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            oracle: format!("localhost:{ORACLE_PORT}"),
+            bind: SocketAddr::from(([0, 0, 0, 0], 3000)),
+            registration: RegistrationPolicy::default(),
+            network: NetworkConfig::default(),
+            auth: AuthConfig::default(),
+            fees: FeeConfig::default(),
+            prover: ProverConfig::default(),
+            prices: PriceFeedConfig::default(),
+            watcher: WatcherConfig::default(),
+            indexer: IndexerConfig::default(),
+            metrics: MetricsConfig::default(),
+            webhooks: WebhookConfig::default(),
+            persistence: PersistenceConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the configuration file (if any), apply command line overrides, and validate the result
+    pub fn load(args: Args) -> anyhow::Result<Self> {
+        let mut config = match &args.config {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+
+        if let Some(oracle) = args.oracle {
+            config.oracle = oracle;
+        }
+        if let Some(bind) = args.bind {
+            config.bind = bind;
+        }
+        if let Some(network) = args.network {
+            config.network.name = network;
+        }
+        if let Some(endpoint) = args.node_endpoint {
+            config.network.node_endpoint = endpoint;
+        }
+        if let Some(endpoint) = args.broadcast_endpoint {
+            config.network.broadcast_endpoint = Some(endpoint);
+        }
+        if let Some(registration) = args.registration {
+            config.registration = registration;
+        }
+        if let Some(history) = args.history {
+            config.persistence.history = Some(history);
+        }
+        if let Some(priority_fee) = args.priority_fee {
+            config.fees.priority_fee = priority_fee;
+        }
+        if let Some(proving) = args.proving {
+            config.prover.mode = proving;
+        }
+
+        config.validate().with_context(|| "Invalid configuration")?;
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))
+    }
+
+    /// A copy of the configuration that is safe to expose, i.e., without secrets
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        for key in &mut config.auth.keys {
+            key.key = "<redacted>".to_string();
+        }
+        if let Some(secret) = &mut config.webhooks.secret {
+            *secret = "<redacted>".to_string();
+        }
+        if let Some(token) = &mut config.prover.token {
+            *token = "<redacted>".to_string();
+        }
+        config
+    }
+
+    /// Check the configuration for errors that deserialization cannot catch
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let Some((host, port)) = self.oracle.rsplit_once(':') else {
+            anyhow::bail!("oracle address `{}` is missing a port", self.oracle);
+        };
+        if host.is_empty() {
+            anyhow::bail!("oracle address `{}` is missing a host", self.oracle);
+        }
+        port.parse::<u16>()
+            .with_context(|| format!("oracle address `{}` has an invalid port", self.oracle))?;
+
+        if self.network.name.as_str() != network_path() {
+            anyhow::bail!(
+                "network.name = \"{}\" is not supported; the gateway and the oracle are built for {}",
+                self.network.name.as_str(),
+                network_path()
+            );
+        }
+
+        let endpoints = std::iter::once(&self.network.node_endpoint)
+            .chain(self.network.broadcast_endpoint.as_ref())
+            .chain(self.prover.url.as_ref())
+            .chain(&self.webhooks.urls);
+        for endpoint in endpoints {
+            validate_url(endpoint)?;
+        }
+
+        if self.auth.open && !self.auth.keys.is_empty() {
+            anyhow::bail!("auth.open cannot be combined with auth.keys");
+        }
+        let mut names = HashSet::new();
+        let mut keys = HashSet::new();
+        for key in &self.auth.keys {
+            if key.name.is_empty() {
+                anyhow::bail!("API key names must not be empty");
+            }
+            if key.key.len() < 16 {
+                anyhow::bail!("API key `{}` must be at least 16 characters", key.name);
+            }
+            if !names.insert(&key.name) {
+                anyhow::bail!("API key name `{}` is used more than once", key.name);
+            }
+            if !keys.insert(&key.key) {
+                anyhow::bail!("API key `{}` is a duplicate of another key", key.name);
+            }
+            if key.rate_limit == Some(0) {
+                anyhow::bail!("API key `{}` has a rate limit of zero", key.name);
+            }
+        }
+
+        if self.fees.balance_interval == 0 {
+            anyhow::bail!("fees.balance_interval must be at least one second");
+        }
+        if let Some(max_fee) = self.fees.max_fee
+            && max_fee < self.fees.priority_fee
+        {
+            anyhow::bail!("fees.max_fee must not be lower than fees.priority_fee");
+        }
+        if self.fees.low_balance == LowBalancePolicy::Refuse && self.fees.min_balance.is_none() {
+            anyhow::bail!("fees.low_balance = \"refuse\" requires fees.min_balance");
+        }
+
+        if self.prover.mode == ProvingMode::Remote && self.prover.url.is_none() {
+            anyhow::bail!("prover.mode = \"remote\" requires prover.url");
+        }
+        if self.prover.mode == ProvingMode::Remote && self.prover.token.is_none() {
+            anyhow::bail!("prover.mode = \"remote\" requires prover.token");
+        }
+        if self.prover.connect_timeout == 0 || self.prover.timeout == 0 {
+            anyhow::bail!("prover.connect_timeout and prover.timeout must be at least one second");
+        }
+
+        if self.prices.interval == 0 {
+            anyhow::bail!("prices.interval must be at least one second");
+        }
+        let mut pairs = HashSet::new();
+        for pair in &self.prices.pairs {
+            let valid = pair.split_once('/').is_some_and(|(base, quote)| {
+                [base, quote].iter().all(|asset| {
+                    !asset.is_empty()
+                        && asset
+                            .chars()
+                            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+                })
+            });
+            if !valid {
+                anyhow::bail!("price pair `{pair}` must have the form BASE/QUOTE, e.g., ALEO/USD");
+            }
+            if !pairs.insert(pair) {
+                anyhow::bail!("price pair `{pair}` is listed more than once");
+            }
+        }
+
+        if self.watcher.interval == 0 {
+            anyhow::bail!("watcher.interval must be at least one second");
+        }
+        if self.watcher.max_attempts == 0 {
+            anyhow::bail!("watcher.max_attempts must be at least one");
+        }
+        if self.watcher.enabled && self.watcher.final_statuses.is_empty() {
+            anyhow::bail!("watcher.final_statuses must not be empty");
+        }
+
+        if self.indexer.interval == 0 {
+            anyhow::bail!("indexer.interval must be at least one second");
+        }
+
+        if self.metrics.interval == 0 {
+            anyhow::bail!("metrics.interval must be at least one second");
+        }
+
+        if !self.webhooks.urls.is_empty() && self.webhooks.secret.is_none() {
+            anyhow::bail!("webhooks.urls requires webhooks.secret");
+        }
+        if self
+            .webhooks
+            .secret
+            .as_ref()
+            .is_some_and(|secret| secret.len() < 16)
+        {
+            anyhow::bail!("webhooks.secret must be at least 16 characters");
+        }
+        if self.webhooks.max_attempts == 0 {
+            anyhow::bail!("webhooks.max_attempts must be at least one");
+        }
+        if self.webhooks.poll_interval == 0 {
+            anyhow::bail!("webhooks.poll_interval must be at least one second");
+        }
+        if self.webhooks.timeout == 0 {
+            anyhow::bail!("webhooks.timeout must be at least one second");
+        }
+
+        if let Some(history) = &self.persistence.history
+            && history.is_dir()
+        {
+            anyhow::bail!("history path {} is a directory", history.display());
+        }
+        if let Some(index) = &self.persistence.index
+            && index.is_dir()
+        {
+            anyhow::bail!("index path {} is a directory", index.display());
+        }
+        if let Some(proofs) = &self.persistence.proofs
+            && proofs.exists()
+            && !proofs.is_dir()
+        {
+            anyhow::bail!("proofs path {} is not a directory", proofs.display());
+        }
+
+        Ok(())
+    }
+}
+
+/// Make sure `url` is a valid HTTP(S) URL
+pub fn validate_url(url: &str) -> anyhow::Result<()> {
+    let parsed = reqwest::Url::parse(url).with_context(|| format!("`{url}` is not a valid URL"))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        anyhow::bail!("`{url}` must use http or https");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn other_networks_are_rejected() {
+        Config::default().validate().unwrap();
+
+        let mut config = Config::default();
+        config.network.name = NetworkName::Mainnet;
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("not supported"));
+    }
+
+    #[test]
+    fn redaction_removes_every_secret() {
+        let mut config = Config::default();
+        config.auth.keys.push(ApiKeyConfig {
+            name: "client".to_string(),
+            key: "client-0123456789abcdef".to_string(),
+            scope: Scope::Admin,
+            rate_limit: None,
+            daily_quota: None,
+        });
+        config.webhooks.secret = Some("webhook-0123456789abcdef".to_string());
+        config.prover.token = Some("prover-0123456789abcdef".to_string());
+
+        let redacted = format!("{:?}", config.redacted());
+        for secret in [
+            "client-0123456789abcdef",
+            "webhook-0123456789abcdef",
+            "prover-0123456789abcdef",
+        ] {
+            assert!(!redacted.contains(secret), "{secret} was not redacted");
+        }
+        assert!(redacted.contains("client"));
+    }
+}
@@ -0,0 +1,155 @@
+use std::convert::Infallible;
+
+use axum::response::sse::{Event, KeepAlive, Sse};
+
+use chrono::{DateTime, Local};
+
+use futures::Stream;
+
+use serde::Serialize;
+
+use tokio::sync::broadcast;
+
+use crate::Gateway;
+use crate::webhooks::FinalStatus;
+
+/// Number of events buffered per subscriber; slower subscribers miss events
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Something the gateway or the oracle did
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Activity {
+    /// The oracle started fetching and proving a submission
+    SubmissionStarted {
+        program: String,
+        game_id: String,
+        revision: u32,
+    },
+    /// The submission was proven and broadcast
+    SubmissionBroadcast {
+        program: String,
+        game_id: String,
+        revision: u32,
+        transaction_id: String,
+    },
+    SubmissionFailed {
+        program: String,
+        game_id: String,
+        error: String,
+    },
+    /// A registration was broadcast
+    Registration {
+        program: String,
+        transaction_id: String,
+    },
+    RegistrationFailed {
+        program: String,
+        error: String,
+    },
+    /// A broadcast transaction was accepted or rejected, or not confirmed in time
+    Confirmation {
+        transaction_id: String,
+        status: FinalStatus,
+    },
+    PriceUpdate {
+        program: String,
+        pair: String,
+        price: u128,
+        transaction_id: String,
+    },
+    PriceUpdateFailed {
+        pair: String,
+        error: String,
+    },
+    /// The indexer found a new event on chain; `foreign` if another oracle submitted it
+    EventIndexed {
+        program: String,
+        event_id: String,
+        oracle: String,
+        foreign: bool,
+    },
+}
+
+impl Activity {
+    /// The name of the event in the stream
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::SubmissionStarted { .. } => "submission_started",
+            Self::SubmissionBroadcast { .. } => "submission_broadcast",
+            Self::SubmissionFailed { .. } => "submission_failed",
+            Self::Registration { .. } => "registration",
+            Self::RegistrationFailed { .. } => "registration_failed",
+            Self::Confirmation { .. } => "confirmation",
+            Self::PriceUpdate { .. } => "price_update",
+            Self::PriceUpdateFailed { .. } => "price_update_failed",
+            Self::EventIndexed { .. } => "event_indexed",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ActivityEntry {
+    pub time: DateTime<Local>,
+    #[serde(flatten)]
+    pub activity: Activity,
+}
+
+/// Fans activity out to all subscribers of `/events/stream`
+#[derive(Clone)]
+pub struct ActivityFeed {
+    sender: broadcast::Sender<ActivityEntry>,
+}
+
+impl Default for ActivityFeed {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(CHANNEL_CAPACITY).0,
+        }
+    }
+}
+
+impl ActivityFeed {
+    pub fn publish(&self, activity: Activity) {
+        let entry = ActivityEntry {
+            time: Local::now(),
+            activity,
+        };
+
+        // Fails only if nobody is subscribed
+        let _ = self.sender.send(entry);
+    }
+
+    fn subscribe(&self) -> impl Stream<Item = Result<Event, Infallible>> + use<> {
+        let receiver = self.sender.subscribe();
+
+        futures::stream::unfold(receiver, async |mut receiver| {
+            loop {
+                match receiver.recv().await {
+                    Ok(entry) => {
+                        let event = Event::default()
+                            .event(entry.activity.kind())
+                            .json_data(&entry)
+                            .unwrap_or_else(|err| {
+                                Event::default().event("error").data(err.to_string())
+                            });
+                        return Some((Ok(event), receiver));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        log::warn!("Event stream subscriber missed {missed} events");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
+
+impl Gateway {
+    /// Streams activity as Server-Sent Events, named after the `type` of each entry
+    pub async fn events_stream_handler(
+        &self,
+    ) -> Sse<impl Stream<Item = Result<Event, Infallible>> + use<>> {
+        Sse::new(self.activity.subscribe()).keep_alive(KeepAlive::default())
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use snarkvm::prelude::{Literal, Plaintext};
+
+use snorkle_oracle_interface::FeeOptions;
+
+use crate::Gateway;
+use crate::config::LowBalancePolicy;
+use crate::metrics;
+
+impl Gateway {
+    /// Fee settings for a new transaction
+    pub fn fee_options(&self, fee_record: Option<String>) -> FeeOptions {
+        FeeOptions {
+            priority_fee: self.config.fees.priority_fee,
+            fee_record,
+            max_fee: self.config.fees.max_fee,
+        }
+    }
+
+    /// Fetch the public balance of the oracle in microcredits
+    pub async fn fetch_balance(&self) -> anyhow::Result<u64> {
+        let Some(address) = self
+            .oracle_info
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|info| info.address.clone())
+        else {
+            anyhow::bail!("Oracle info not fetched yet");
+        };
+
+        let value = self
+            .node
+            .get_mapping_value("credits.aleo", "account", &address)
+            .await?;
+
+        match value {
+            None => Ok(0),
+            Some(Plaintext::Literal(Literal::U64(balance), _)) => Ok(*balance),
+            Some(other) => anyhow::bail!("Invalid balance: {other}"),
+        }
+    }
+
+    /// Is the last known balance below the configured minimum?
+    pub fn is_balance_low(&self) -> bool {
+        match (self.config.fees.min_balance, *self.balance.lock().unwrap()) {
+            (Some(min_balance), Some(balance)) => balance < min_balance,
+            _ => false,
+        }
+    }
+
+    /// Should submissions paid from the public balance be refused?
+    pub fn refuse_public_fee(&self) -> bool {
+        self.config.fees.low_balance == LowBalancePolicy::Refuse && self.is_balance_low()
+    }
+
+    /// Periodically check the oracle's public balance
+    pub async fn run_balance_monitor(self: Arc<Self>) {
+        let interval = Duration::from_secs(self.config.fees.balance_interval);
+
+        loop {
+            match self.fetch_balance().await {
+                Ok(balance) => {
+                    *self.balance.lock().unwrap() = Some(balance);
+                    metrics::ORACLE_BALANCE.set(balance.try_into().unwrap_or(i64::MAX));
+
+                    if self.is_balance_low() {
+                        log::warn!(
+                            "Oracle balance of {balance} microcredits is below the minimum of {}",
+                            self.config.fees.min_balance.unwrap_or_default()
+                        );
+                    }
+                }
+                Err(err) => log::warn!("Failed to fetch oracle balance: {err}"),
+            }
+
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{Json, extract::Query, http::StatusCode};
+
+use serde::{Deserialize, Serialize};
+
+use snarkvm::prelude::{Identifier, Literal, Plaintext};
+
+use snorkle_oracle_interface::files;
+
+use crate::events::Activity;
+use crate::{CurrentNetwork, Gateway, metrics};
+
+/// Maximum number of events fetched per program and pass, so that progress is saved regularly
+const BATCH_SIZE: u128 = 500;
+
+/// Maximum number of entries returned per request
+const MAX_LIMIT: usize = 1000;
+
+/// An entry of the `events` mapping
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexedEvent {
+    /// Position in the `event_ids` mapping
+    pub index: u128,
+    pub event_id: String,
+    /// Address of the oracle that submitted the event
+    pub oracle: String,
+    /// Block height at which the event was submitted
+    pub block_height: u32,
+    pub home_score: u8,
+    pub away_score: u8,
+}
+
+/// An entry of the `registered_oracles` mapping
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexedOracle {
+    pub address: String,
+    pub attestation_hash: String,
+    /// Block height of the latest registration; `None` for programs that do not record it
+    pub registered_at: Option<u32>,
+}
+
+/// The indexed state of one program
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ProgramIndex {
+    pub events: Vec<IndexedEvent>,
+    pub oracles: Vec<IndexedOracle>,
+}
+
+/// Indexed state, by program ID
+pub type ChainIndex = HashMap<String, ProgramIndex>;
+
+#[derive(Debug, Deserialize)]
+pub struct IndexQuery {
+    /// The program to list; the oracle's default program if not set
+    program: Option<String>,
+    /// Only list events submitted by this oracle
+    oracle: Option<String>,
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+}
+
+/// Look up a (nested) member of a struct
+fn member<'a>(
+    value: &'a Plaintext<CurrentNetwork>,
+    path: &[&str],
+) -> anyhow::Result<&'a Plaintext<CurrentNetwork>> {
+    let mut value = value;
+    for name in path {
+        let Plaintext::Struct(members, _) = value else {
+            anyhow::bail!("Expected a struct but got {value}");
+        };
+        value = members
+            .get(&Identifier::from_str(name)?)
+            .ok_or_else(|| anyhow::anyhow!("Missing member `{name}`"))?;
+    }
+
+    Ok(value)
+}
+
+fn as_u8(value: &Plaintext<CurrentNetwork>) -> anyhow::Result<u8> {
+    match value {
+        Plaintext::Literal(Literal::U8(value), _) => Ok(**value),
+        other => anyhow::bail!("Expected a u8 but got {other}"),
+    }
+}
+
+fn as_u32(value: &Plaintext<CurrentNetwork>) -> anyhow::Result<u32> {
+    match value {
+        Plaintext::Literal(Literal::U32(value), _) => Ok(**value),
+        other => anyhow::bail!("Expected a u32 but got {other}"),
+    }
+}
+
+fn as_u128(value: &Plaintext<CurrentNetwork>) -> anyhow::Result<u128> {
+    match value {
+        Plaintext::Literal(Literal::U128(value), _) => Ok(**value),
+        other => anyhow::bail!("Expected a u128 but got {other}"),
+    }
+}
+
+fn parse_event(
+    index: u128,
+    event_id: String,
+    event: &Plaintext<CurrentNetwork>,
+) -> anyhow::Result<IndexedEvent> {
+    // proto_snorkle_oracle_001 calls the oracle `oracle` instead of `oracle_id`
+    let oracle = member(event, &["oracle_id"]).or_else(|_| member(event, &["oracle"]))?;
+
+    Ok(IndexedEvent {
+        index,
+        event_id,
+        oracle: oracle.to_string(),
+        block_height: as_u32(member(event, &["timestamp"])?)?,
+        home_score: as_u8(member(event, &["event_data", "home_team_score"])?)?,
+        away_score: as_u8(member(event, &["event_data", "away_team_score"])?)?,
+    })
+}
+
+fn parse_oracle(
+    address: String,
+    data: &Plaintext<CurrentNetwork>,
+) -> anyhow::Result<IndexedOracle> {
+    // Older programs only store the attestation hash
+    if let Plaintext::Literal(Literal::Field(_), _) = data {
+        return Ok(IndexedOracle {
+            address,
+            attestation_hash: data.to_string(),
+            registered_at: None,
+        });
+    }
+
+    Ok(IndexedOracle {
+        address,
+        attestation_hash: member(data, &["attestation_hash"])?.to_string(),
+        registered_at: Some(as_u32(member(data, &["registration_timestamp"])?)?),
+    })
+}
+
+/// Load the persisted index, or start from scratch if it is missing or unreadable
+///
+/// The index only caches what is on chain, so it can always be rebuilt.
+pub fn load_index(path: &Path) -> ChainIndex {
+    if !path.exists() {
+        return Default::default();
+    }
+
+    let index = std::fs::read(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|data| Ok(serde_json::from_slice(&data)?));
+    index.unwrap_or_else(|err| {
+        log::warn!(
+            "Rebuilding the index, as {} cannot be read: {err}",
+            path.display()
+        );
+        Default::default()
+    })
+}
+
+fn save_index(path: &Path, index: &ChainIndex) -> anyhow::Result<()> {
+    let data = serde_json::to_vec(index)?;
+    files::write_atomic(path, &data)?;
+    Ok(())
+}
+
+impl Gateway {
+    /// Periodically index the events and oracles of the configured programs
+    pub async fn run_indexer(self: Arc<Self>) {
+        let config = &self.config.indexer;
+        if !config.enabled {
+            return;
+        }
+
+        let programs = if config.programs.is_empty() {
+            self.program_info(None)
+                .into_iter()
+                .map(|program| program.id)
+                .collect()
+        } else {
+            config.programs.clone()
+        };
+        let interval = Duration::from_secs(config.interval);
+        log::info!(
+            "Indexing {} every {}s",
+            programs.join(", "),
+            config.interval
+        );
+
+        loop {
+            for program in &programs {
+                if let Err(err) = self.index_program(program).await {
+                    log::warn!("Failed to index {program}: {err}");
+                }
+            }
+
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    async fn index_program(&self, program: &str) -> anyhow::Result<()> {
+        let (next_event, known_oracles) = {
+            let index = self.index.lock().unwrap();
+            index.get(program).map_or((0, 0), |index| {
+                (index.events.len() as u128, index.oracles.len() as u128)
+            })
+        };
+
+        let total_events = match self
+            .node
+            .get_mapping_value(program, "total_events", "0u8")
+            .await?
+        {
+            Some(total) => as_u128(&total)?,
+            None => 0,
+        };
+
+        let mut events = vec![];
+        for index in next_event..total_events.min(next_event + BATCH_SIZE) {
+            let Some(event_id) = self
+                .node
+                .get_mapping_value(program, "event_ids", &format!("{index}u128"))
+                .await?
+            else {
+                anyhow::bail!("event_ids[{index}] is missing");
+            };
+            let event_id = event_id.to_string();
+
+            let Some(event) = self
+                .node
+                .get_mapping_value(program, "events", &event_id)
+                .await?
+            else {
+                anyhow::bail!("events[{event_id}] is missing");
+            };
+
+            events.push(parse_event(index, event_id, &event)?);
+        }
+
+        // Older programs do not enumerate their oracles
+        let total_oracles = match self
+            .node
+            .get_mapping_value(program, "total_oracles", "0u8")
+            .await
+        {
+            Ok(Some(total)) => as_u128(&total)?,
+            Ok(None) => 0,
+            Err(err) => {
+                log::debug!("Cannot enumerate the oracles of {program}: {err}");
+                known_oracles
+            }
+        };
+
+        let mut addresses: Vec<String> = {
+            let index = self.index.lock().unwrap();
+            index.get(program).map_or(vec![], |index| {
+                index
+                    .oracles
+                    .iter()
+                    .map(|oracle| oracle.address.clone())
+                    .collect()
+            })
+        };
+        for index in known_oracles..total_oracles {
+            let Some(address) = self
+                .node
+                .get_mapping_value(
+                    program,
+                    "registered_oracles_addresses",
+                    &format!("{index}u128"),
+                )
+                .await?
+            else {
+                anyhow::bail!("registered_oracles_addresses[{index}] is missing");
+            };
+            addresses.push(address.to_string());
+        }
+
+        // Refresh all oracles, as registering again updates their data
+        let mut oracles = vec![];
+        for address in addresses {
+            if let Some(data) = self
+                .node
+                .get_mapping_value(program, "registered_oracles", &address)
+                .await?
+            {
+                oracles.push(parse_oracle(address, &data)?);
+            }
+        }
+
+        let own_address = self
+            .oracle_info
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|info| info.address.clone());
+        for event in &events {
+            let foreign = own_address.as_ref() != Some(&event.oracle);
+            if foreign {
+                log::info!(
+                    "Event {} on {program} was submitted by another oracle ({})",
+                    event.event_id,
+                    event.oracle
+                );
+            }
+            self.activity.publish(Activity::EventIndexed {
+                program: program.to_string(),
+                event_id: event.event_id.clone(),
+                oracle: event.oracle.clone(),
+                foreign,
+            });
+        }
+
+        let snapshot = {
+            let mut index = self.index.lock().unwrap();
+            let entry = index.entry(program.to_string()).or_default();
+            if !events.is_empty() {
+                log::debug!("Indexed {} new events of {program}", events.len());
+            }
+            entry.events.extend(events);
+            entry.oracles = oracles;
+            metrics::INDEXED_EVENTS
+                .with_label_values(&[program])
+                .set(entry.events.len().try_into().unwrap_or(i64::MAX));
+
+            self.config
+                .persistence
+                .index
+                .clone()
+                .map(|path| (path, index.clone()))
+        };
+
+        let Some((path, snapshot)) = snapshot else {
+            return Ok(());
+        };
+        match tokio::task::spawn_blocking(move || save_index(&path, &snapshot)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => log::error!("Failed to persist index: {err}"),
+            Err(err) => log::error!("Failed to persist index: {err}"),
+        }
+
+        Ok(())
+    }
+
+    fn indexed_program(&self, program: Option<&str>) -> Result<String, (StatusCode, String)> {
+        match program {
+            Some(program) => Ok(program.to_string()),
+            None => self
+                .program_info(None)
+                .map(|program| program.id)
+                .ok_or_else(|| {
+                    (
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        "oracle info not fetched yet".to_string(),
+                    )
+                }),
+        }
+    }
+
+    /// Lists indexed events of a program, optionally only those of one oracle
+    pub async fn index_events_handler(
+        &self,
+        Query(query): Query<IndexQuery>,
+    ) -> Result<Json<Vec<IndexedEvent>>, (StatusCode, String)> {
+        let program = self.indexed_program(query.program.as_deref())?;
+        let limit = query.limit.unwrap_or(100).min(MAX_LIMIT);
+
+        let index = self.index.lock().unwrap();
+        let Some(index) = index.get(&program) else {
+            return Err((StatusCode::NOT_FOUND, format!("{program} is not indexed")));
+        };
+
+        let events = index
+            .events
+            .iter()
+            .filter(|event| {
+                query
+                    .oracle
+                    .as_ref()
+                    .is_none_or(|oracle| &event.oracle == oracle)
+            })
+            .skip(query.offset)
+            .take(limit)
+            .cloned()
+            .collect();
+
+        Ok(Json(events))
+    }
+
+    /// Lists the oracles registered with a program
+    pub async fn index_oracles_handler(
+        &self,
+        Query(query): Query<IndexQuery>,
+    ) -> Result<Json<Vec<IndexedOracle>>, (StatusCode, String)> {
+        let program = self.indexed_program(query.program.as_deref())?;
+
+        let index = self.index.lock().unwrap();
+        let Some(index) = index.get(&program) else {
+            return Err((StatusCode::NOT_FOUND, format!("{program} is not indexed")));
+        };
+
+        Ok(Json(index.oracles.clone()))
+    }
+}
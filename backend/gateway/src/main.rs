@@ -1,3 +1,7 @@
+use std::collections::{HashSet, VecDeque};
+
+use std::path::Path;
+
 use std::sync::Arc;
 
 use std::sync::Mutex;
@@ -7,43 +11,186 @@ use anyhow::Context;
 use axum::response::Html;
 use axum::{
     Json, Router,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     routing::{get, post},
 };
 
+use clap::Parser;
+
 use serde::{Deserialize, Serialize};
 
-use snorkle_oracle_interface::OracleInfo;
+use snarkvm::prelude::TestnetV0;
+
+use snorkle_oracle_interface::{
+    BatchItem, FeeOptions, FetchProof, GameData, OracleError, OracleInfo, ProgramInfo, files,
+};
+
+mod admin;
+
+mod auth;
+use auth::RateLimiter;
+
+mod batch;
+
+mod config;
+use config::{Args, Config, RegistrationPolicy, Scope};
+
+mod events;
+use events::{Activity, ActivityFeed};
+
+mod fees;
+
+mod indexer;
+use indexer::ChainIndex;
+
+mod metrics;
+
+mod node;
+use node::Node;
 
 mod oracle;
 use oracle::Oracle;
 
+mod prices;
+use prices::PriceUpdates;
+
+mod proofs;
+use proofs::ProofView;
+
+mod prover;
+use prover::Prover;
+
+mod status;
+
+mod watcher;
+use watcher::WatcherState;
+
+mod webhooks;
+use webhooks::{FinalStatus, Notification, Webhooks};
+
+/// The Aleo network the gateway operates on
+type CurrentNetwork = TestnetV0;
+
 #[derive(Debug, Serialize, Deserialize)]
 struct SubmitRequest {
     game_id: String,
+    /// The program to submit to; the oracle's default program if not set
+    #[serde(default)]
+    program: Option<String>,
+    /// Submit a new event for a game that was already settled, e.g., to correct its score
+    #[serde(default)]
+    correction: bool,
+    /// Plaintext of a `credits.aleo` record owned by the oracle to pay the fee privately
+    #[serde(default)]
+    fee_record: Option<String>,
+    /// URL that is notified once the submission is confirmed or rejected
+    #[serde(default)]
+    callback_url: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct SubmitResult {
-    game_data: snorkle_oracle_interface::GameData,
+    program: String,
+    game_data: GameData,
     transaction: String,
+    transaction_id: String,
+    /// 0 for the original submission of a game, incremented for every correction
+    revision: u32,
+    /// Was the game already settled, i.e., is this an earlier submission?
+    existing: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct StatusResponse {
-    message: String,
+/// Where a submission stands before asking the oracle for it
+enum Prepared {
+    /// The game was already settled
+    Existing(SubmitResult),
+    /// The game needs to be submitted with this revision
+    Revision(u32),
 }
 
+/// A submission the oracle generated, ready to broadcast
+struct Generated {
+    game_data: GameData,
+    /// The proof-of-fetch of the game's data, recorded once the transaction is broadcast
+    proof: FetchProof,
+    transaction: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 struct RequestInfo {
     time: DateTime<Local>,
+    #[serde(default = "legacy_program")]
+    program: String,
     game_id: String,
+    revision: u32,
+    game_data: GameData,
+    transaction: String,
     transaction_id: String,
 }
 
+impl RequestInfo {
+    fn to_result(&self, existing: bool) -> SubmitResult {
+        SubmitResult {
+            program: self.program.clone(),
+            game_data: self.game_data.clone(),
+            transaction: self.transaction.clone(),
+            transaction_id: self.transaction_id.clone(),
+            revision: self.revision,
+            existing,
+        }
+    }
+
+    /// A notification about this submission, before its final status is known
+    fn notification(&self) -> Notification {
+        Notification {
+            program: self.program.clone(),
+            game_id: self.game_id.clone(),
+            revision: self.revision,
+            event_id: self.game_data.event_id.clone(),
+            home_score: self.game_data.home_score,
+            away_score: self.game_data.away_score,
+            transaction_id: self.transaction_id.clone(),
+            status: FinalStatus::Unconfirmed,
+        }
+    }
+}
+
 struct Gateway {
+    config: Config,
+    rate_limiter: RateLimiter,
     history: Mutex<Vec<RequestInfo>>,
+    /// Held while the history is persisted, so writes do not overtake each other
+    history_writes: tokio::sync::Mutex<()>,
+    /// Programs and games for which a submission is currently being generated
+    in_flight: Mutex<HashSet<(String, String)>>,
+    node: Node,
+    webhooks: Webhooks,
+    activity: ActivityFeed,
     oracle: Oracle,
+    /// Proves submissions outside the oracle; `None` if the oracle proves them
+    prover: Option<Prover>,
     oracle_info: Mutex<Option<OracleInfo>>,
+    /// Last known public balance of the oracle in microcredits
+    balance: Mutex<Option<u64>>,
+    prices: Mutex<PriceUpdates>,
+    watcher: Mutex<WatcherState>,
+    index: Mutex<ChainIndex>,
+    /// The latest proofs-of-fetch, if they are not persisted
+    proofs: Mutex<VecDeque<ProofView>>,
+    /// Metrics of the oracle as of the last refresh, in the Prometheus text format
+    oracle_metrics: Mutex<String>,
+}
+
+/// A submission in progress; see [`Gateway::claim`]
+struct InFlight<'a> {
+    in_flight: &'a Mutex<HashSet<(String, String)>>,
+    key: (String, String),
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.in_flight.lock().unwrap().remove(&self.key);
+    }
 }
 
 use chrono::{DateTime, Local};
@@ -61,8 +208,8 @@ impl Gateway {
                 .map(|info| {
                     let date_str = info.time.format("%a %b %e %T %Y").to_string();
                     format!(
-                        "<li>{date_str}: transaction_id={} game_id={}",
-                        info.transaction_id, info.game_id
+                        "<li>{date_str}: transaction_id={} program={} game_id={}",
+                        info.transaction_id, info.program, info.game_id
                     )
                 })
                 .collect::<Vec<String>>()
@@ -81,7 +228,32 @@ impl Gateway {
             <ul>
                 <li><b>/info</b> Show report data for the oracle</li>
                 <li><b>/submit</b> Ask the oracle to submit a new event</li>
+                <li><b>/submit/batch</b> Ask the oracle to submit several events at once</li>
+                <li><b>/status</b> Show the state of the oracle, its registration, and the node</li>
+                <li><b>/healthz</b> Liveness probe</li>
+                <li><b>/readyz</b> Readiness probe</li>
+                <li><b>/metrics</b> Prometheus metrics of the gateway and the oracle</li>
+                <li><b>/prices</b> Show the latest price pushed for each asset pair</li>
+                <li><b>/proofs/{{event_id}}</b> Show the signed response an event was read from</li>
+                <li><b>/events/stream</b> Live activity of the oracle as Server-Sent Events</li>
+                <li><b>/admin/register</b> Register the oracle with the contract (admin only)</li>
+                <li><b>/admin/config</b> Show the gateway's configuration (admin only)</li>
             </ul>
+            <h2>Live Activity</h2>
+            <ul id=\"activity\"></ul>
+            <script>
+                const source = new EventSource('/events/stream');
+                const kinds = ['submission_started', 'submission_broadcast', 'submission_failed',
+                    'registration', 'registration_failed', 'confirmation', 'price_update',
+                    'price_update_failed'];
+                for (const kind of kinds) {{
+                    source.addEventListener(kind, (event) => {{
+                        const item = document.createElement('li');
+                        item.textContent = event.data;
+                        document.getElementById('activity').prepend(item);
+                    }});
+                }}
+            </script>
             <h2>Event History</h2>
             {history_str}
             </ul>
@@ -98,6 +270,33 @@ impl Gateway {
         Ok(())
     }
 
+    /// All programs known to the oracle
+    fn programs(&self) -> Vec<ProgramInfo> {
+        self.oracle_info
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|info| info.programs.clone())
+            .unwrap_or_default()
+    }
+
+    /// Look up a program of the oracle, or its default program if `id` is `None`
+    fn program_info(&self, id: Option<&str>) -> Option<ProgramInfo> {
+        self.oracle_info
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|info| info.program(id).cloned())
+    }
+
+    /// Like [`Self::program_info`], but fails with a client error for unknown programs
+    fn resolve_program(&self, id: Option<&str>) -> Result<ProgramInfo, (StatusCode, String)> {
+        self.program_info(id).ok_or_else(|| {
+            let id = id.unwrap_or("default");
+            (StatusCode::BAD_REQUEST, format!("unknown program {id}"))
+        })
+    }
+
     async fn info_handler(&self) -> Result<Json<String>, StatusCode> {
         let info = self.oracle_info.lock().unwrap().clone().unwrap();
         let response = serde_json::to_string(&info).unwrap();
@@ -108,49 +307,444 @@ impl Gateway {
     /// Generate a new witness/statement through the oracle
     async fn submit_handler(
         &self,
+        headers: HeaderMap,
         request: Json<SubmitRequest>,
-    ) -> Result<Json<SubmitResult>, StatusCode> {
-        let (game_data, txn_str) = match self
-            .oracle
-            .generate_submission(request.game_id.clone())
+    ) -> Result<Json<SubmitResult>, (StatusCode, String)> {
+        let client = self
+            .authorize(&headers, Scope::Submit)
+            .map_err(|status| (status, status.to_string()))?;
+        if let Some(client) = client {
+            log::info!("Got submission request from `{client}`");
+        }
+
+        let program = self.resolve_program(request.program.as_deref())?;
+
+        self.check_callback(request.callback_url.as_deref()).await?;
+
+        self.submit_once(&program, &request).await.map(Json)
+    }
+
+    /// Make sure a callback URL in a request can be notified
+    async fn check_callback(&self, callback_url: Option<&str>) -> Result<(), (StatusCode, String)> {
+        if let Some(url) = callback_url {
+            if !self.webhooks.accepts_callbacks() {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "callbacks are not enabled on this gateway".to_string(),
+                ));
+            }
+            self.webhooks
+                .check_callback_url(url)
+                .await
+                .map_err(|err| (StatusCode::BAD_REQUEST, format!("{err:#}")))?;
+        }
+
+        Ok(())
+    }
+
+    /// Submit a game, unless a submission for it is already in progress
+    async fn submit_once(
+        &self,
+        program: &ProgramInfo,
+        request: &SubmitRequest,
+    ) -> Result<SubmitResult, (StatusCode, String)> {
+        // Only one submission per game and program at a time
+        let Some(_claim) = self.claim(program, &request.game_id) else {
+            log::warn!(
+                "Submission for game {} to {} already in progress",
+                request.game_id,
+                program.id
+            );
+            return Err((
+                StatusCode::CONFLICT,
+                "submission already in progress".to_string(),
+            ));
+        };
+
+        self.submit(program, request).await
+    }
+
+    /// Mark a submission of the game to `program` as in progress, unless it already is
+    ///
+    /// The submission stays in progress until the returned claim is dropped, also if the
+    /// request is abandoned midway.
+    fn claim(&self, program: &ProgramInfo, game_id: &str) -> Option<InFlight<'_>> {
+        let key = (program.id.clone(), game_id.to_string());
+        if !self.in_flight.lock().unwrap().insert(key.clone()) {
+            return None;
+        }
+
+        Some(InFlight {
+            in_flight: &self.in_flight,
+            key,
+        })
+    }
+
+    /// Has the game already been submitted to `program`?
+    fn is_settled(&self, program: &ProgramInfo, game_id: &str) -> bool {
+        self.history
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|info| info.program == program.id && info.game_id == game_id)
+    }
+
+    async fn submit(
+        &self,
+        program: &ProgramInfo,
+        request: &SubmitRequest,
+    ) -> Result<SubmitResult, (StatusCode, String)> {
+        let revision = match self.prepare_submission(program, request)? {
+            Prepared::Existing(result) => return Ok(result),
+            Prepared::Revision(revision) => revision,
+        };
+
+        let generated = self
+            .generate_submission(
+                program,
+                request.game_id.clone(),
+                revision,
+                self.fee_options(request.fee_record.clone()),
+            )
             .await
-        {
-            Ok(result) => result,
-            Err(err) => {
-                log::error!("Got error: {err}");
-                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            .map_err(|err| self.submission_failed(program, request, err))?;
+
+        self.broadcast_submission(program, request, revision, generated)
+            .await
+    }
+
+    /// Find the revision to submit, or the existing submission if the game was settled
+    ///
+    /// Publishes `submission_started` if a new submission is needed.
+    fn prepare_submission(
+        &self,
+        program: &ProgramInfo,
+        request: &SubmitRequest,
+    ) -> Result<Prepared, (StatusCode, String)> {
+        let revision = {
+            let history = self.history.lock().unwrap();
+            let latest = history
+                .iter()
+                .filter(|info| info.program == program.id && info.game_id == request.game_id)
+                .max_by_key(|info| info.revision);
+
+            match latest {
+                Some(info) if !request.correction => {
+                    log::info!(
+                        "Game {} was already settled on {} in transaction {}",
+                        request.game_id,
+                        program.id,
+                        info.transaction_id
+                    );
+                    metrics::SUBMISSIONS.with_label_values(&["duplicate"]).inc();
+
+                    // Only the caller asked about this submission again
+                    self.webhooks
+                        .notify(info.notification(), false, request.callback_url.clone());
+                    return Ok(Prepared::Existing(info.to_result(true)));
+                }
+                Some(info) => info.revision + 1,
+                None => 0,
             }
         };
 
-        log::info!("Issuing new 'submit_event' transaction");
+        if request.fee_record.is_none() && self.refuse_public_fee() {
+            log::warn!("Refusing submission: oracle balance is too low");
+            metrics::SUBMISSIONS
+                .with_label_values(&["low_balance"])
+                .inc();
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                "oracle balance is below the configured minimum".to_string(),
+            ));
+        }
+
+        self.activity.publish(Activity::SubmissionStarted {
+            program: program.id.clone(),
+            game_id: request.game_id.clone(),
+            revision,
+        });
+
+        Ok(Prepared::Revision(revision))
+    }
+
+    /// Record a failure of the oracle to generate a submission
+    fn submission_failed(
+        &self,
+        program: &ProgramInfo,
+        request: &SubmitRequest,
+        err: anyhow::Error,
+    ) -> (StatusCode, String) {
+        log::error!("Got error: {err}");
+
+        // Pre-flight checks of the oracle fail with specific errors
+        let status = match err.downcast_ref::<OracleError>() {
+            Some(OracleError::EventExists { .. }) => StatusCode::CONFLICT,
+            Some(OracleError::GameNotFinished(_)) => StatusCode::TOO_EARLY,
+            Some(OracleError::UnknownProgram(_) | OracleError::InvalidGameId(_)) => {
+                StatusCode::BAD_REQUEST
+            }
+            Some(
+                OracleError::NotRegistered
+                | OracleError::RegistrationExpired { .. }
+                | OracleError::FeeTooHigh { .. }
+                | OracleError::InsufficientBalance { .. },
+            ) => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let outcome = if status == StatusCode::INTERNAL_SERVER_ERROR {
+            "oracle_error"
+        } else {
+            "preflight_failed"
+        };
+        metrics::SUBMISSIONS.with_label_values(&[outcome]).inc();
+        self.publish_submission_failed(program, request, &err);
+
+        (status, err.to_string())
+    }
+
+    /// Broadcast a generated submission, and add it to the history and its proof to the
+    /// proofs
+    async fn broadcast_submission(
+        &self,
+        program: &ProgramInfo,
+        request: &SubmitRequest,
+        revision: u32,
+        generated: Generated,
+    ) -> Result<SubmitResult, (StatusCode, String)> {
+        let Generated {
+            game_data,
+            proof,
+            transaction: txn_str,
+        } = generated;
+        log::info!("Issuing new '{}' transaction", program.submit);
 
         let transaction_id = match self.issue_transaction(txn_str.clone()).await {
             Ok(txn_id) => txn_id,
             Err(err) => {
                 log::error!("Got error: {err}");
-                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                metrics::SUBMISSIONS
+                    .with_label_values(&["broadcast_error"])
+                    .inc();
+                self.publish_submission_failed(program, request, &err);
+                return Err((StatusCode::INTERNAL_SERVER_ERROR, err.to_string()));
             }
         };
 
         log::debug!("Successfully sent new transaction");
-        self.history.lock().unwrap().push(RequestInfo {
+        metrics::SUBMISSIONS.with_label_values(&["success"]).inc();
+        self.record_proof(&proof).await;
+        let info = RequestInfo {
             time: Local::now(),
+            program: program.id.clone(),
             game_id: request.game_id.clone(),
+            revision,
+            game_data,
+            transaction: txn_str,
             transaction_id,
+        };
+        self.activity.publish(Activity::SubmissionBroadcast {
+            program: info.program.clone(),
+            game_id: info.game_id.clone(),
+            revision,
+            transaction_id: info.transaction_id.clone(),
         });
+        let result = info.to_result(false);
+        self.webhooks
+            .notify(info.notification(), true, request.callback_url.clone());
+        self.record_submission(info).await;
+
+        Ok(result)
+    }
 
-        Ok(Json(SubmitResult {
+    /// Have the oracle generate a submission, and prove it outside the oracle if configured
+    async fn generate_submission(
+        &self,
+        program: &ProgramInfo,
+        game_id: String,
+        revision: u32,
+        fee: FeeOptions,
+    ) -> anyhow::Result<Generated> {
+        let Some(prover) = &self.prover else {
+            let (game_data, proof, transaction) = self
+                .oracle
+                .generate_submission(program.id.clone(), game_id, revision, fee)
+                .await?;
+
+            return Ok(Generated {
+                game_data,
+                proof,
+                transaction,
+            });
+        };
+
+        let (game_data, proof, authorization, fee_authorization) = self
+            .oracle
+            .authorize_submission(program.id.clone(), game_id, revision, fee)
+            .await?;
+
+        log::info!(
+            "Proving '{}' transaction outside the oracle",
+            program.submit
+        );
+        let transaction = prover.prove(&authorization, &fee_authorization).await?;
+
+        Ok(Generated {
             game_data,
-            transaction: txn_str,
-        }))
+            proof,
+            transaction,
+        })
     }
 
-    /// Register the oracle with the contract
-    pub async fn register(&self) -> anyhow::Result<()> {
-        let txn_str = self.oracle.generate_registration().await?;
+    /// Like [`Self::generate_submission`], but for several games at once
+    ///
+    /// Returns the outcome of each game, in the order of `games`. Authorizations are proven
+    /// concurrently, which only runs in parallel with a remote prover.
+    async fn generate_submissions(
+        &self,
+        program: &ProgramInfo,
+        games: Vec<BatchItem>,
+        fee: FeeOptions,
+    ) -> anyhow::Result<Vec<anyhow::Result<Generated>>> {
+        let Some(prover) = &self.prover else {
+            let results = self
+                .oracle
+                .generate_submissions(program.id.clone(), games, fee)
+                .await?;
+
+            return Ok(results
+                .into_iter()
+                .map(|result| {
+                    let submission = result?;
+                    Ok(Generated {
+                        game_data: submission.game_data,
+                        proof: submission.proof,
+                        transaction: submission.transaction,
+                    })
+                })
+                .collect());
+        };
+
+        let authorizations = self
+            .oracle
+            .authorize_submissions(program.id.clone(), games, fee)
+            .await?;
+
+        log::info!(
+            "Proving {} '{}' transactions outside the oracle",
+            authorizations.len(),
+            program.submit
+        );
+        let proofs = authorizations.into_iter().map(|result| async move {
+            let submission = result?;
+            let transaction = prover
+                .prove(&submission.authorization, &submission.fee_authorization)
+                .await?;
+            Ok(Generated {
+                game_data: submission.game_data,
+                proof: submission.proof,
+                transaction,
+            })
+        });
+
+        Ok(futures::future::join_all(proofs).await)
+    }
 
-        log::info!("Issuing new 'register' transaction");
-        self.issue_transaction(txn_str).await.map(|_| ())
+    fn publish_submission_failed(
+        &self,
+        program: &ProgramInfo,
+        request: &SubmitRequest,
+        err: &anyhow::Error,
+    ) {
+        self.activity.publish(Activity::SubmissionFailed {
+            program: program.id.clone(),
+            game_id: request.game_id.clone(),
+            error: err.to_string(),
+        });
+    }
+
+    /// Add a submission to the history and persist it, if configured
+    async fn record_submission(&self, info: RequestInfo) {
+        self.history.lock().unwrap().push(info);
+
+        let Some(path) = self.config.persistence.history.clone() else {
+            return;
+        };
+
+        // Snapshot only once it is our turn to write, so the last write has every submission
+        let _writing = self.history_writes.lock().await;
+        let snapshot = self.history.lock().unwrap().clone();
+        match tokio::task::spawn_blocking(move || save_history(&path, &snapshot)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => log::error!("Failed to persist history: {err}"),
+            Err(err) => log::error!("Failed to persist history: {err}"),
+        }
+    }
+
+    /// Register the oracle with every program, if the registration policy requires it
+    async fn register_on_startup(&self) -> anyhow::Result<()> {
+        if self.config.registration == RegistrationPolicy::Never {
+            log::info!("Skipping registration");
+            return Ok(());
+        }
+
+        for program in self.programs() {
+            if self.config.registration == RegistrationPolicy::IfNeeded {
+                let height = self.node.latest_height().await?;
+                if let Some(registration) = self.registration_status(&program, height).await?
+                    && registration.is_active()
+                {
+                    match registration.expires_at {
+                        Some(expires_at) => log::info!(
+                            "Oracle is registered with {} until block {expires_at}; skipping registration",
+                            program.id
+                        ),
+                        None => log::info!(
+                            "Oracle is registered with {}; skipping registration",
+                            program.id
+                        ),
+                    }
+                    continue;
+                }
+            }
+
+            log::info!("Registering oracle with {}", program.id);
+            self.register(&program).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Register the oracle with a program
+    ///
+    /// Returns the transaction id as a string
+    pub async fn register(&self, program: &ProgramInfo) -> anyhow::Result<String> {
+        let result = async {
+            let txn_str = self
+                .oracle
+                .generate_registration(program.id.clone(), self.fee_options(None))
+                .await?;
+
+            log::info!("Issuing new '{}' transaction", program.register);
+            self.issue_transaction(txn_str).await
+        }
+        .await;
+
+        match &result {
+            Ok(transaction_id) => {
+                self.activity.publish(Activity::Registration {
+                    program: program.id.clone(),
+                    transaction_id: transaction_id.clone(),
+                });
+                self.webhooks.track(transaction_id.clone());
+            }
+            Err(err) => self.activity.publish(Activity::RegistrationFailed {
+                program: program.id.clone(),
+                error: err.to_string(),
+            }),
+        }
+
+        result
     }
 
     /// Broadcast a transaction to the Aleo network
@@ -162,67 +756,169 @@ impl Gateway {
             serde_json::to_string(&txn).unwrap()
         );
 
-        let endpoint = "https://api.explorer.provable.com/v1/testnet";
-
-        log::info!("Broadcasting transaction to endpoint at {endpoint}");
+        self.node.broadcast(txn).await
+    }
+}
 
-        let api_client = reqwest::Client::new();
-        let response = api_client
-            .post(format!("{endpoint}/transaction/broadcast"))
-            .body(txn)
-            .header("Content-Type", "application/json")
-            .send()
-            .await?;
+/// Submissions recorded before programs could be selected all went to this program
+fn legacy_program() -> String {
+    "proto_snorkle_oracle_002.aleo".to_string()
+}
 
-        if response.status() != reqwest::StatusCode::OK {
-            anyhow::bail!(
-                "snarkOS REST API returned status code: {}",
-                response.status()
-            );
-        }
+fn load_history(path: &Path) -> anyhow::Result<Vec<RequestInfo>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
 
-        let txn_id = response.text().await?.replace("\"", "");
-        log::debug!("Transaction ID was: {txn_id}");
+    let data = std::fs::read(path)?;
+    Ok(serde_json::from_slice(&data)?)
+}
 
-        Ok(txn_id)
-    }
+fn save_history(path: &Path, history: &[RequestInfo]) -> anyhow::Result<()> {
+    let data = serde_json::to_vec(history)?;
+    files::write_atomic(path, &data)?;
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     env_logger::init();
 
-    let oracle = Oracle::new("localhost")
+    let config = Config::load(Args::parse())?;
+    log::debug!("Loaded configuration: {:?}", config.redacted());
+
+    let history = match &config.persistence.history {
+        Some(path) => load_history(path)
+            .with_context(|| format!("Failed to load history from {}", path.display()))?,
+        None => vec![],
+    };
+
+    let watcher = match &config.persistence.watcher {
+        Some(path) => watcher::load_state(path)
+            .with_context(|| format!("Failed to load watcher state from {}", path.display()))?,
+        None => Default::default(),
+    };
+
+    let index = match &config.persistence.index {
+        Some(path) => indexer::load_index(path),
+        None => Default::default(),
+    };
+
+    if let Some(dir) = &config.persistence.proofs {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create proofs directory {}", dir.display()))?;
+    }
+
+    let oracle = Oracle::new(&config.oracle)
         .await
         .with_context(|| "Failed to connect to oracle")?;
 
+    let node = Node::new(&config.network.node_url(), &config.network.broadcast_url());
+
+    let prover = Prover::new(&config.prover, &config.network.node_url())
+        .with_context(|| "Failed to initialize the prover")?;
+
+    let activity = ActivityFeed::default();
+
     let obj = Arc::new(Gateway {
+        webhooks: Webhooks::new(config.webhooks.clone(), node.clone(), activity.clone())
+            .with_context(|| "Failed to initialize webhooks")?,
+        activity,
+        node,
         oracle,
+        prover,
         oracle_info: Default::default(),
-        history: Default::default(),
+        history: Mutex::new(history),
+        history_writes: Default::default(),
+        in_flight: Default::default(),
+        rate_limiter: Default::default(),
+        balance: Default::default(),
+        prices: Default::default(),
+        watcher: Mutex::new(watcher),
+        index: Mutex::new(index),
+        proofs: Default::default(),
+        oracle_metrics: Default::default(),
+        config,
     });
     let obj1 = obj.clone();
     let obj2 = obj.clone();
     let obj3 = obj.clone();
+    let obj4 = obj.clone();
+    let obj5 = obj.clone();
+    let obj6 = obj.clone();
+    let obj7 = obj.clone();
+    let obj8 = obj.clone();
+    let obj9 = obj.clone();
+    let obj10 = obj.clone();
+    let obj11 = obj.clone();
+    let obj12 = obj.clone();
+    let obj13 = obj.clone();
+    let obj14 = obj.clone();
+    let obj15 = obj.clone();
 
     // Build our application with a route
     let app = Router::new()
         .route(
             "/submit",
-            post(async move |payload| obj1.submit_handler(payload).await),
+            post(async move |headers, payload| obj1.submit_handler(headers, payload).await),
+        )
+        .route(
+            "/submit/batch",
+            post(async move |headers, payload| obj14.submit_batch_handler(headers, payload).await),
         )
         .route("/info", get(async move || obj2.info_handler().await))
-        .route("/", get(async move || obj3.landing_handler().await));
+        .route("/", get(async move || obj3.landing_handler().await))
+        .route("/healthz", get(async move || obj4.healthz_handler().await))
+        .route("/readyz", get(async move || obj5.readyz_handler().await))
+        .route("/status", get(async move || obj6.status_handler().await))
+        .route("/metrics", get(async move || obj7.metrics_handler().await))
+        .route("/prices", get(async move || obj10.prices_handler().await))
+        .route(
+            "/events/stream",
+            get(async move || obj11.events_stream_handler().await),
+        )
+        .route(
+            "/chain/events",
+            get(async move |query| obj12.index_events_handler(query).await),
+        )
+        .route(
+            "/proofs/{event_id}",
+            get(async move |path| obj15.proof_handler(path).await),
+        )
+        .route(
+            "/chain/oracles",
+            get(async move |query| obj13.index_oracles_handler(query).await),
+        )
+        .route(
+            "/admin/register",
+            post(async move |headers, params| obj8.admin_register_handler(headers, params).await),
+        )
+        .route(
+            "/admin/config",
+            get(async move |headers| obj9.admin_config_handler(headers).await),
+        );
+
+    if obj.config.auth.open {
+        log::warn!("auth.open is set; anyone can submit and use the admin endpoints");
+    } else if obj.config.auth.keys.is_empty() {
+        log::warn!("No API keys configured; submission and admin endpoints reject every request");
+    }
 
     obj.fetch_oracle_info().await?;
 
-    log::info!("Registering oracle");
-    obj.register().await?;
+    obj.register_on_startup().await?;
 
-    // Run it with hyper on localhost:3000
-    let addr = "0.0.0.0:3000";
+    tokio::spawn(obj.clone().run_balance_monitor());
+    tokio::spawn(obj.clone().run_price_feed());
+    tokio::spawn(obj.clone().run_watcher());
+    tokio::spawn(obj.clone().run_indexer());
+    tokio::spawn(obj.clone().run_metrics_refresh());
 
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    let addr = obj.config.bind;
+
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind to {addr}"))?;
     println!("Frontend listening at http://{addr}");
 
     axum::serve(listener, app).await?;
@@ -0,0 +1,197 @@
+//! Prometheus metrics of the gateway
+
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+
+use crate::Gateway;
+use crate::status::RegistrationStatus;
+
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
+
+fn register<M: prometheus::core::Collector + Clone + 'static>(metric: M) -> M {
+    REGISTRY.register(Box::new(metric.clone())).unwrap();
+    metric
+}
+
+/// Calls to `/submit`, by outcome
+pub static SUBMISSIONS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    let opts = Opts::new(
+        "snorkle_gateway_submissions_total",
+        "Number of submission requests by outcome",
+    );
+    register(IntCounterVec::new(opts, &["outcome"]).unwrap())
+});
+
+/// Responses of the node to transaction broadcasts, by HTTP status code
+pub static BROADCASTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    let opts = Opts::new(
+        "snorkle_gateway_broadcasts_total",
+        "Number of broadcast transactions by HTTP status code",
+    );
+    register(IntCounterVec::new(opts, &["status"]).unwrap())
+});
+
+/// Requests rejected by authentication or rate limiting, by reason
+pub static REJECTED_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    let opts = Opts::new(
+        "snorkle_gateway_rejected_requests_total",
+        "Number of requests rejected by authentication or rate limiting",
+    );
+    register(IntCounterVec::new(opts, &["reason"]).unwrap())
+});
+
+pub static ORACLE_RECONNECTS: LazyLock<IntCounter> = LazyLock::new(|| {
+    let opts = Opts::new(
+        "snorkle_gateway_oracle_reconnects_total",
+        "Number of times the connection to the oracle was re-established",
+    );
+    register(IntCounter::with_opts(opts).unwrap())
+});
+
+pub static ORACLE_BALANCE: LazyLock<IntGauge> = LazyLock::new(|| {
+    let opts = Opts::new(
+        "snorkle_gateway_oracle_balance_microcredits",
+        "Public balance of the oracle's address",
+    );
+    register(IntGauge::with_opts(opts).unwrap())
+});
+
+pub static PRICE_UPDATES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    let opts = Opts::new(
+        "snorkle_gateway_price_updates_total",
+        "Price updates pushed by the gateway, by asset pair and outcome",
+    );
+    register(IntCounterVec::new(opts, &["pair", "outcome"]).unwrap())
+});
+
+pub static WATCHER_SUBMISSIONS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    let opts = Opts::new(
+        "snorkle_gateway_watcher_submissions_total",
+        "Submissions triggered by the watcher, by outcome",
+    );
+    register(IntCounterVec::new(opts, &["outcome"]).unwrap())
+});
+
+pub static WEBHOOK_DELIVERIES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    let opts = Opts::new(
+        "snorkle_gateway_webhook_deliveries_total",
+        "Notifications sent to webhooks, by outcome",
+    );
+    register(IntCounterVec::new(opts, &["outcome"]).unwrap())
+});
+
+pub static INDEXED_EVENTS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    let opts = Opts::new(
+        "snorkle_gateway_indexed_events",
+        "Events found on chain by the indexer, by program",
+    );
+    register(IntGaugeVec::new(opts, &["program"]).unwrap())
+});
+
+/// Time spent proving authorizations of the oracle, by where they were proven
+pub static PROVING_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    let opts = HistogramOpts::new(
+        "snorkle_gateway_proving_duration_seconds",
+        "Time spent proving a transaction outside the oracle",
+    )
+    .buckets(vec![1.0, 2.5, 5.0, 10.0, 20.0, 40.0, 80.0, 160.0]);
+    register(HistogramVec::new(opts, &["prover"]).unwrap())
+});
+
+pub static REGISTRATION_BLOCKS_REMAINING: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    let opts = Opts::new(
+        "snorkle_gateway_registration_blocks_remaining",
+        "Blocks left until the oracle's registration with a program expires",
+    );
+    register(IntGaugeVec::new(opts, &["program"]).unwrap())
+});
+
+impl Gateway {
+    /// Periodically update the metrics that require the node or the oracle
+    ///
+    /// Scrapes only read the results, so that they neither wait for the oracle (which may be
+    /// busy proving) nor add load to the node.
+    pub async fn run_metrics_refresh(self: Arc<Self>) {
+        let interval = Duration::from_secs(self.config.metrics.interval);
+
+        loop {
+            self.refresh_metrics().await;
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    async fn refresh_metrics(&self) {
+        match self.node.latest_height().await {
+            Ok(height) => {
+                for program in self.programs() {
+                    // Registrations without a window never expire; leave them out
+                    match self.registration_status(&program, height).await {
+                        Ok(Some(RegistrationStatus {
+                            blocks_remaining: None,
+                            ..
+                        })) => {}
+                        Ok(registration) => REGISTRATION_BLOCKS_REMAINING
+                            .with_label_values(&[&program.id])
+                            .set(
+                                registration
+                                    .and_then(|registration| registration.blocks_remaining)
+                                    .map_or(0, i64::from),
+                            ),
+                        Err(err) => log::warn!(
+                            "Failed to fetch registration status for {}: {err}",
+                            program.id
+                        ),
+                    }
+                }
+            }
+            Err(err) => log::warn!("Failed to reach node: {err}"),
+        }
+
+        match self.oracle.get_metrics().await {
+            Ok(oracle_metrics) => *self.oracle_metrics.lock().unwrap() = oracle_metrics,
+            Err(err) => log::warn!("Failed to fetch metrics from oracle: {err}"),
+        }
+    }
+
+    /// Serves the metrics of the gateway and the oracle in the Prometheus text format
+    pub async fn metrics_handler(&self) -> Result<Response, StatusCode> {
+        // Make sure every metric is registered, even if it has not been used yet
+        LazyLock::force(&SUBMISSIONS);
+        LazyLock::force(&BROADCASTS);
+        LazyLock::force(&REJECTED_REQUESTS);
+        LazyLock::force(&ORACLE_RECONNECTS);
+        LazyLock::force(&PRICE_UPDATES);
+        LazyLock::force(&WATCHER_SUBMISSIONS);
+        LazyLock::force(&WEBHOOK_DELIVERIES);
+        LazyLock::force(&INDEXED_EVENTS);
+        LazyLock::force(&PROVING_DURATION);
+
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&REGISTRY.gather(), &mut buffer)
+            .map_err(|err| {
+                log::error!("Failed to encode metrics: {err}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        let mut body = String::from_utf8(buffer).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        body.push_str(&self.oracle_metrics.lock().unwrap());
+
+        Ok((
+            [(
+                header::CONTENT_TYPE,
+                TextEncoder::new().format_type().to_string(),
+            )],
+            body,
+        )
+            .into_response())
+    }
+}
@@ -0,0 +1,166 @@
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use snarkvm::prelude::{Identifier, Literal, Plaintext};
+
+use crate::CurrentNetwork;
+use crate::metrics;
+
+/// Outcome of a transaction that made it into a block
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionStatus {
+    Accepted,
+    Rejected,
+}
+
+#[derive(Deserialize)]
+struct ConfirmedTransaction {
+    status: TransactionStatus,
+}
+
+/// Client for the REST API of an Aleo node
+#[derive(Clone)]
+pub struct Node {
+    endpoint: String,
+    broadcast_endpoint: String,
+    client: reqwest::Client,
+}
+
+impl Node {
+    /// Both endpoints must include the network, e.g., `https://api.explorer.provable.com/v1/testnet`
+    pub fn new(endpoint: &str, broadcast_endpoint: &str) -> Self {
+        Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            broadcast_endpoint: broadcast_endpoint.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Fetch the height of the latest block
+    pub async fn latest_height(&self) -> anyhow::Result<u32> {
+        let response = self
+            .client
+            .get(format!("{}/block/height/latest", self.endpoint))
+            .send()
+            .await?;
+
+        if response.status() != reqwest::StatusCode::OK {
+            anyhow::bail!(
+                "snarkOS REST API returned status code: {}",
+                response.status()
+            );
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Fetch the value stored under `key` in a program's mapping
+    ///
+    /// Returns `None` if there is no such entry
+    pub async fn get_mapping_value(
+        &self,
+        program: &str,
+        mapping: &str,
+        key: &str,
+    ) -> anyhow::Result<Option<Plaintext<CurrentNetwork>>> {
+        let response = self
+            .client
+            .get(format!(
+                "{}/program/{program}/mapping/{mapping}/{key}",
+                self.endpoint
+            ))
+            .send()
+            .await?;
+
+        if response.status() != reqwest::StatusCode::OK {
+            anyhow::bail!(
+                "snarkOS REST API returned status code: {}",
+                response.status()
+            );
+        }
+
+        let Some(value) = response.json::<Option<String>>().await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Plaintext::from_str(&value)?))
+    }
+
+    /// Look up whether a transaction was accepted or rejected
+    ///
+    /// Returns `None` if the transaction has not been included in a block (yet)
+    pub async fn transaction_status(
+        &self,
+        transaction_id: &str,
+    ) -> anyhow::Result<Option<TransactionStatus>> {
+        let response = self
+            .client
+            .get(format!(
+                "{}/transaction/confirmed/{transaction_id}",
+                self.endpoint
+            ))
+            .send()
+            .await?;
+
+        if response.status() != reqwest::StatusCode::OK {
+            log::debug!(
+                "Transaction {transaction_id} is not confirmed yet (status code {})",
+                response.status()
+            );
+            return Ok(None);
+        }
+
+        let confirmed: ConfirmedTransaction = response.json().await?;
+        Ok(Some(confirmed.status))
+    }
+
+    /// Broadcast a transaction to the Aleo network
+    ///
+    /// Returns the transaction id as a string
+    pub async fn broadcast(&self, txn: String) -> anyhow::Result<String> {
+        log::info!(
+            "Broadcasting transaction to endpoint at {}",
+            self.broadcast_endpoint
+        );
+
+        let response = self
+            .client
+            .post(format!("{}/transaction/broadcast", self.broadcast_endpoint))
+            .body(txn)
+            .header("Content-Type", "application/json")
+            .send()
+            .await?;
+
+        metrics::BROADCASTS
+            .with_label_values(&[response.status().as_str()])
+            .inc();
+
+        if response.status() != reqwest::StatusCode::OK {
+            anyhow::bail!(
+                "snarkOS REST API returned status code: {}",
+                response.status()
+            );
+        }
+
+        let txn_id = response.text().await?.replace("\"", "");
+        log::debug!("Transaction ID was: {txn_id}");
+
+        Ok(txn_id)
+    }
+}
+
+/// Read a `u32` member from a struct stored in a mapping
+pub fn get_u32_member(value: &Plaintext<CurrentNetwork>, member: &str) -> anyhow::Result<u32> {
+    let member = Identifier::from_str(member)?;
+
+    match value.find(&[member])? {
+        Plaintext::Literal(Literal::U32(value), _) => Ok(*value),
+        other => anyhow::bail!("Expected a u32 but got {other}"),
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
 use tokio::net::TcpStream;
 use tokio::sync::Mutex;
 use tokio_stream::StreamExt;
@@ -8,26 +10,56 @@ use anyhow::Context;
 use futures::sink::SinkExt;
 
 use snorkle_oracle_interface::{
-    BINCODE_CONFIG, GameData, ORACLE_PORT, OracleInfo, OracleRequest, OracleResponse,
+    BINCODE_CONFIG, BatchItem, FeeOptions, FetchProof, GameData, OracleError, OracleInfo,
+    OracleRequest, OracleResponse, PriceData, Submission, SubmissionAuthorization,
 };
 
 use bincode::serde::{decode_from_slice, encode_to_vec};
 
+use crate::metrics;
+
+type Connection = Framed<TcpStream, LengthDelimitedCodec>;
+
 /// Maintains a connection to the oracle
+///
+/// If the connection breaks, it is re-established on the next request.
 pub struct Oracle {
-    connection: Mutex<Framed<TcpStream, LengthDelimitedCodec>>,
+    address: String,
+    connection: Mutex<Option<Connection>>,
+    connected: AtomicBool,
+    /// Number of requests that are waiting for or being processed by the oracle
+    pending: AtomicUsize,
 }
 
 impl Oracle {
-    pub async fn new(hostname: &str) -> anyhow::Result<Self> {
-        let addr = format!("{hostname}:{ORACLE_PORT}");
-        log::trace!("Connecting to oracle at {addr}");
+    /// `address` has the form `host:port`
+    pub async fn new(address: &str) -> anyhow::Result<Self> {
+        let connection = Self::connect(address).await?;
+
+        println!("Connected to Oracle at {address}");
+        Ok(Self {
+            address: address.to_string(),
+            connection: Mutex::new(Some(connection)),
+            connected: AtomicBool::new(true),
+            pending: AtomicUsize::new(0),
+        })
+    }
 
-        let connection = TcpStream::connect(addr).await?;
-        let connection = Mutex::new(LengthDelimitedCodec::new().framed(connection));
+    async fn connect(address: &str) -> anyhow::Result<Connection> {
+        log::trace!("Connecting to oracle at {address}");
 
-        println!("Connected to Oracle at {hostname}");
-        Ok(Self { connection })
+        let connection = TcpStream::connect(address).await?;
+        Ok(LengthDelimitedCodec::new().framed(connection))
+    }
+
+    /// Was the last interaction with the oracle successful?
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    /// The number of requests currently queued at the oracle
+    pub fn queue_depth(&self) -> usize {
+        self.pending.load(Ordering::SeqCst)
     }
 
     pub async fn get_info(&self) -> anyhow::Result<OracleInfo> {
@@ -42,23 +74,126 @@ impl Oracle {
         Ok(info)
     }
 
-    pub async fn generate_submission(&self, game_id: String) -> anyhow::Result<(GameData, String)> {
-        let msg = OracleRequest::GenerateSubmission { game_id };
+    pub async fn generate_submission(
+        &self,
+        program: String,
+        game_id: String,
+        revision: u32,
+        fee: FeeOptions,
+    ) -> anyhow::Result<(GameData, FetchProof, String)> {
+        let msg = OracleRequest::GenerateSubmission {
+            program: Some(program),
+            game_id,
+            revision,
+            fee,
+        };
         let response = self.issue_request(msg).await?;
 
         let OracleResponse::Submission {
             transaction,
             game_data,
+            proof,
         } = response
         else {
             anyhow::bail!("Got invalid response");
         };
 
-        Ok((game_data, transaction))
+        Ok((game_data, proof, transaction))
     }
 
-    pub async fn generate_registration(&self) -> anyhow::Result<String> {
-        let msg = OracleRequest::GetRegistration;
+    /// Like [`Self::generate_submission`], but the oracle only signs
+    ///
+    /// Returns the game data, its proof-of-fetch, and the JSON-serialized authorizations of
+    /// the submission and its fee.
+    pub async fn authorize_submission(
+        &self,
+        program: String,
+        game_id: String,
+        revision: u32,
+        fee: FeeOptions,
+    ) -> anyhow::Result<(GameData, FetchProof, String, String)> {
+        let msg = OracleRequest::AuthorizeSubmission {
+            program: Some(program),
+            game_id,
+            revision,
+            fee,
+        };
+        let response = self.issue_request(msg).await?;
+
+        let OracleResponse::SubmissionAuthorization {
+            game_data,
+            authorization,
+            fee_authorization,
+            proof,
+        } = response
+        else {
+            anyhow::bail!("Got invalid response");
+        };
+
+        Ok((game_data, proof, authorization, fee_authorization))
+    }
+
+    /// Generate submissions for several games at once
+    ///
+    /// Returns the outcome of each game, in the order of `games`
+    pub async fn generate_submissions(
+        &self,
+        program: String,
+        games: Vec<BatchItem>,
+        fee: FeeOptions,
+    ) -> anyhow::Result<Vec<Result<Submission, OracleError>>> {
+        let num_games = games.len();
+        let msg = OracleRequest::GenerateSubmissions {
+            program: Some(program),
+            games,
+            fee,
+        };
+        let response = self.issue_request(msg).await?;
+
+        let OracleResponse::Submissions(results) = response else {
+            anyhow::bail!("Got invalid response");
+        };
+        if results.len() != num_games {
+            anyhow::bail!("Got {} results for {num_games} games", results.len());
+        }
+
+        Ok(results)
+    }
+
+    /// Like [`Self::generate_submissions`], but the oracle only signs
+    pub async fn authorize_submissions(
+        &self,
+        program: String,
+        games: Vec<BatchItem>,
+        fee: FeeOptions,
+    ) -> anyhow::Result<Vec<Result<SubmissionAuthorization, OracleError>>> {
+        let num_games = games.len();
+        let msg = OracleRequest::AuthorizeSubmissions {
+            program: Some(program),
+            games,
+            fee,
+        };
+        let response = self.issue_request(msg).await?;
+
+        let OracleResponse::SubmissionAuthorizations(results) = response else {
+            anyhow::bail!("Got invalid response");
+        };
+        if results.len() != num_games {
+            anyhow::bail!("Got {} results for {num_games} games", results.len());
+        }
+
+        Ok(results)
+    }
+
+    pub async fn generate_registration(
+        &self,
+        program: String,
+        fee: FeeOptions,
+    ) -> anyhow::Result<String> {
+        let msg = OracleRequest::GetRegistration {
+            program: Some(program),
+            fee,
+        };
         let response = self.issue_request(msg).await?;
 
         let OracleResponse::Registration(txn_str) = response else {
@@ -68,11 +203,81 @@ impl Oracle {
         Ok(txn_str)
     }
 
+    pub async fn generate_price_update(
+        &self,
+        program: String,
+        pair: String,
+        fee: FeeOptions,
+    ) -> anyhow::Result<(PriceData, String)> {
+        let msg = OracleRequest::GeneratePriceUpdate {
+            program: Some(program),
+            pair,
+            fee,
+        };
+        let response = self.issue_request(msg).await?;
+
+        let OracleResponse::PriceUpdate {
+            price_data,
+            transaction,
+        } = response
+        else {
+            anyhow::bail!("Got invalid response");
+        };
+
+        Ok((price_data, transaction))
+    }
+
+    /// Fetch the oracle's metrics in the Prometheus text format
+    pub async fn get_metrics(&self) -> anyhow::Result<String> {
+        let msg = OracleRequest::GetMetrics;
+        let response = self.issue_request(msg).await?;
+
+        let OracleResponse::Metrics(metrics) = response else {
+            anyhow::bail!("Got invalid response");
+        };
+
+        Ok(metrics)
+    }
+
     async fn issue_request(&self, msg: OracleRequest) -> anyhow::Result<OracleResponse> {
+        let _pending = Pending::new(&self.pending);
         let data = encode_to_vec(&msg, BINCODE_CONFIG)?;
 
-        let mut connection = self.connection.lock().await;
+        let mut guard = self.connection.lock().await;
+
+        // The connection is only put back once the exchange completes. If it fails, or the
+        // request is abandoned midway and the oracle's response is still in flight, the
+        // connection is dropped, so that the next request reconnects instead of reading the
+        // response to this one.
+        let mut connection = match guard.take() {
+            Some(connection) => connection,
+            None => {
+                log::info!("Reconnecting to oracle at {}", self.address);
+                let connection = Self::connect(&self.address).await.inspect_err(|_| {
+                    self.connected.store(false, Ordering::SeqCst);
+                })?;
+                metrics::ORACLE_RECONNECTS.inc();
+                connection
+            }
+        };
+
+        let result = Self::exchange(&mut connection, data).await;
+
+        if result.is_ok() {
+            *guard = Some(connection);
+        }
+        self.connected.store(result.is_ok(), Ordering::SeqCst);
+
+        match result? {
+            OracleResponse::Error(err) => Err(err.into()),
+            response => Ok(response),
+        }
+    }
 
+    async fn exchange(
+        connection: &mut Connection,
+        data: Vec<u8>,
+    ) -> anyhow::Result<OracleResponse> {
         log::trace!("Sending request to oracle");
         connection.send(data.into()).await?;
 
@@ -87,3 +292,19 @@ impl Oracle {
         }
     }
 }
+
+/// Counts a request as pending until it is dropped, also if the request is abandoned
+struct Pending<'a>(&'a AtomicUsize);
+
+impl<'a> Pending<'a> {
+    fn new(pending: &'a AtomicUsize) -> Self {
+        pending.fetch_add(1, Ordering::SeqCst);
+        Self(pending)
+    }
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::Json;
+
+use chrono::{DateTime, Local};
+
+use serde::{Deserialize, Serialize};
+
+use snorkle_oracle_interface::{PriceData, ProgramInfo};
+
+use crate::Gateway;
+use crate::events::Activity;
+use crate::metrics;
+
+/// The latest price pushed for an asset pair
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PriceUpdate {
+    pub time: DateTime<Local>,
+    pub program: String,
+    pub price_data: PriceData,
+    pub transaction_id: String,
+}
+
+/// Latest price updates, by asset pair
+pub type PriceUpdates = HashMap<String, PriceUpdate>;
+
+impl Gateway {
+    /// Lists the latest price pushed for each asset pair
+    pub async fn prices_handler(&self) -> Json<Vec<PriceUpdate>> {
+        let mut prices: Vec<_> = self.prices.lock().unwrap().values().cloned().collect();
+        prices.sort_by(|a, b| a.price_data.pair.cmp(&b.price_data.pair));
+
+        Json(prices)
+    }
+
+    /// Periodically push the prices of the configured asset pairs
+    pub async fn run_price_feed(self: Arc<Self>) {
+        let config = &self.config.prices;
+        if config.pairs.is_empty() {
+            return;
+        }
+
+        let Some(program) = self.program_info(config.program.as_deref()) else {
+            log::error!(
+                "Price feed disabled: unknown program {}",
+                config.program.as_deref().unwrap_or("default")
+            );
+            return;
+        };
+        if program.submit_price.is_none() {
+            log::error!("Price feed disabled: {} has no price feed", program.id);
+            return;
+        }
+
+        let interval = Duration::from_secs(config.interval);
+        log::info!(
+            "Pushing prices for {} to {} every {}s",
+            config.pairs.join(", "),
+            program.id,
+            config.interval
+        );
+
+        loop {
+            for pair in &config.pairs {
+                let outcome = match self.push_price(&program, pair).await {
+                    Ok(update) => {
+                        log::info!(
+                            "Pushed price {} for {pair} in transaction {}",
+                            update.price_data.price,
+                            update.transaction_id
+                        );
+                        self.activity.publish(Activity::PriceUpdate {
+                            program: update.program.clone(),
+                            pair: pair.clone(),
+                            price: update.price_data.price,
+                            transaction_id: update.transaction_id.clone(),
+                        });
+                        self.webhooks.track(update.transaction_id.clone());
+                        self.prices.lock().unwrap().insert(pair.clone(), update);
+                        "success"
+                    }
+                    Err((outcome, err)) => {
+                        log::error!("Failed to push price for {pair}: {err}");
+                        self.activity.publish(Activity::PriceUpdateFailed {
+                            pair: pair.clone(),
+                            error: err.to_string(),
+                        });
+                        outcome
+                    }
+                };
+                metrics::PRICE_UPDATES
+                    .with_label_values(&[pair, outcome])
+                    .inc();
+            }
+
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Fetch, sign, and broadcast the price of `pair`
+    ///
+    /// Fails with the outcome to record in the metrics and the error.
+    async fn push_price(
+        &self,
+        program: &ProgramInfo,
+        pair: &str,
+    ) -> Result<PriceUpdate, (&'static str, anyhow::Error)> {
+        if self.refuse_public_fee() {
+            return Err((
+                "low_balance",
+                anyhow::anyhow!("oracle balance is below the configured minimum"),
+            ));
+        }
+
+        let (price_data, txn_str) = self
+            .oracle
+            .generate_price_update(program.id.clone(), pair.to_string(), self.fee_options(None))
+            .await
+            .map_err(|err| ("oracle_error", err))?;
+
+        let transaction_id = self
+            .issue_transaction(txn_str)
+            .await
+            .map_err(|err| ("broadcast_error", err))?;
+
+        Ok(PriceUpdate {
+            time: Local::now(),
+            program: program.id.clone(),
+            price_data,
+            transaction_id,
+        })
+    }
+}
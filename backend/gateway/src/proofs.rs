@@ -0,0 +1,182 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use axum::{Json, extract, http::StatusCode};
+
+use serde::{Deserialize, Serialize};
+
+use snarkvm::prelude::{Address, Signature};
+
+use snorkle_oracle_interface::{FetchProof, files};
+
+use crate::{CurrentNetwork, Gateway};
+
+/// Number of proofs kept in memory if they are not persisted
+const MAX_PROOFS_IN_MEMORY: usize = 1000;
+
+/// A [`FetchProof`] with its binary fields hex-encoded
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProofView {
+    pub event_id: String,
+    pub url: String,
+    /// Unix time (in seconds) at which the response was received
+    pub timestamp: u64,
+    pub body: String,
+    /// SHA-256 of the body
+    pub body_hash: String,
+    /// The DER-encoded certificate chain presented by the server, leaf first
+    pub certificates: Vec<String>,
+    /// The digest the oracle signed
+    pub digest: String,
+    pub oracle: String,
+    pub signature: String,
+}
+
+impl From<&FetchProof> for ProofView {
+    fn from(proof: &FetchProof) -> Self {
+        Self {
+            event_id: proof.event_id.clone(),
+            url: proof.url.clone(),
+            timestamp: proof.timestamp,
+            body: hex::encode(&proof.body),
+            body_hash: hex::encode(proof.body_hash),
+            certificates: proof.certificates.iter().map(hex::encode).collect(),
+            digest: hex::encode(proof.digest()),
+            oracle: proof.oracle.clone(),
+            signature: proof.signature.clone(),
+        }
+    }
+}
+
+/// Check that a proof is consistent and signed by `oracle`
+fn verify_proof(proof: &FetchProof, oracle: &str) -> anyhow::Result<()> {
+    if proof.oracle != oracle {
+        anyhow::bail!("signed by {} instead of {oracle}", proof.oracle);
+    }
+    if !proof.body_matches() {
+        anyhow::bail!("body does not match its hash");
+    }
+
+    let address = Address::<CurrentNetwork>::from_str(&proof.oracle)?;
+    let signature = Signature::<CurrentNetwork>::from_str(&proof.signature)?;
+    if !signature.verify_bytes(&address, &proof.digest()) {
+        anyhow::bail!("invalid signature");
+    }
+
+    Ok(())
+}
+
+/// Event IDs are field elements, e.g., `123field`; anything else must not become a path
+fn is_valid_event_id(event_id: &str) -> bool {
+    !event_id.is_empty() && event_id.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn proof_path(dir: &Path, event_id: &str) -> PathBuf {
+    dir.join(format!("{event_id}.json"))
+}
+
+fn save_proof(dir: &Path, proof: &ProofView) -> anyhow::Result<()> {
+    let data = serde_json::to_vec(proof)?;
+    files::write_atomic(&proof_path(dir, &proof.event_id), &data)?;
+    Ok(())
+}
+
+fn load_proof(dir: &Path, event_id: &str) -> anyhow::Result<Option<ProofView>> {
+    match std::fs::read(proof_path(dir, event_id)) {
+        Ok(data) => Ok(Some(serde_json::from_slice(&data)?)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+impl Gateway {
+    /// Verify and store the proof-of-fetch of an event
+    ///
+    /// Proofs are written to `persistence.proofs` if configured; otherwise only the latest
+    /// ones are kept in memory.
+    pub async fn record_proof(&self, proof: &FetchProof) {
+        if self.oracle_info.lock().unwrap().is_none()
+            && let Err(err) = self.fetch_oracle_info().await
+        {
+            log::warn!("Failed to fetch oracle info: {err}");
+        }
+        let Some(oracle) = self
+            .oracle_info
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|info| info.address.clone())
+        else {
+            log::error!(
+                "Discarding proof for event {}: the oracle's address is unknown",
+                proof.event_id
+            );
+            return;
+        };
+
+        if let Err(err) = verify_proof(proof, &oracle) {
+            log::error!("Discarding proof for event {}: {err:#}", proof.event_id);
+            return;
+        }
+        if !is_valid_event_id(&proof.event_id) {
+            log::error!("Discarding proof for invalid event ID {}", proof.event_id);
+            return;
+        }
+
+        let view = ProofView::from(proof);
+        match &self.config.persistence.proofs {
+            Some(dir) => {
+                let dir = dir.clone();
+                let event_id = view.event_id.clone();
+                match tokio::task::spawn_blocking(move || save_proof(&dir, &view)).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(err)) => {
+                        log::error!("Failed to persist proof for event {event_id}: {err}")
+                    }
+                    Err(err) => log::error!("Failed to persist proof for event {event_id}: {err}"),
+                }
+            }
+            None => {
+                let mut proofs = self.proofs.lock().unwrap();
+                if proofs.len() >= MAX_PROOFS_IN_MEMORY {
+                    proofs.pop_front();
+                }
+                proofs.push_back(view);
+            }
+        }
+    }
+
+    /// The proof-of-fetch of an event, for auditing its data
+    pub async fn proof_handler(
+        &self,
+        extract::Path(event_id): extract::Path<String>,
+    ) -> Result<Json<ProofView>, (StatusCode, String)> {
+        if !is_valid_event_id(&event_id) {
+            return Err((StatusCode::BAD_REQUEST, "invalid event ID".to_string()));
+        }
+
+        let proof = match &self.config.persistence.proofs {
+            Some(dir) => load_proof(dir, &event_id).map_err(|err| {
+                log::error!("Failed to load proof for event {event_id}: {err}");
+                (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+            })?,
+            None => find_proof(&self.proofs.lock().unwrap(), &event_id),
+        };
+
+        proof.map(Json).ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("no proof for event {event_id}"),
+            )
+        })
+    }
+}
+
+fn find_proof(proofs: &VecDeque<ProofView>, event_id: &str) -> Option<ProofView> {
+    proofs
+        .iter()
+        .rev()
+        .find(|proof| proof.event_id == event_id)
+        .cloned()
+}
@@ -0,0 +1,111 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use snarkvm::prelude::{Authorization, Transaction};
+
+use snorkle_prover::ProveRequest;
+
+use crate::CurrentNetwork;
+use crate::config::{ProverConfig, ProvingMode};
+use crate::metrics;
+
+/// Proves submissions that the oracle only signed
+pub enum Prover {
+    /// Prove on the blocking thread pool, one transaction at a time
+    Local {
+        prover: Arc<snorkle_prover::Prover<CurrentNetwork>>,
+        lock: tokio::sync::Mutex<()>,
+    },
+    /// Send the authorizations to a prover service
+    Remote {
+        url: String,
+        token: String,
+        client: reqwest::Client,
+    },
+}
+
+impl Prover {
+    /// The prover for `config`, or `None` if the oracle proves by itself
+    ///
+    /// `node_url` must include the network.
+    pub fn new(config: &ProverConfig, node_url: &str) -> anyhow::Result<Option<Self>> {
+        let prover = match config.mode {
+            ProvingMode::Oracle => return Ok(None),
+            ProvingMode::Gateway => Self::Local {
+                prover: Arc::new(snorkle_prover::Prover::new(node_url)?),
+                lock: Default::default(),
+            },
+            ProvingMode::Remote => Self::Remote {
+                url: config
+                    .url
+                    .clone()
+                    .ok_or_else(|| anyhow::anyhow!("No prover URL configured"))?
+                    .trim_end_matches('/')
+                    .to_string(),
+                token: config
+                    .token
+                    .clone()
+                    .ok_or_else(|| anyhow::anyhow!("No prover token configured"))?,
+                client: reqwest::Client::builder()
+                    .connect_timeout(Duration::from_secs(config.connect_timeout))
+                    .timeout(Duration::from_secs(config.timeout))
+                    .build()?,
+            },
+        };
+
+        Ok(Some(prover))
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Local { .. } => "gateway",
+            Self::Remote { .. } => "remote",
+        }
+    }
+
+    /// Prove the JSON-serialized authorizations of a transition and its fee
+    ///
+    /// Returns the JSON-serialized transaction
+    pub async fn prove(
+        &self,
+        authorization: &str,
+        fee_authorization: &str,
+    ) -> anyhow::Result<String> {
+        let request = ProveRequest::<CurrentNetwork> {
+            authorization: Authorization::from_str(authorization)?,
+            fee_authorization: Some(Authorization::from_str(fee_authorization)?),
+        };
+
+        let timer = metrics::PROVING_DURATION
+            .with_label_values(&[self.name()])
+            .start_timer();
+        let transaction: Transaction<CurrentNetwork> = match self {
+            Self::Local { prover, lock } => {
+                let _guard = lock.lock().await;
+                let prover = prover.clone();
+                tokio::task::spawn_blocking(move || prover.prove(request)).await??
+            }
+            Self::Remote { url, token, client } => {
+                let response = client
+                    .post(format!("{url}/prove"))
+                    .bearer_auth(token)
+                    .json(&request)
+                    .send()
+                    .await?;
+
+                if !response.status().is_success() {
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    anyhow::bail!("Prover returned status code {status}: {body}");
+                }
+
+                response.json().await?
+            }
+        };
+        timer.observe_duration();
+
+        log::info!("Proved transaction {}", transaction.id());
+        Ok(serde_json::to_string(&transaction)?)
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+
+use axum::{Json, http::StatusCode};
+
+use chrono::{DateTime, Local};
+
+use serde::{Deserialize, Serialize};
+
+use snarkvm::prelude::{Literal, Plaintext};
+
+use snorkle_oracle_interface::ProgramInfo;
+
+use crate::Gateway;
+use crate::node::get_u32_member;
+
+/// The oracle's entry in the `registered_oracles` mapping of a program
+///
+/// The block heights are `None` if the program's registrations do not expire.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegistrationStatus {
+    /// Block height at which the oracle (last) registered
+    pub registered_at: Option<u32>,
+    /// Last block height at which the oracle may submit events
+    pub expires_at: Option<u32>,
+    /// Blocks left until the registration expires
+    pub blocks_remaining: Option<u32>,
+}
+
+impl RegistrationStatus {
+    /// Can the oracle still submit events?
+    pub fn is_active(&self) -> bool {
+        self.blocks_remaining.is_none_or(|blocks| blocks > 0)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmissionStatus {
+    pub time: DateTime<Local>,
+    pub game_id: String,
+    pub transaction_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatusResponse {
+    pub oracle_connected: bool,
+    pub oracle_address: Option<String>,
+    /// Number of requests waiting for the oracle
+    pub queue_depth: usize,
+    pub last_submission: Option<SubmissionStatus>,
+    pub node_endpoint: String,
+    pub node_reachable: bool,
+    pub block_height: Option<u32>,
+    /// Last known public balance of the oracle in microcredits
+    pub balance: Option<u64>,
+    pub balance_low: bool,
+    /// Registration per program; `None` if the oracle is not registered or the node
+    /// could not be reached
+    pub registrations: BTreeMap<String, Option<RegistrationStatus>>,
+}
+
+impl Gateway {
+    /// Liveness probe; succeeds as long as the process is serving requests
+    pub async fn healthz_handler(&self) -> &'static str {
+        "ok"
+    }
+
+    /// Readiness probe; succeeds if the oracle is connected, registered with the default
+    /// program, and within its registration window
+    pub async fn readyz_handler(&self) -> Result<&'static str, (StatusCode, String)> {
+        let not_ready = |msg: String| (StatusCode::SERVICE_UNAVAILABLE, msg);
+
+        if !self.oracle.is_connected() {
+            return Err(not_ready("oracle is not connected".to_string()));
+        }
+
+        let height = self
+            .node
+            .latest_height()
+            .await
+            .map_err(|err| not_ready(format!("node is not reachable: {err}")))?;
+
+        let Some(program) = self.program_info(None) else {
+            return Err(not_ready("oracle info not fetched yet".to_string()));
+        };
+
+        match self.registration_status(&program, height).await {
+            Ok(Some(registration)) if registration.is_active() => Ok("ready"),
+            Ok(Some(_)) => Err(not_ready("oracle registration expired".to_string())),
+            Ok(None) => Err(not_ready("oracle is not registered".to_string())),
+            Err(err) => Err(not_ready(format!(
+                "failed to fetch registration status: {err}"
+            ))),
+        }
+    }
+
+    /// Reports the state of the gateway, the oracle, and the node
+    pub async fn status_handler(&self) -> Json<StatusResponse> {
+        let oracle_address = self
+            .oracle_info
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|info| info.address.clone());
+
+        let last_submission = self
+            .history
+            .lock()
+            .unwrap()
+            .last()
+            .map(|info| SubmissionStatus {
+                time: info.time,
+                game_id: info.game_id.clone(),
+                transaction_id: info.transaction_id.clone(),
+            });
+
+        let block_height = match self.node.latest_height().await {
+            Ok(height) => Some(height),
+            Err(err) => {
+                log::warn!("Failed to reach node: {err}");
+                None
+            }
+        };
+
+        let mut registrations = BTreeMap::new();
+        for program in self.programs() {
+            let registration = match block_height {
+                Some(height) => self
+                    .registration_status(&program, height)
+                    .await
+                    .inspect_err(|err| {
+                        log::warn!(
+                            "Failed to fetch registration status for {}: {err}",
+                            program.id
+                        )
+                    })
+                    .ok()
+                    .flatten(),
+                None => None,
+            };
+            registrations.insert(program.id, registration);
+        }
+
+        Json(StatusResponse {
+            oracle_connected: self.oracle.is_connected(),
+            oracle_address,
+            queue_depth: self.oracle.queue_depth(),
+            last_submission,
+            node_endpoint: self.node.endpoint().to_string(),
+            node_reachable: block_height.is_some(),
+            block_height,
+            balance: *self.balance.lock().unwrap(),
+            balance_low: self.is_balance_low(),
+            registrations,
+        })
+    }
+
+    /// Look up the oracle in the `registered_oracles` mapping of `program`
+    pub async fn registration_status(
+        &self,
+        program: &ProgramInfo,
+        height: u32,
+    ) -> anyhow::Result<Option<RegistrationStatus>> {
+        let Some(address) = self
+            .oracle_info
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|info| info.address.clone())
+        else {
+            anyhow::bail!("Oracle info not fetched yet");
+        };
+
+        let Some(data) = self
+            .node
+            .get_mapping_value(&program.id, "registered_oracles", &address)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        // Older programs only store the attestation hash
+        if let Plaintext::Literal(Literal::Field(_), _) = data {
+            return Ok(Some(RegistrationStatus {
+                registered_at: None,
+                expires_at: None,
+                blocks_remaining: None,
+            }));
+        }
+
+        let registered_at = get_u32_member(&data, "registration_timestamp")?;
+        let expires_at = program
+            .registration_window
+            .map(|window| registered_at.saturating_add(window));
+
+        Ok(Some(RegistrationStatus {
+            registered_at: Some(registered_at),
+            expires_at,
+            blocks_remaining: expires_at.map(|expires_at| expires_at.saturating_sub(height)),
+        }))
+    }
+}
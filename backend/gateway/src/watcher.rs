@@ -0,0 +1,262 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+
+use serde::{Deserialize, Serialize};
+
+use snorkle_oracle_interface::{GAME_URL, ProgramInfo, files};
+
+use crate::metrics;
+use crate::{Gateway, SubmitRequest};
+
+/// A game as listed by the scores API
+#[derive(Debug, Deserialize)]
+struct Game {
+    id: String,
+    home_team_id: String,
+    away_team_id: String,
+    #[serde(default)]
+    league: Option<String>,
+    status: String,
+}
+
+/// Games the watcher has seen or given up on
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct WatcherState {
+    /// Games that are done as far as the watcher is concerned; `None` until the first poll
+    done: Option<HashSet<String>>,
+    /// Number of failed submissions per game
+    failures: HashMap<String, u32>,
+    /// Games without a league that were skipped, so that each is only logged once
+    #[serde(skip)]
+    unlabeled: HashSet<String>,
+}
+
+pub fn load_state(path: &Path) -> anyhow::Result<WatcherState> {
+    if !path.exists() {
+        return Ok(Default::default());
+    }
+
+    let data = std::fs::read(path)?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+fn save_state(path: &Path, state: &WatcherState) -> anyhow::Result<()> {
+    let data = serde_json::to_vec(state)?;
+    files::write_atomic(path, &data)?;
+    Ok(())
+}
+
+impl Gateway {
+    /// Periodically poll the scores API and submit games once they are final
+    pub async fn run_watcher(self: Arc<Self>) {
+        let config = &self.config.watcher;
+        if !config.enabled {
+            return;
+        }
+
+        let Some(program) = self.program_info(config.program.as_deref()) else {
+            log::error!(
+                "Watcher disabled: unknown program {}",
+                config.program.as_deref().unwrap_or("default")
+            );
+            return;
+        };
+
+        let client = reqwest::Client::new();
+        let interval = Duration::from_secs(config.interval);
+        log::info!(
+            "Watching for final games every {}s, submitting to {}",
+            config.interval,
+            program.id
+        );
+
+        loop {
+            if let Err(err) = self.poll_games(&client, &program).await {
+                log::warn!("Failed to poll games: {err}");
+            }
+            self.save_watcher_state().await;
+
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Persist which games are done, if configured
+    async fn save_watcher_state(&self) {
+        let Some(path) = self.config.persistence.watcher.clone() else {
+            return;
+        };
+        let state = self.watcher.lock().unwrap().clone();
+
+        match tokio::task::spawn_blocking(move || save_state(&path, &state)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => log::error!("Failed to persist watcher state: {err}"),
+            Err(err) => log::error!("Failed to persist watcher state: {err}"),
+        }
+    }
+
+    /// Is the game in one of the watched leagues and played by one of the watched teams?
+    fn is_watched(&self, game: &Game) -> bool {
+        let config = &self.config.watcher;
+
+        let league = match &game.league {
+            _ if config.leagues.is_empty() => true,
+            Some(league) => config.leagues.contains(league),
+            None => {
+                // The API does not say which league it is in, so it cannot be watched
+                if self
+                    .watcher
+                    .lock()
+                    .unwrap()
+                    .unlabeled
+                    .insert(game.id.clone())
+                {
+                    log::warn!(
+                        "Skipping game {}, which has no league, as `watcher.leagues` is set",
+                        game.id
+                    );
+                }
+                false
+            }
+        };
+        let team = config.teams.is_empty()
+            || config.teams.contains(&game.home_team_id)
+            || config.teams.contains(&game.away_team_id);
+
+        league && team
+    }
+
+    fn is_final(&self, game: &Game) -> bool {
+        self.config
+            .watcher
+            .final_statuses
+            .iter()
+            .any(|status| status.eq_ignore_ascii_case(&game.status))
+    }
+
+    async fn poll_games(
+        &self,
+        client: &reqwest::Client,
+        program: &ProgramInfo,
+    ) -> anyhow::Result<()> {
+        let games: Vec<Game> = client
+            .get(GAME_URL)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let finals: Vec<_> = games
+            .into_iter()
+            .filter(|game| self.is_watched(game) && self.is_final(game))
+            .collect();
+
+        {
+            let mut state = self.watcher.lock().unwrap();
+            if state.done.is_none() {
+                // Only games that finish from now on are submitted, unless backfilling
+                let done = if self.config.watcher.backfill {
+                    HashSet::new()
+                } else {
+                    log::info!("Ignoring {} games that are already final", finals.len());
+                    finals.iter().map(|game| game.id.clone()).collect()
+                };
+                state.done = Some(done);
+            }
+        }
+
+        for game in finals {
+            let skip = {
+                let state = self.watcher.lock().unwrap();
+                state
+                    .done
+                    .as_ref()
+                    .is_some_and(|done| done.contains(&game.id))
+            };
+            if skip || self.is_settled(program, &game.id) {
+                continue;
+            }
+
+            log::info!("Game {} is final; submitting", game.id);
+            let request = SubmitRequest {
+                game_id: game.id.clone(),
+                program: Some(program.id.clone()),
+                correction: false,
+                fee_record: None,
+                callback_url: None,
+            };
+
+            let outcome = match self.submit_once(program, &request).await {
+                Ok(result) => {
+                    log::info!(
+                        "Submitted game {} in transaction {}",
+                        game.id,
+                        result.transaction_id
+                    );
+                    self.finish_game(&game.id);
+                    "success"
+                }
+                Err((StatusCode::CONFLICT, _))
+                    if self
+                        .in_flight
+                        .lock()
+                        .unwrap()
+                        .contains(&(program.id.clone(), game.id.clone())) =>
+                {
+                    // Someone else is submitting it right now; check again on the next poll
+                    "in_progress"
+                }
+                Err((StatusCode::TOO_EARLY, msg)) => {
+                    // The scores API is ahead of the oracle's source; check again on the next poll
+                    log::info!("Game {} is not final at the oracle yet: {msg}", game.id);
+                    "not_finished"
+                }
+                Err((StatusCode::CONFLICT, msg)) => {
+                    log::info!("Game {} is already on chain: {msg}", game.id);
+                    self.finish_game(&game.id);
+                    "exists"
+                }
+                Err((_, msg)) => {
+                    let mut state = self.watcher.lock().unwrap();
+                    let failures = state.failures.entry(game.id.clone()).or_default();
+                    *failures += 1;
+
+                    if *failures >= self.config.watcher.max_attempts {
+                        log::error!(
+                            "Giving up on game {} after {failures} failed submissions: {msg}",
+                            game.id
+                        );
+                        state.failures.remove(&game.id);
+                        state.done.get_or_insert_default().insert(game.id.clone());
+                        "gave_up"
+                    } else {
+                        log::warn!(
+                            "Failed to submit game {} (attempt {failures}); retrying on the next poll: {msg}",
+                            game.id
+                        );
+                        "failed"
+                    }
+                }
+            };
+            metrics::WATCHER_SUBMISSIONS
+                .with_label_values(&[outcome])
+                .inc();
+        }
+
+        Ok(())
+    }
+
+    /// Never submit the game again
+    fn finish_game(&self, game_id: &str) {
+        let mut state = self.watcher.lock().unwrap();
+        state.failures.remove(game_id);
+        state
+            .done
+            .get_or_insert_default()
+            .insert(game_id.to_string());
+    }
+}
@@ -0,0 +1,383 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+
+use hmac::{Hmac, Mac};
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+use serde::Serialize;
+
+use sha2::Sha256;
+
+use crate::config::WebhookConfig;
+use crate::events::{Activity, ActivityFeed};
+use crate::metrics;
+use crate::node::{Node, TransactionStatus};
+
+/// Header that carries the signature of a notification
+pub const SIGNATURE_HEADER: &str = "X-Snorkle-Signature";
+
+/// Header that carries the Unix time at which a notification was signed
+pub const TIMESTAMP_HEADER: &str = "X-Snorkle-Timestamp";
+
+/// Final state of a submission, as reported to webhooks
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FinalStatus {
+    Accepted,
+    Rejected,
+    /// The transaction was not included in a block within the confirmation timeout
+    Unconfirmed,
+}
+
+/// Body of the POST request sent to webhooks once a submission is final
+#[derive(Clone, Debug, Serialize)]
+pub struct Notification {
+    pub program: String,
+    pub game_id: String,
+    pub revision: u32,
+    pub event_id: String,
+    pub home_score: u8,
+    pub away_score: u8,
+    pub transaction_id: String,
+    pub status: FinalStatus,
+}
+
+/// The hex-encoded HMAC-SHA256 of `<timestamp>.<body>`, keyed with `secret`
+fn sign(secret: &str, timestamp: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Can `ip` be reached on the public internet?
+///
+/// Callbacks must not reach the gateway's own network, such as the node, the oracle, or
+/// cloud metadata services.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // Shared address space (RFC 6598) and `0.0.0.0/8`
+                || (a == 100 && (64..128).contains(&b))
+                || a == 0)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+/// Resolves host names to their public addresses only, so that a callback URL cannot be
+/// pointed at an internal address after it was checked
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addrs: Vec<_> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| is_public(addr.ip()))
+                .collect();
+
+            if addrs.is_empty() {
+                return Err(format!("{host} does not resolve to a public address").into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Make sure a callback URL is a valid HTTP(S) URL that resolves to a public address
+async fn check_public_url(url: &str) -> anyhow::Result<()> {
+    crate::config::validate_url(url)?;
+
+    let parsed = reqwest::Url::parse(url)?;
+    let host = parsed.host_str().unwrap_or_default();
+    let public = match host.trim_start_matches('[').trim_end_matches(']').parse() {
+        Ok(ip) => is_public(ip),
+        Err(_) => tokio::net::lookup_host((host, 0))
+            .await
+            .with_context(|| format!("Cannot resolve {host}"))?
+            .any(|addr| is_public(addr.ip())),
+    };
+
+    if !public {
+        anyhow::bail!("`{url}` does not point to a public address");
+    }
+
+    Ok(())
+}
+
+/// Tracks broadcast transactions until they are confirmed, and delivers notifications about
+/// submissions to webhooks
+#[derive(Clone)]
+pub struct Webhooks {
+    config: WebhookConfig,
+    node: Node,
+    /// Client for the configured webhooks, which the operator trusts
+    client: reqwest::Client,
+    /// Client for callback URLs from requests, which only reaches public addresses
+    callback_client: reqwest::Client,
+    activity: ActivityFeed,
+}
+
+impl Webhooks {
+    pub fn new(config: WebhookConfig, node: Node, activity: ActivityFeed) -> anyhow::Result<Self> {
+        let timeout = Duration::from_secs(config.timeout);
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+
+        // Redirects and proxies would bypass the resolver
+        let mut callback_client = reqwest::Client::builder()
+            .timeout(timeout)
+            .redirect(reqwest::redirect::Policy::none())
+            .no_proxy();
+        if !config.allow_private_callbacks {
+            callback_client = callback_client.dns_resolver(Arc::new(PublicResolver));
+        }
+
+        Ok(Self {
+            config,
+            node,
+            client,
+            callback_client: callback_client.build()?,
+            activity,
+        })
+    }
+
+    /// Report the confirmation of a transaction to the activity feed, in the background
+    pub fn track(&self, transaction_id: String) {
+        let webhooks = self.clone();
+        tokio::spawn(async move {
+            webhooks.wait_for_confirmation(&transaction_id).await;
+        });
+    }
+
+    /// Are per-request callbacks accepted?
+    pub fn accepts_callbacks(&self) -> bool {
+        self.config.secret.is_some()
+    }
+
+    /// Make sure a callback URL from a request can be notified, and does not point to the
+    /// gateway's own network (unless `allow_private_callbacks` is set)
+    pub async fn check_callback_url(&self, url: &str) -> anyhow::Result<()> {
+        if self.config.allow_private_callbacks {
+            crate::config::validate_url(url)
+        } else {
+            check_public_url(url).await
+        }
+    }
+
+    /// Wait until the submission is final and notify the global webhooks (if `global` is set)
+    /// and `callback`, in the background
+    ///
+    /// New submissions (`global`) are always tracked, so that their confirmation shows up in
+    /// the activity feed.
+    pub fn notify(&self, notification: Notification, global: bool, callback: Option<String>) {
+        let urls = if global {
+            self.config.urls.clone()
+        } else {
+            vec![]
+        };
+
+        if urls.is_empty() && callback.is_none() && !global {
+            return;
+        }
+
+        let webhooks = self.clone();
+        tokio::spawn(async move {
+            let mut notification = notification;
+            notification.status = webhooks
+                .wait_for_confirmation(&notification.transaction_id)
+                .await;
+
+            let body = match serde_json::to_vec(&notification) {
+                Ok(body) => body,
+                Err(err) => {
+                    log::error!("Failed to serialize notification: {err}");
+                    return;
+                }
+            };
+
+            for url in urls {
+                webhooks.deliver(&webhooks.client, &url, &body).await;
+            }
+            if let Some(url) = callback {
+                // Addresses given as IPs are not resolved, so check them again
+                if let Err(err) = webhooks.check_callback_url(&url).await {
+                    log::warn!("Not notifying callback: {err:#}");
+                    return;
+                }
+                webhooks
+                    .deliver(&webhooks.callback_client, &url, &body)
+                    .await;
+            }
+        });
+    }
+
+    async fn wait_for_confirmation(&self, transaction_id: &str) -> FinalStatus {
+        let status = self.poll_confirmation(transaction_id).await;
+        self.activity.publish(Activity::Confirmation {
+            transaction_id: transaction_id.to_string(),
+            status,
+        });
+
+        status
+    }
+
+    async fn poll_confirmation(&self, transaction_id: &str) -> FinalStatus {
+        let timeout = Duration::from_secs(self.config.confirmation_timeout);
+        let interval = Duration::from_secs(self.config.poll_interval);
+        let start = tokio::time::Instant::now();
+
+        while start.elapsed() < timeout {
+            match self.node.transaction_status(transaction_id).await {
+                Ok(Some(TransactionStatus::Accepted)) => return FinalStatus::Accepted,
+                Ok(Some(TransactionStatus::Rejected)) => return FinalStatus::Rejected,
+                Ok(None) => {}
+                Err(err) => log::warn!("Failed to fetch status of {transaction_id}: {err}"),
+            }
+
+            tokio::time::sleep(interval).await;
+        }
+
+        log::warn!("Transaction {transaction_id} was not confirmed within {timeout:?}");
+        FinalStatus::Unconfirmed
+    }
+
+    /// POST the notification to `url`, retrying with exponential backoff
+    async fn deliver(&self, client: &reqwest::Client, url: &str, body: &[u8]) {
+        let mut delay = Duration::from_secs(self.config.retry_delay);
+
+        for attempt in 1..=self.config.max_attempts {
+            match self.send(client, url, body).await {
+                Ok(()) => {
+                    log::info!("Delivered notification to {url}");
+                    metrics::WEBHOOK_DELIVERIES
+                        .with_label_values(&["success"])
+                        .inc();
+                    return;
+                }
+                Err(err) => {
+                    log::warn!(
+                        "Failed to deliver notification to {url} (attempt {attempt}): {err}"
+                    );
+                }
+            }
+
+            if attempt < self.config.max_attempts {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+
+        log::error!(
+            "Giving up on notifying {url} after {} attempts",
+            self.config.max_attempts
+        );
+        metrics::WEBHOOK_DELIVERIES
+            .with_label_values(&["failed"])
+            .inc();
+    }
+
+    async fn send(&self, client: &reqwest::Client, url: &str, body: &[u8]) -> anyhow::Result<()> {
+        let mut request = client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .body(body.to_vec());
+
+        // The signature covers the timestamp, so that old notifications cannot be replayed
+        if let Some(secret) = &self.config.secret {
+            let timestamp = chrono::Utc::now().timestamp().to_string();
+            let signature = sign(secret, &timestamp, body);
+
+            request = request
+                .header(TIMESTAMP_HEADER, timestamp)
+                .header(SIGNATURE_HEADER, format!("sha256={signature}"));
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            anyhow::bail!("webhook returned status code {}", response.status());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_are_hmac_sha256_of_the_timestamp_and_body() {
+        assert_eq!(
+            sign("secret", "1700000000", br#"{"status":"accepted"}"#),
+            "5a47a51b9b5bfd426062e07f66fab7a0111ada92a49ef9c5551116e251a5b0c8"
+        );
+    }
+
+    #[test]
+    fn internal_addresses_are_not_public() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip} should not be public");
+        }
+
+        for ip in ["1.1.1.1", "8.8.8.8", "2606:4700:4700::1111"] {
+            assert!(is_public(ip.parse().unwrap()), "{ip} should be public");
+        }
+    }
+
+    #[tokio::test]
+    async fn callbacks_to_internal_addresses_are_refused() {
+        for url in [
+            "http://127.0.0.1:3000/",
+            "http://[::1]/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://localhost/",
+            "ftp://1.1.1.1/",
+        ] {
+            assert!(
+                check_public_url(url).await.is_err(),
+                "{url} should be refused"
+            );
+        }
+
+        assert!(check_public_url("https://1.1.1.1/hook").await.is_ok());
+    }
+}
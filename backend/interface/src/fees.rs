@@ -0,0 +1,53 @@
+//! Fee estimates for transactions that have not been proven yet
+//!
+//! Authorizing a fee requires the base fee, which snarkVM computes from the size of the
+//! proven execution. Oracles that only authorize cannot wait for the proof, so the proof's
+//! size is bounded instead.
+
+use snarkvm::prelude::{Authorization, Execution, Network, Process};
+use snarkvm::synthesizer::program::StackProgram;
+
+/// Upper bound for the size (in bytes) of the fixed part of an execution proof
+///
+/// This covers the Varuna commitments and evaluations that every proof has, and the
+/// polynomial commitment proof.
+const PROOF_BASE_SIZE: u64 = 1_024;
+
+/// Upper bound for the size (in bytes) a transition adds to an execution proof
+///
+/// Each transition is one instance of a circuit, which adds at most four commitments and
+/// seven field elements.
+const PROOF_SIZE_PER_TRANSITION: u64 = 512;
+
+/// Storage cost of an execution of `size` bytes, as charged by the network
+fn storage_cost<N: Network>(size: u64) -> u64 {
+    if size > N::EXECUTION_STORAGE_PENALTY_THRESHOLD {
+        size.saturating_mul(size)
+            .saturating_div(N::EXECUTION_STORAGE_FEE_SCALING_FACTOR)
+    } else {
+        size
+    }
+}
+
+/// Estimate the base fee (in microcredits) of an authorized execution, without proving it
+///
+/// The estimate is exact except for the proof, whose size is bounded from above, so it never
+/// falls short of the fee the network charges.
+pub fn estimate_base_fee<N: Network>(
+    process: &Process<N>,
+    authorization: &Authorization<N>,
+) -> anyhow::Result<u64> {
+    let transitions = authorization.transitions();
+    let execution = Execution::from(transitions.values().cloned(), Default::default(), None)?;
+
+    let proof_size = PROOF_BASE_SIZE
+        .saturating_add(PROOF_SIZE_PER_TRANSITION.saturating_mul(transitions.len() as u64));
+    let size = execution.size_in_bytes()?.saturating_add(proof_size);
+
+    let root = execution.peek()?;
+    let finalize_cost = process
+        .get_stack(root.program_id())?
+        .get_finalize_cost(root.function_name())?;
+
+    Ok(storage_cost::<N>(size).saturating_add(finalize_cost))
+}
@@ -0,0 +1,27 @@
+//! Helpers for the files the oracle and the gateway persist their state to
+
+use std::io::Write;
+use std::path::Path;
+
+/// Replace the contents of `path` with `data`, so that a crash leaves either the old or the
+/// new contents behind
+///
+/// The file is only readable by its owner, as it may hold key material or the operator's
+/// records.
+pub fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(&tmp_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    drop(file);
+
+    std::fs::rename(&tmp_path, path)
+}
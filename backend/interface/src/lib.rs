@@ -1,7 +1,16 @@
 use serde::{Deserialize, Serialize};
 
+use sha2::{Digest, Sha256};
+
+pub mod fees;
+
+pub mod files;
+
 pub const ORACLE_PORT: u16 = 54541;
 
+/// The scores API that game results are fetched from
+pub const GAME_URL: &str = "https://scores-api-349861721231.northamerica-northeast1.run.app/games";
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GameData {
     pub event_id: String,
@@ -9,17 +18,221 @@ pub struct GameData {
     pub home_score: u8,
 }
 
+/// Evidence of what the data source returned when the oracle fetched an event
+///
+/// The oracle signs [`FetchProof::digest`] with its key, so that disputes about an event can
+/// be audited against the raw response it was derived from.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FetchProof {
+    pub event_id: String,
+    /// The URL that was fetched
+    pub url: String,
+    /// Unix time (in seconds) at which the response was received
+    pub timestamp: u64,
+    /// The raw response body
+    pub body: Vec<u8>,
+    /// SHA-256 of `body`
+    pub body_hash: [u8; 32],
+    /// The DER-encoded certificate chain presented by the server, leaf first
+    pub certificates: Vec<Vec<u8>>,
+    /// The oracle's address
+    pub oracle: String,
+    /// The oracle's signature on [`FetchProof::digest`]
+    pub signature: String,
+}
+
+impl FetchProof {
+    /// Separates the digest from other data signed by the oracle
+    const DOMAIN: &[u8] = b"snorkle/proof-of-fetch/v1";
+
+    /// The SHA-256 digest that the oracle signs
+    ///
+    /// It commits to every field except the signature, and to the body and certificates
+    /// through their hashes.
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        let mut field = |bytes: &[u8]| {
+            hasher.update((bytes.len() as u64).to_le_bytes());
+            hasher.update(bytes);
+        };
+
+        field(Self::DOMAIN);
+        field(self.event_id.as_bytes());
+        field(self.url.as_bytes());
+        field(&self.timestamp.to_le_bytes());
+        field(&self.body_hash);
+        field(self.oracle.as_bytes());
+        for certificate in &self.certificates {
+            field(&Sha256::digest(certificate));
+        }
+
+        hasher.finalize().into()
+    }
+
+    /// The SHA-256 hash of a response body, as stored in `body_hash`
+    pub fn hash_body(body: &[u8]) -> [u8; 32] {
+        Sha256::digest(body).into()
+    }
+
+    /// Whether `body_hash` matches `body`
+    pub fn body_matches(&self) -> bool {
+        Self::hash_body(&self.body) == self.body_hash
+    }
+}
+
+/// Number of decimal places of [`PriceData::price`]
+pub const PRICE_DECIMALS: u32 = 8;
+
+/// The price of an asset pair, aggregated from one or more sources
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PriceData {
+    /// The asset pair as `BASE/QUOTE`, e.g., `ALEO/USD`
+    pub pair: String,
+    /// The price of one unit of the base asset in the quote asset, as a fixed-point
+    /// integer with [`PRICE_DECIMALS`] decimal places
+    pub price: u128,
+    /// Unix time (in seconds) at which the price was observed
+    pub timestamp: u64,
+    /// Names of the sources the price was aggregated from
+    pub sources: Vec<String>,
+}
+
+/// An oracle program the oracle can submit to
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProgramInfo {
+    /// The program ID, e.g., `proto_snorkle_oracle_002.aleo`
+    pub id: String,
+    /// IDs of the programs it imports
+    pub imports: Vec<String>,
+    /// The transition that registers an oracle
+    pub register: String,
+    /// The transition that submits an event
+    pub submit: String,
+    /// The transition that submits a price update; `None` if the program has no price feed
+    pub submit_price: Option<String>,
+    /// The number of blocks after registration during which an oracle may submit events;
+    /// `None` if registrations do not expire
+    pub registration_window: Option<u32>,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct OracleInfo {
     pub address: String,
     pub report: String,
+    /// All programs known to the oracle
+    pub programs: Vec<ProgramInfo>,
+    /// The program used if a request does not name one
+    pub default_program: String,
+}
+
+impl OracleInfo {
+    /// Look up a program by ID, or the default program if `id` is `None`
+    pub fn program(&self, id: Option<&str>) -> Option<&ProgramInfo> {
+        let id = id.unwrap_or(&self.default_program);
+        self.programs.iter().find(|program| program.id == id)
+    }
+}
+
+/// How the oracle pays for a transaction
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FeeOptions {
+    /// Additional fee (in microcredits) on top of the base fee
+    pub priority_fee: u64,
+    /// Plaintext of a `credits.aleo` record to pay the fee privately; pays publicly if `None`
+    pub fee_record: Option<String>,
+    /// Refuse to prove the transaction if its estimated fee (in microcredits) is higher
+    pub max_fee: Option<u64>,
+}
+
+/// A game in a batch of submissions
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BatchItem {
+    pub game_id: String,
+    pub revision: u32,
+}
+
+/// A submission generated as part of a batch
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Submission {
+    /// The raw results
+    pub game_data: GameData,
+    /// The JSON-serialized transaction as a string
+    pub transaction: String,
+    /// Evidence of the response the results were read from
+    pub proof: FetchProof,
+}
+
+/// A submission authorized as part of a batch
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SubmissionAuthorization {
+    /// The raw results
+    pub game_data: GameData,
+    /// The JSON-serialized authorization of the submit transition
+    pub authorization: String,
+    /// The JSON-serialized authorization of its fee
+    pub fee_authorization: String,
+    /// Evidence of the response the results were read from
+    pub proof: FetchProof,
 }
 
 #[derive(Serialize, Deserialize)]
 pub enum OracleRequest {
-    GenerateSubmission { game_id: String },
+    /// Fetch the result of a game and sign it
+    ///
+    /// The event ID is derived from the data source and `game_id`, so that the same game always
+    /// maps to the same event. A `revision` greater than zero yields a new event ID for the same
+    /// game, which allows submitting corrections.
+    GenerateSubmission {
+        /// The program to submit to; the default program if `None`
+        program: Option<String>,
+        game_id: String,
+        revision: u32,
+        fee: FeeOptions,
+    },
     GetOracleInfo,
-    GetRegistration,
+    GetRegistration {
+        /// The program to register with; the default program if `None`
+        program: Option<String>,
+        fee: FeeOptions,
+    },
+    /// Fetch the oracle's metrics in the Prometheus text format
+    GetMetrics,
+    /// Fetch the current price of an asset pair and sign it
+    GeneratePriceUpdate {
+        /// The program to submit to; the default program if `None`
+        program: Option<String>,
+        /// The asset pair as `BASE/QUOTE`
+        pair: String,
+        fee: FeeOptions,
+    },
+    /// Like [`OracleRequest::GenerateSubmission`], but only sign the transition and its fee
+    ///
+    /// The oracle returns authorizations instead of proving them, so that a prover outside the
+    /// enclave can turn them into a transaction.
+    AuthorizeSubmission {
+        /// The program to submit to; the default program if `None`
+        program: Option<String>,
+        game_id: String,
+        revision: u32,
+        fee: FeeOptions,
+    },
+    /// Like [`OracleRequest::GenerateSubmission`], but for several games of one program
+    ///
+    /// The games are fetched and proven in parallel, and each one succeeds or fails on its
+    /// own. A fee record can only be spent once, so `fee` must not contain one.
+    GenerateSubmissions {
+        /// The program to submit to; the default program if `None`
+        program: Option<String>,
+        games: Vec<BatchItem>,
+        fee: FeeOptions,
+    },
+    /// Like [`OracleRequest::GenerateSubmissions`], but only sign the transitions and their fees
+    AuthorizeSubmissions {
+        /// The program to submit to; the default program if `None`
+        program: Option<String>,
+        games: Vec<BatchItem>,
+        fee: FeeOptions,
+    },
 }
 
 #[derive(Serialize, Deserialize)]
@@ -29,10 +242,117 @@ pub enum OracleResponse {
         game_data: GameData,
         /// The JSON-serialized transaction as a string
         transaction: String,
+        /// Evidence of the response the results were read from
+        proof: FetchProof,
     },
     OracleInfo(OracleInfo),
     /// Contains the JSON-serialized transaction as a string
     Registration(String),
+    /// Contains the metrics in the Prometheus text format
+    Metrics(String),
+    PriceUpdate {
+        /// The aggregated price
+        price_data: PriceData,
+        /// The JSON-serialized transaction as a string
+        transaction: String,
+    },
+    SubmissionAuthorization {
+        /// The raw results
+        game_data: GameData,
+        /// The JSON-serialized authorization of the submit transition
+        authorization: String,
+        /// The JSON-serialized authorization of its fee
+        fee_authorization: String,
+        /// Evidence of the response the results were read from
+        proof: FetchProof,
+    },
+    /// The outcome of each game of a batch, in the order of the request
+    Submissions(Vec<Result<Submission, OracleError>>),
+    /// The outcome of each game of a batch, in the order of the request
+    SubmissionAuthorizations(Vec<Result<SubmissionAuthorization, OracleError>>),
+    /// The request could not be processed
+    Error(OracleError),
+}
+
+/// Errors reported by the oracle to the gateway
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum OracleError {
+    /// An event with this ID already exists on chain
+    EventExists { event_id: String },
+    /// The oracle is not in `registered_oracles`
+    NotRegistered,
+    /// The oracle's registration window ended at block `expired_at`
+    RegistrationExpired { expired_at: u32, height: u32 },
+    /// The estimated fee exceeds `max_fee`
+    FeeTooHigh { estimate: u64, max_fee: u64 },
+    /// The oracle's public balance or fee record cannot cover the estimated fee
+    InsufficientBalance { required: u64, available: u64 },
+    /// The oracle does not know the requested program
+    UnknownProgram(String),
+    /// The game ID is empty or contains `#`, which separates revisions in event IDs
+    InvalidGameId(String),
+    /// The data source has no final score for the game yet
+    GameNotFinished(String),
+    /// The program has no transition for price updates
+    NoPriceFeed(String),
+    /// The asset pair is malformed
+    InvalidPair(String),
+    /// No price source returned a price for the pair
+    PriceUnavailable(String),
+    /// Fewer than `quorum` price sources agree on the price of the pair
+    NoPriceQuorum {
+        pair: String,
+        agreeing: usize,
+        quorum: usize,
+    },
+    /// Any other error
+    Other(String),
+}
+
+impl std::fmt::Display for OracleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EventExists { event_id } => write!(f, "event {event_id} already exists"),
+            Self::NotRegistered => write!(f, "oracle is not registered"),
+            Self::RegistrationExpired { expired_at, height } => write!(
+                f,
+                "oracle registration expired at block {expired_at} (current height is {height})"
+            ),
+            Self::FeeTooHigh { estimate, max_fee } => write!(
+                f,
+                "estimated fee of {estimate} microcredits exceeds the maximum of {max_fee}"
+            ),
+            Self::InsufficientBalance {
+                required,
+                available,
+            } => write!(
+                f,
+                "fee requires {required} microcredits but only {available} are available"
+            ),
+            Self::UnknownProgram(id) => write!(f, "unknown program {id}"),
+            Self::InvalidGameId(id) => write!(f, "invalid game ID `{id}`"),
+            Self::GameNotFinished(id) => write!(f, "game {id} has not finished yet"),
+            Self::NoPriceFeed(id) => write!(f, "program {id} has no price feed"),
+            Self::InvalidPair(pair) => write!(f, "invalid asset pair {pair}"),
+            Self::PriceUnavailable(pair) => write!(f, "all price sources failed for {pair}"),
+            Self::NoPriceQuorum {
+                pair,
+                agreeing,
+                quorum,
+            } => write!(
+                f,
+                "only {agreeing} price source(s) agree on {pair}, but {quorum} are required"
+            ),
+            Self::Other(msg) => write!(f, "{msg}"),
+        }
+    }
 }
 
+impl std::error::Error for OracleError {}
+
 pub const BINCODE_CONFIG: bincode::config::Configuration = bincode::config::standard();
+
+/// Compare two secrets without leaking the position of the first difference
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
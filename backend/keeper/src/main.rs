@@ -0,0 +1,367 @@
+//! Settles bets of `proto_snorkle_bet_000.aleo` once the oracle has emitted their event
+//!
+//! The node cannot enumerate mappings, so the keeper scans blocks for `make_bet` and
+//! `emit_event` transitions to learn about bets and events, and submits `resolve_bet` for
+//! every bet whose event was emitted.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::Context;
+
+use clap::Parser;
+
+use snarkvm::prelude::{Argument, Field, Output, Plaintext, PrivateKey, TestnetV0, Transition};
+
+use snorkle_bet::{BetClient, ORACLE_PROGRAM_ID, PROGRAM_ID};
+use snorkle_oracle_interface::FeeOptions;
+
+mod state;
+use state::{Settlement, State, TrackedBet};
+
+type CurrentNetwork = TestnetV0;
+
+/// Command line arguments of the keeper
+#[derive(Debug, Parser)]
+#[command(version, about = "Settles bets once their event is final", long_about = None)]
+struct Args {
+    /// Private key that signs and pays for settlements
+    #[arg(long, env = "SNORKLE_KEEPER_PRIVATE_KEY", hide_env_values = true)]
+    private_key: String,
+
+    /// REST endpoint of the node, including the network
+    #[arg(
+        long,
+        env = "SNORKLE_NODE_ENDPOINT",
+        default_value = "https://api.explorer.provable.com/v1/testnet"
+    )]
+    endpoint: String,
+
+    /// File the keeper persists its progress to
+    #[arg(
+        long,
+        env = "SNORKLE_KEEPER_STATE",
+        default_value = ".snorkle/keeper.json"
+    )]
+    state: PathBuf,
+
+    /// Block to start scanning at on the first run; the latest block if unset
+    #[arg(long, env = "SNORKLE_KEEPER_START_HEIGHT")]
+    start_height: Option<u32>,
+
+    /// Seconds between passes
+    #[arg(long, env = "SNORKLE_KEEPER_INTERVAL", default_value_t = 30)]
+    interval: u64,
+
+    /// Maximum number of blocks scanned per pass
+    #[arg(long, default_value_t = 100)]
+    max_blocks: u32,
+
+    /// Blocks to wait for a settlement before submitting it again
+    #[arg(long, default_value_t = 30)]
+    retry_after: u32,
+
+    /// Additional fee (in microcredits) paid for every settlement
+    #[arg(long, env = "SNORKLE_PRIORITY_FEE", default_value_t = 0)]
+    priority_fee: u64,
+
+    /// Refuse settlements with a higher estimated fee (in microcredits)
+    #[arg(long, env = "SNORKLE_MAX_FEE")]
+    max_fee: Option<u64>,
+
+    /// Stop settling once this many microcredits have been spent on fees
+    #[arg(long, env = "SNORKLE_KEEPER_BUDGET", default_value_t = 10_000_000)]
+    budget: u64,
+
+    /// Give up on a bet after this many failed settlements
+    #[arg(long, default_value_t = 5)]
+    max_attempts: u32,
+}
+
+struct Keeper {
+    args: Args,
+    client: BetClient<CurrentNetwork>,
+    state: State,
+}
+
+/// The arguments of the future returned by a transition
+fn future_arguments(transition: &Transition<CurrentNetwork>) -> Vec<Plaintext<CurrentNetwork>> {
+    transition
+        .outputs()
+        .iter()
+        .find_map(|output| match output {
+            Output::Future(_, Some(future)) => Some(future.arguments()),
+            _ => None,
+        })
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|argument| match argument {
+            Argument::Plaintext(plaintext) => Some(plaintext.clone()),
+            Argument::Future(_) => None,
+        })
+        .collect()
+}
+
+/// The member of a struct argument
+fn struct_member(value: &Plaintext<CurrentNetwork>, name: &str) -> anyhow::Result<String> {
+    let Plaintext::Struct(members, _) = value else {
+        anyhow::bail!("Expected a struct but got {value}");
+    };
+    let member = members
+        .iter()
+        .find(|(identifier, _)| identifier.to_string() == name)
+        .ok_or_else(|| anyhow::anyhow!("Missing member `{name}`"))?;
+
+    Ok(member.1.to_string())
+}
+
+impl Keeper {
+    fn new(args: Args) -> anyhow::Result<Self> {
+        let key = PrivateKey::<CurrentNetwork>::from_str(&args.private_key)
+            .with_context(|| "Invalid private key")?;
+        let client = BetClient::new(key, &args.endpoint)?;
+
+        let state = match State::load(&args.state)
+            .with_context(|| format!("Failed to load state from {}", args.state.display()))?
+        {
+            Some(state) => state,
+            None => {
+                let next_height = match args.start_height {
+                    Some(height) => height,
+                    None => client
+                        .latest_height()
+                        .with_context(|| "Failed to fetch the latest block")?,
+                };
+                State {
+                    next_height,
+                    ..Default::default()
+                }
+            }
+        };
+
+        log::info!(
+            "Keeper {} tracking {} bets, scanning from block {}",
+            client.address()?,
+            state.bets.len(),
+            state.next_height
+        );
+
+        Ok(Self {
+            args,
+            client,
+            state,
+        })
+    }
+
+    fn save(&self) {
+        if let Err(err) = self.state.save(&self.args.state) {
+            log::error!("Failed to persist state: {err}");
+        }
+    }
+
+    fn run(&mut self) -> ! {
+        loop {
+            if let Err(err) = self.pass() {
+                log::warn!("Keeper pass failed: {err:#}");
+            }
+
+            std::thread::sleep(Duration::from_secs(self.args.interval));
+        }
+    }
+
+    fn pass(&mut self) -> anyhow::Result<()> {
+        let latest = self.client.latest_height()?;
+
+        let end = latest.min(
+            self.state
+                .next_height
+                .saturating_add(self.args.max_blocks - 1),
+        );
+        for height in self.state.next_height..=end {
+            self.scan_block(height)
+                .with_context(|| format!("Failed to scan block {height}"))?;
+            self.state.next_height = height + 1;
+        }
+        self.save();
+
+        self.check_submitted(latest)?;
+        self.settle(latest)?;
+
+        Ok(())
+    }
+
+    fn scan_block(&mut self, height: u32) -> anyhow::Result<()> {
+        let transactions = self.client.transactions(height)?;
+
+        let transitions = transactions
+            .iter()
+            .filter(|confirmed| confirmed.is_accepted())
+            .flat_map(|confirmed| confirmed.transaction().transitions());
+        for transition in transitions {
+            let program = transition.program_id().to_string();
+            let function = transition.function_name().to_string();
+            let arguments = future_arguments(transition);
+
+            match (program.as_str(), function.as_str(), arguments.as_slice()) {
+                (PROGRAM_ID, "make_bet", [bet_id, bet, ..]) => {
+                    let event_id = struct_member(bet, "event_id")?;
+                    log::info!("Found bet {bet_id} on event {event_id}");
+                    self.state
+                        .bets
+                        .entry(bet_id.to_string())
+                        .or_insert(TrackedBet {
+                            event_id,
+                            attempts: 0,
+                            settlement: Settlement::Waiting,
+                        });
+                }
+                (PROGRAM_ID, "resolve_bet", [bet_id]) => {
+                    if self.state.bets.remove(&bet_id.to_string()).is_some() {
+                        log::info!("Bet {bet_id} was resolved");
+                    }
+                }
+                (ORACLE_PROGRAM_ID, "emit_event", [event_id, ..]) => {
+                    let event_id = event_id.to_string();
+                    for (bet_id, bet) in &mut self.state.bets {
+                        if bet.event_id == event_id && bet.settlement == Settlement::Waiting {
+                            log::info!("Event {event_id} was emitted; bet {bet_id} is ready");
+                            bet.settlement = Settlement::Ready;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Retry settlements that did not go through within `retry_after` blocks
+    fn check_submitted(&mut self, latest: u32) -> anyhow::Result<()> {
+        let stale: Vec<String> = self
+            .state
+            .bets
+            .iter()
+            .filter(|(_, bet)| {
+                matches!(bet.settlement, Settlement::Submitted { height, .. }
+                    if latest >= height.saturating_add(self.args.retry_after))
+            })
+            .map(|(bet_id, _)| bet_id.clone())
+            .collect();
+
+        for bet_id in stale {
+            if self.client.is_resolved(&Field::from_str(&bet_id)?)? {
+                log::info!("Bet {bet_id} was resolved");
+                self.state.bets.remove(&bet_id);
+            } else if let Some(bet) = self.state.bets.get_mut(&bet_id) {
+                log::warn!("Settlement of bet {bet_id} did not go through; retrying");
+                bet.settlement = Settlement::Ready;
+            }
+            self.save();
+        }
+
+        Ok(())
+    }
+
+    fn settle(&mut self, latest: u32) -> anyhow::Result<()> {
+        let ready: Vec<String> = self
+            .state
+            .bets
+            .iter()
+            .filter(|(_, bet)| bet.settlement == Settlement::Ready)
+            .map(|(bet_id, _)| bet_id.clone())
+            .collect();
+
+        for bet_id in ready {
+            let field = Field::from_str(&bet_id)?;
+
+            // Someone else may have settled it already
+            if self.client.is_resolved(&field)? {
+                log::info!("Bet {bet_id} was already resolved");
+                self.state.bets.remove(&bet_id);
+                self.save();
+                continue;
+            }
+
+            let Some(bet) = self.state.bets.get_mut(&bet_id) else {
+                continue;
+            };
+            if bet.attempts >= self.args.max_attempts {
+                log::error!(
+                    "Giving up on bet {bet_id} after {} failed settlements",
+                    bet.attempts
+                );
+                self.state.bets.remove(&bet_id);
+                self.save();
+                continue;
+            }
+
+            // `resolve_bet` fails (and still costs its fee) unless the event is on chain
+            let event_id = Field::from_str(&bet.event_id)?;
+            if !self.client.event_exists(&event_id)? {
+                log::warn!(
+                    "Event {} of bet {bet_id} is not on chain; waiting for it",
+                    bet.event_id
+                );
+                bet.settlement = Settlement::Waiting;
+                self.save();
+                continue;
+            }
+
+            let remaining = self.args.budget.saturating_sub(self.state.spent);
+            if remaining == 0 {
+                log::warn!("Fee budget exhausted; not settling bet {bet_id}");
+                return Ok(());
+            }
+
+            let fee = FeeOptions {
+                priority_fee: self.args.priority_fee,
+                fee_record: None,
+                max_fee: Some(
+                    self.args
+                        .max_fee
+                        .map_or(remaining, |max_fee| max_fee.min(remaining)),
+                ),
+            };
+
+            bet.attempts += 1;
+            self.save();
+
+            log::info!("Settling bet {bet_id}");
+            let transaction = match self.client.resolve_bet(field, &fee) {
+                Ok(transaction) => transaction,
+                Err(err) => {
+                    log::error!("Failed to create settlement of bet {bet_id}: {err:#}");
+                    continue;
+                }
+            };
+            let transaction_id = self.client.broadcast(&transaction)?;
+            log::info!("Broadcast settlement of bet {bet_id} in transaction {transaction_id}");
+
+            self.state.spent = self.state.spent.saturating_add(*transaction.fee_amount()?);
+            if let Some(bet) = self.state.bets.get_mut(&bet_id) {
+                bet.settlement = Settlement::Submitted {
+                    transaction_id,
+                    height: latest,
+                };
+            }
+            self.save();
+        }
+
+        Ok(())
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::init();
+
+    let args = Args::parse();
+    if args.max_blocks == 0 {
+        anyhow::bail!("--max-blocks must be at least one");
+    }
+    if args.max_attempts == 0 {
+        anyhow::bail!("--max-attempts must be at least one");
+    }
+    Keeper::new(args)?.run()
+}
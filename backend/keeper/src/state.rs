@@ -0,0 +1,63 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Where a bet is in the settlement process
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Settlement {
+    /// The oracle has not emitted the event yet
+    Waiting,
+    /// The event was emitted; the bet can be resolved
+    Ready,
+    /// A `resolve_bet` transaction was broadcast at `height`
+    Submitted { transaction_id: String, height: u32 },
+}
+
+/// A bet that has not been resolved yet
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrackedBet {
+    pub event_id: String,
+    /// Number of settlements created for the bet so far
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(flatten)]
+    pub settlement: Settlement,
+}
+
+/// Everything the keeper needs to pick up where it left off
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct State {
+    /// The next block to scan
+    pub next_height: u32,
+    /// Unresolved bets, by bet ID; resolved bets are dropped
+    pub bets: BTreeMap<String, TrackedBet>,
+    /// Microcredits spent on fees so far
+    pub spent: u64,
+}
+
+impl State {
+    /// Load the state, or `None` if the keeper has not run before
+    pub fn load(path: &Path) -> anyhow::Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let data = std::fs::read(path)?;
+        Ok(Some(serde_json::from_slice(&data)?))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Write to a temporary file first, so that a crash cannot leave a truncated state
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(self)?)?;
+        std::fs::rename(tmp, path)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,24 @@
+//! Exposes the version of snarkVM the oracle is built with as `SNARKVM_VERSION`, which the
+//! key cache is bound to
+
+fn main() {
+    let lockfile = concat!(env!("CARGO_MANIFEST_DIR"), "/../Cargo.lock");
+    println!("cargo:rerun-if-changed={lockfile}");
+
+    let lock = std::fs::read_to_string(lockfile).unwrap_or_default();
+    let version = lock
+        .split("[[package]]")
+        .find(|package| package.contains("\nname = \"snarkvm\"\n"))
+        .and_then(|package| {
+            package
+                .lines()
+                .find_map(|line| line.strip_prefix("version = "))
+        })
+        .map(|version| version.trim_matches('"').to_string());
+
+    let version = version.unwrap_or_else(|| {
+        println!("cargo:warning=Cannot determine the snarkVM version from {lockfile}");
+        "unknown".to_string()
+    });
+    println!("cargo:rustc-env=SNARKVM_VERSION={version}");
+}
@@ -0,0 +1,185 @@
+use std::panic::AssertUnwindSafe;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use snarkvm::prelude::*;
+
+use snorkle_oracle_interface::{
+    BatchItem, FeeOptions, OracleError, Submission, SubmissionAuthorization,
+};
+
+use super::Oracle;
+use crate::programs::OracleProgram;
+
+/// Default number of games of a batch that are processed at the same time; can be
+/// overridden with `SNORKLE_BATCH_PARALLELISM`
+const DEFAULT_PARALLELISM: usize = 4;
+
+fn parallelism() -> usize {
+    std::env::var("SNORKLE_BATCH_PARALLELISM")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|&value| value > 0)
+        .unwrap_or(DEFAULT_PARALLELISM)
+}
+
+/// Report an error to the gateway as an [`OracleError`]
+pub fn to_oracle_error(err: anyhow::Error) -> OracleError {
+    err.downcast::<OracleError>()
+        .unwrap_or_else(|err| OracleError::Other(format!("{err:#}")))
+}
+
+/// The message of a caught panic
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown error")
+}
+
+/// Apply `f` to every item on a pool of threads, keeping the order of the items
+///
+/// A panic while processing an item only fails that item.
+fn parallel_map<T: Sync, U: Send>(
+    items: &[T],
+    f: impl Fn(&T) -> Result<U, OracleError> + Sync,
+) -> Vec<Result<U, OracleError>> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<_>>());
+
+    std::thread::scope(|scope| {
+        for _ in 0..parallelism().min(items.len()) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(item) = items.get(index) else {
+                        break;
+                    };
+
+                    let result = std::panic::catch_unwind(AssertUnwindSafe(|| f(item)))
+                        .unwrap_or_else(|payload| {
+                            Err(OracleError::Other(format!(
+                                "Processing failed: {}",
+                                panic_message(&*payload)
+                            )))
+                        });
+                    results.lock().unwrap()[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every item was processed"))
+        .collect()
+}
+
+impl<N: Network> Oracle<N> {
+    /// Generate submissions for several games in parallel
+    pub fn generate_submissions(
+        &self,
+        program: &OracleProgram<N>,
+        games: &[BatchItem],
+        fee: &FeeOptions,
+    ) -> anyhow::Result<Vec<Result<Submission, OracleError>>> {
+        Self::check_batch_fee(fee)?;
+        println!("Generating {} submissions", games.len());
+
+        Ok(self.with_fee_reservation(|| {
+            parallel_map(games, |game| {
+                let (game_data, proof, txn) = self
+                    .generate_submission(program, game.game_id.clone(), game.revision, fee)
+                    .map_err(to_oracle_error)?;
+                println!("Submission for game {} is {}", game.game_id, txn.id());
+
+                Ok(Submission {
+                    game_data,
+                    transaction: serde_json::to_string(&txn)
+                        .map_err(|err| OracleError::Other(err.to_string()))?,
+                    proof,
+                })
+            })
+        }))
+    }
+
+    /// Authorize submissions for several games, fetching them in parallel
+    pub fn authorize_submissions(
+        &self,
+        program: &OracleProgram<N>,
+        games: &[BatchItem],
+        fee: &FeeOptions,
+    ) -> anyhow::Result<Vec<Result<SubmissionAuthorization, OracleError>>> {
+        Self::check_batch_fee(fee)?;
+        println!("Authorizing {} submissions", games.len());
+
+        Ok(self.with_fee_reservation(|| {
+            parallel_map(games, |game| {
+                let (game_data, proof, authorization, fee_authorization) = self
+                    .authorize_submission(program, game.game_id.clone(), game.revision, fee)
+                    .map_err(to_oracle_error)?;
+                let to_string = |authorization: &Authorization<N>| {
+                    serde_json::to_string(authorization)
+                        .map_err(|err| OracleError::Other(err.to_string()))
+                };
+
+                Ok(SubmissionAuthorization {
+                    game_data,
+                    authorization: to_string(&authorization)?,
+                    fee_authorization: to_string(&fee_authorization)?,
+                    proof,
+                })
+            })
+        }))
+    }
+
+    /// Run `f` with the fees of the games of a batch adding up, so that the public balance
+    /// must cover all of them rather than each one on its own
+    fn with_fee_reservation<T>(&self, f: impl FnOnce() -> T) -> T {
+        *self.reserved_fees.lock().unwrap() = Some(0);
+        let result = f();
+        *self.reserved_fees.lock().unwrap() = None;
+        result
+    }
+
+    fn check_batch_fee(fee: &FeeOptions) -> anyhow::Result<()> {
+        if fee.fee_record.is_some() {
+            return Err(OracleError::Other(
+                "a fee record cannot pay for more than one submission".to_string(),
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panics_only_fail_their_item() {
+        let items: Vec<u32> = (0..10).collect();
+        let results = parallel_map(&items, |&item| {
+            if item == 3 {
+                panic!("item {item} is broken");
+            }
+            Ok(item * 2)
+        });
+
+        assert_eq!(results.len(), items.len());
+        for (item, result) in items.iter().zip(results) {
+            match result {
+                Ok(doubled) => assert_eq!(doubled, item * 2),
+                Err(err) => {
+                    assert_eq!(*item, 3);
+                    assert!(err.to_string().contains("item 3 is broken"));
+                }
+            }
+        }
+    }
+}
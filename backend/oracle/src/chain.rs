@@ -0,0 +1,131 @@
+use std::str::FromStr;
+
+use anyhow::Context;
+
+use snarkvm::prelude::*;
+
+use snorkle_oracle_interface::OracleError;
+
+use super::Oracle;
+use crate::programs::OracleProgram;
+
+/// The REST endpoint used to query the chain (without the network)
+pub const NODE_URL: &str = "https://api.explorer.provable.com/v1";
+
+/// Name of the node in the source registry, which pins it like the data sources
+pub const NODE_SOURCE: &str = "node";
+
+/// Read-only access to the state of the oracle programs
+impl<N: Network> Oracle<N> {
+    fn node_url() -> anyhow::Result<String> {
+        let network = match N::ID {
+            MainnetV0::ID => "mainnet",
+            TestnetV0::ID => "testnet",
+            CanaryV0::ID => "canary",
+            _ => anyhow::bail!("Unsupported network: {}", N::NAME),
+        };
+
+        Ok(format!("{NODE_URL}/{network}"))
+    }
+
+    /// Fetch `path` from the node and parse the response as JSON
+    fn node_get<T: serde::de::DeserializeOwned>(&self, path: &str) -> anyhow::Result<T> {
+        let url = format!("{}/{path}", Self::node_url()?);
+        let body = self.source_agent(NODE_SOURCE)?.get(&url)?;
+
+        serde_json::from_slice(&body).with_context(|| format!("Invalid response from {url}"))
+    }
+
+    /// Fetch the height of the latest block
+    pub fn latest_height(&self) -> anyhow::Result<u32> {
+        self.node_get("block/height/latest")
+    }
+
+    /// Fetch the value stored under `key` in the mapping of a program
+    ///
+    /// Returns `None` if there is no such entry
+    pub fn get_program_mapping_value(
+        &self,
+        program: &str,
+        mapping: &str,
+        key: &str,
+    ) -> anyhow::Result<Option<Plaintext<N>>> {
+        let value: Option<String> =
+            self.node_get(&format!("program/{program}/mapping/{mapping}/{key}"))?;
+
+        value.map(|value| Plaintext::from_str(&value)).transpose()
+    }
+
+    /// Fetch the public balance of the oracle in microcredits
+    pub fn public_balance(&self) -> anyhow::Result<u64> {
+        let address = Address::<N>::try_from(&self.key)?;
+        let value =
+            self.get_program_mapping_value("credits.aleo", "account", &address.to_string())?;
+
+        match value {
+            None => Ok(0),
+            Some(Plaintext::Literal(Literal::U64(balance), _)) => Ok(*balance),
+            Some(other) => anyhow::bail!("Invalid balance: {other}"),
+        }
+    }
+
+    /// Make sure the submission will not be rejected by finalize
+    ///
+    /// Checks that the event does not exist yet, and that the oracle is registered and within
+    /// its registration window (if the program has one).
+    pub fn check_submission(
+        &self,
+        program: &OracleProgram<N>,
+        event_id: &str,
+    ) -> anyhow::Result<()> {
+        let program_id = program.id().to_string();
+
+        if self
+            .get_program_mapping_value(&program_id, "events", event_id)?
+            .is_some()
+        {
+            return Err(OracleError::EventExists {
+                event_id: event_id.to_string(),
+            }
+            .into());
+        }
+
+        self.check_registration(program)
+    }
+
+    /// Make sure the oracle is registered with `program` and within its registration window
+    /// (if the program has one)
+    pub fn check_registration(&self, program: &OracleProgram<N>) -> anyhow::Result<()> {
+        let program_id = program.id().to_string();
+        let address = Address::<N>::try_from(&self.key)?;
+        let Some(data) = self.get_program_mapping_value(
+            &program_id,
+            "registered_oracles",
+            &address.to_string(),
+        )?
+        else {
+            return Err(OracleError::NotRegistered.into());
+        };
+
+        // Only programs that do not record when oracles registered lack a window; see
+        // `check_registration_window`
+        let Some(window) = program.info.registration_window else {
+            return Ok(());
+        };
+
+        let member = Identifier::from_str("registration_timestamp")?;
+        let Plaintext::Literal(Literal::U32(registered_at), _) = data.find(&[member])? else {
+            anyhow::bail!("Invalid oracle data: {data}");
+        };
+
+        let expired_at = registered_at.saturating_add(window);
+        let height = self.latest_height()?;
+
+        // Finalize runs in a later block, so the window must not end at the current one
+        if expired_at <= height {
+            return Err(OracleError::RegistrationExpired { expired_at, height }.into());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,361 @@
+//! A minimal DNS-over-HTTPS (RFC 8484) client
+//!
+//! Lets the oracle resolve hosts without trusting the machine's DNS: queries go to a single
+//! server over TLS, whose keys are pinned like those of a data source.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+
+use base64::prelude::*;
+
+use crate::http::Resolver;
+use crate::sources::Source;
+use crate::tls::SourceAgent;
+
+const MESSAGE_TYPE: &str = "application/dns-message";
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+
+/// Upper bound on how long answers are cached, whatever their TTL
+const MAX_TTL: u32 = 300;
+
+/// Resolves hosts over DNS-over-HTTPS, caching the answers
+pub struct DohClient {
+    url: String,
+    agent: SourceAgent,
+    cache: Mutex<HashMap<String, (Vec<IpAddr>, Instant)>>,
+}
+
+impl fmt::Debug for DohClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DohClient").field("url", &self.url).finish()
+    }
+}
+
+impl DohClient {
+    /// A client for the server at `url`, which `source` must allow
+    ///
+    /// `resolver` only has to resolve the server itself.
+    pub fn new(url: String, source: &Source, resolver: Resolver) -> anyhow::Result<Self> {
+        Ok(Self {
+            url,
+            agent: SourceAgent::new(source, resolver)?,
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The IPv4 addresses of `host`, or its IPv6 addresses if it has none
+    pub fn lookup(&self, host: &str) -> anyhow::Result<Vec<IpAddr>> {
+        if let Some((ips, expiry)) = self.cache.lock().unwrap().get(host)
+            && Instant::now() < *expiry
+        {
+            return Ok(ips.clone());
+        }
+
+        let (mut ips, mut ttl) = self.query(host, TYPE_A)?;
+        if ips.is_empty() {
+            (ips, ttl) = self.query(host, TYPE_AAAA)?;
+        }
+        if ips.is_empty() {
+            anyhow::bail!("{host} has no A or AAAA records");
+        }
+
+        let expiry = Instant::now() + Duration::from_secs(ttl.min(MAX_TTL).into());
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(host.to_string(), (ips.clone(), expiry));
+
+        Ok(ips)
+    }
+
+    /// Ask for records of `record_type`, returning their addresses and smallest TTL
+    fn query(&self, host: &str, record_type: u16) -> anyhow::Result<(Vec<IpAddr>, u32)> {
+        let query = BASE64_URL_SAFE_NO_PAD.encode(encode_query(host, record_type)?);
+        let separator = if self.url.contains('?') { '&' } else { '?' };
+        let response = self
+            .agent
+            .get_as(&format!("{}{separator}dns={query}", self.url), MESSAGE_TYPE)?;
+
+        decode_response(&response, record_type)
+            .with_context(|| format!("Invalid answer from {}", self.url))
+    }
+}
+
+/// A query for `record_type` records of `host`, with ID 0 as RFC 8484 recommends
+fn encode_query(host: &str, record_type: u16) -> anyhow::Result<Vec<u8>> {
+    // ID, flags (recursion desired), one question, no other records
+    let mut message = vec![0, 0, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+
+    for label in host.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            anyhow::bail!("`{host}` is not a valid host name");
+        }
+        message.push(label.len() as u8);
+        message.extend_from_slice(label.as_bytes());
+    }
+    message.push(0);
+    if message.len() - 12 > 255 {
+        anyhow::bail!("`{host}` is too long for a host name");
+    }
+
+    message.extend_from_slice(&record_type.to_be_bytes());
+    message.extend_from_slice(&CLASS_IN.to_be_bytes());
+
+    Ok(message)
+}
+
+/// Reads a DNS message front to back
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .with_context(|| "Message is truncated")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> anyhow::Result<u16> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    /// Skip a (possibly compressed) name
+    fn skip_name(&mut self) -> anyhow::Result<()> {
+        loop {
+            match self.u8()? {
+                0 => return Ok(()),
+                len if len & 0xC0 == 0xC0 => {
+                    self.u8()?;
+                    return Ok(());
+                }
+                len if len & 0xC0 != 0 => anyhow::bail!("Invalid label type {len:#x}"),
+                len => {
+                    self.take(len.into())?;
+                }
+            }
+        }
+    }
+}
+
+/// The addresses in the answers of `response`, and their smallest TTL
+///
+/// The server follows CNAMEs, so records of other types are skipped.
+fn decode_response(response: &[u8], record_type: u16) -> anyhow::Result<(Vec<IpAddr>, u32)> {
+    let mut reader = Reader {
+        bytes: response,
+        pos: 0,
+    };
+
+    let _id = reader.u16()?;
+    let flags = reader.u16()?;
+    let questions = reader.u16()?;
+    let answers = reader.u16()?;
+    let _authorities = reader.u16()?;
+    let _additional = reader.u16()?;
+
+    if flags & 0x8000 == 0 {
+        anyhow::bail!("Message is not a response");
+    }
+    match flags & 0x000F {
+        0 => {}
+        3 => anyhow::bail!("The host does not exist"),
+        rcode => anyhow::bail!("The server failed with response code {rcode}"),
+    }
+
+    for _ in 0..questions {
+        reader.skip_name()?;
+        reader.take(4)?;
+    }
+
+    let mut ips = vec![];
+    let mut ttl = MAX_TTL;
+    for _ in 0..answers {
+        reader.skip_name()?;
+        let rtype = reader.u16()?;
+        let class = reader.u16()?;
+        let record_ttl = reader.u32()?;
+        let len = reader.u16()?;
+        let data = reader.take(len.into())?;
+
+        if rtype != record_type || class != CLASS_IN {
+            continue;
+        }
+        let ip = match rtype {
+            TYPE_A => IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(data)?)),
+            TYPE_AAAA => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(data)?)),
+            _ => continue,
+        };
+        ips.push(ip);
+        ttl = ttl.min(record_ttl);
+    }
+
+    Ok((ips, ttl))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TYPE_CNAME: u16 = 5;
+
+    /// A name pointing to the question, at offset 12
+    const QUESTION_NAME: [u8; 2] = [0xC0, 0x0C];
+
+    /// Encode a name without compression
+    fn name(host: &str) -> Vec<u8> {
+        let mut name = vec![];
+        for label in host.split('.') {
+            name.push(label.len() as u8);
+            name.extend_from_slice(label.as_bytes());
+        }
+        name.push(0);
+        name
+    }
+
+    /// A response to a query for A records of `example.com`
+    fn response(rcode: u16, answers: &[(Vec<u8>, u16, u32, Vec<u8>)]) -> Vec<u8> {
+        let mut message = encode_query("example.com", TYPE_A).unwrap();
+        message[2..4].copy_from_slice(&(0x8180 | rcode).to_be_bytes());
+        message[6..8].copy_from_slice(&(answers.len() as u16).to_be_bytes());
+
+        for (owner, rtype, ttl, data) in answers {
+            message.extend_from_slice(owner);
+            message.extend_from_slice(&rtype.to_be_bytes());
+            message.extend_from_slice(&CLASS_IN.to_be_bytes());
+            message.extend_from_slice(&ttl.to_be_bytes());
+            message.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            message.extend_from_slice(data);
+        }
+        message
+    }
+
+    fn a(ttl: u32, ip: [u8; 4]) -> (Vec<u8>, u16, u32, Vec<u8>) {
+        (QUESTION_NAME.to_vec(), TYPE_A, ttl, ip.to_vec())
+    }
+
+    #[test]
+    fn addresses_are_decoded() {
+        let message = response(0, &[a(60, [1, 2, 3, 4]), a(120, [5, 6, 7, 8])]);
+        let (ips, ttl) = decode_response(&message, TYPE_A).unwrap();
+
+        assert_eq!(
+            ips,
+            [IpAddr::from([1, 2, 3, 4]), IpAddr::from([5, 6, 7, 8])]
+        );
+        assert_eq!(ttl, 60);
+    }
+
+    #[test]
+    fn truncated_messages_are_rejected() {
+        let message = response(0, &[a(60, [1, 2, 3, 4])]);
+
+        for len in 0..message.len() {
+            assert!(decode_response(&message[..len], TYPE_A).is_err());
+        }
+    }
+
+    #[test]
+    fn compressed_names_are_skipped() {
+        // `www` followed by a pointer to `example.com`, and a bare pointer
+        let mut www = vec![3];
+        www.extend_from_slice(b"www");
+        www.extend_from_slice(&QUESTION_NAME);
+        let message = response(
+            0,
+            &[(www, TYPE_A, 60, vec![1, 2, 3, 4]), a(60, [5, 6, 7, 8])],
+        );
+
+        let (ips, _) = decode_response(&message, TYPE_A).unwrap();
+        assert_eq!(ips.len(), 2);
+
+        // Reserved label types are neither lengths nor pointers
+        let mut invalid = response(0, &[a(60, [1, 2, 3, 4])]);
+        let answer = invalid.len() - 16;
+        invalid[answer] = 0x40;
+        assert!(decode_response(&invalid, TYPE_A).is_err());
+    }
+
+    #[test]
+    fn missing_hosts_and_failures_are_errors() {
+        let err = decode_response(&response(3, &[]), TYPE_A).unwrap_err();
+        assert!(err.to_string().contains("does not exist"));
+
+        let err = decode_response(&response(2, &[]), TYPE_A).unwrap_err();
+        assert!(err.to_string().contains("response code 2"));
+
+        // A query is not an answer
+        let query = encode_query("example.com", TYPE_A).unwrap();
+        assert!(decode_response(&query, TYPE_A).is_err());
+    }
+
+    #[test]
+    fn cname_chains_are_followed_by_the_server() {
+        let message = response(
+            0,
+            &[
+                (
+                    QUESTION_NAME.to_vec(),
+                    TYPE_CNAME,
+                    30,
+                    name("cdn.example.net"),
+                ),
+                (
+                    name("cdn.example.net"),
+                    TYPE_CNAME,
+                    30,
+                    name("edge.example.org"),
+                ),
+                (name("edge.example.org"), TYPE_A, 90, vec![9, 9, 9, 9]),
+            ],
+        );
+
+        // Only the addresses count, and so only their TTLs
+        let (ips, ttl) = decode_response(&message, TYPE_A).unwrap();
+        assert_eq!(ips, [IpAddr::from([9, 9, 9, 9])]);
+        assert_eq!(ttl, 90);
+
+        // Addresses of another type are skipped as well
+        let (ips, _) = decode_response(&message, TYPE_AAAA).unwrap();
+        assert!(ips.is_empty());
+    }
+
+    #[test]
+    fn ttls_are_clamped() {
+        let message = response(0, &[a(86_400, [1, 2, 3, 4])]);
+        assert_eq!(decode_response(&message, TYPE_A).unwrap().1, MAX_TTL);
+
+        let message = response(0, &[a(0, [1, 2, 3, 4])]);
+        assert_eq!(decode_response(&message, TYPE_A).unwrap().1, 0);
+
+        // Without addresses, nothing is cached for long
+        assert_eq!(
+            decode_response(&response(0, &[]), TYPE_A).unwrap().1,
+            MAX_TTL
+        );
+    }
+}
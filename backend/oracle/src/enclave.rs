@@ -0,0 +1,178 @@
+//! The trusted execution environment the oracle runs in
+//!
+//! Everything the oracle needs from the hardware goes through [`Enclave`], so that the rest of
+//! the oracle builds and runs on a regular host. The backend is picked at compile time for
+//! SGX, and with `SNORKLE_ENCLAVE` (`tdx` or `host`) otherwise.
+
+use rand::{CryptoRng, RngCore};
+
+use crate::http::Resolver;
+
+mod host;
+pub use host::Host;
+
+#[cfg(all(target_arch = "x86_64", not(target_env = "sgx")))]
+mod tdx;
+#[cfg(all(target_arch = "x86_64", not(target_env = "sgx")))]
+pub use tdx::Tdx;
+
+#[cfg(target_env = "sgx")]
+mod sgx;
+#[cfg(target_env = "sgx")]
+pub use sgx::Sgx;
+
+pub trait Enclave: Send + Sync {
+    /// Name of the backend, for logging
+    fn name(&self) -> &'static str;
+
+    /// Resolves the host names of outgoing requests
+    fn resolver(&self) -> Resolver;
+
+    /// Fill `buf` with cryptographically secure random bytes
+    fn fill_random(&self, buf: &mut [u8]) -> anyhow::Result<()>;
+
+    /// The current Unix time in seconds
+    fn now(&self) -> anyhow::Result<u64>;
+
+    /// Evidence that the oracle runs in this enclave, bound to `user_data`
+    fn attest(&self, user_data: &[u8]) -> anyhow::Result<Vec<u8>>;
+
+    /// Encrypt `data` so that only this enclave can decrypt it
+    fn seal(&self, data: &[u8]) -> anyhow::Result<Vec<u8>>;
+
+    /// Decrypt data encrypted with [`Enclave::seal`]
+    fn unseal(&self, sealed: &[u8]) -> anyhow::Result<Vec<u8>>;
+}
+
+/// Open the enclave the oracle runs in
+#[cfg(target_env = "sgx")]
+pub fn open() -> anyhow::Result<Box<dyn Enclave>> {
+    Ok(Box::new(Sgx::new()?))
+}
+
+/// Open the enclave the oracle runs in
+///
+/// Defaults to TDX on x86_64, and to the host (without any hardware protection) elsewhere.
+#[cfg(not(target_env = "sgx"))]
+pub fn open() -> anyhow::Result<Box<dyn Enclave>> {
+    let default = if cfg!(target_arch = "x86_64") {
+        "tdx"
+    } else {
+        "host"
+    };
+    let backend = std::env::var("SNORKLE_ENCLAVE").unwrap_or_else(|_| default.to_string());
+
+    match backend.as_str() {
+        "host" => Ok(Box::new(Host::new()?)),
+        #[cfg(target_arch = "x86_64")]
+        "tdx" => Ok(Box::new(Tdx::new()?)),
+        _ => anyhow::bail!("Unsupported enclave backend `{backend}`"),
+    }
+}
+
+/// A random number generator backed by [`Enclave::fill_random`]
+pub struct EnclaveRng<'a>(pub &'a dyn Enclave);
+
+impl RngCore for EnclaveRng<'_> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest)
+            .expect("Failed to get randomness from the enclave");
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0
+            .fill_random(dest)
+            .map_err(|err| rand::Error::new(Box::<dyn std::error::Error + Send + Sync>::from(err)))
+    }
+}
+
+/// Every backend draws from a cryptographically secure source
+impl CryptoRng for EnclaveRng<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+    use super::*;
+
+    /// Fills buffers with consecutive bytes, or fails once `fail` is set
+    #[derive(Default)]
+    struct Counter {
+        next: AtomicU8,
+        fail: AtomicBool,
+    }
+
+    impl Enclave for Counter {
+        fn name(&self) -> &'static str {
+            "counter"
+        }
+
+        fn resolver(&self) -> Resolver {
+            Resolver::fixed(Default::default())
+        }
+
+        fn fill_random(&self, buf: &mut [u8]) -> anyhow::Result<()> {
+            if self.fail.load(Ordering::SeqCst) {
+                anyhow::bail!("no randomness left");
+            }
+            for byte in buf {
+                *byte = self.next.fetch_add(1, Ordering::SeqCst);
+            }
+            Ok(())
+        }
+
+        fn now(&self) -> anyhow::Result<u64> {
+            Ok(0)
+        }
+
+        fn attest(&self, _user_data: &[u8]) -> anyhow::Result<Vec<u8>> {
+            Ok(vec![])
+        }
+
+        fn seal(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+            Ok(data.to_vec())
+        }
+
+        fn unseal(&self, sealed: &[u8]) -> anyhow::Result<Vec<u8>> {
+            Ok(sealed.to_vec())
+        }
+    }
+
+    #[test]
+    fn rng_draws_from_the_enclave() {
+        let enclave = Counter::default();
+        let mut rng = EnclaveRng(&enclave);
+
+        assert_eq!(rng.next_u32(), u32::from_le_bytes([0, 1, 2, 3]));
+        assert_eq!(
+            rng.next_u64(),
+            u64::from_le_bytes([4, 5, 6, 7, 8, 9, 10, 11])
+        );
+
+        let mut buf = [0; 3];
+        rng.fill_bytes(&mut buf);
+        assert_eq!(buf, [12, 13, 14]);
+    }
+
+    #[test]
+    fn rng_reports_failures_of_the_enclave() {
+        let enclave = Counter::default();
+        enclave.fail.store(true, Ordering::SeqCst);
+
+        let mut buf = [0; 4];
+        let err = EnclaveRng(&enclave).try_fill_bytes(&mut buf).unwrap_err();
+        assert!(err.to_string().contains("no randomness left"));
+    }
+}
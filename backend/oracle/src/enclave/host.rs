@@ -0,0 +1,212 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+
+use rand::RngCore;
+use rand::rngs::OsRng;
+
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+
+use snorkle_oracle_interface::files::write_atomic;
+
+use super::Enclave;
+use crate::http::Resolver;
+
+/// Binds sealed data to its purpose
+const SEAL_AAD: &[u8] = b"snorkle/sealed/v1";
+
+/// Seals data with an AES-256-GCM key kept in a file
+///
+/// This protects sealed data only as well as the key file, so it is meant for development
+/// and for backends without hardware sealing.
+pub struct SoftwareSealer {
+    key_path: PathBuf,
+}
+
+impl SoftwareSealer {
+    /// Use the key at `SNORKLE_SEAL_KEY`, or `.snorkle/seal.key`
+    pub fn new() -> Self {
+        let key_path = std::env::var_os("SNORKLE_SEAL_KEY")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Path::new(".snorkle").join("seal.key"));
+
+        Self { key_path }
+    }
+
+    /// Load the key, generating it on first use
+    fn key(&self) -> anyhow::Result<LessSafeKey> {
+        let bytes = match std::fs::read(&self.key_path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                let mut bytes = vec![0; AES_256_GCM.key_len()];
+                OsRng.fill_bytes(&mut bytes);
+
+                if let Some(parent) = self.key_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                write_atomic(&self.key_path, &bytes).with_context(|| {
+                    format!("Failed to write seal key to {}", self.key_path.display())
+                })?;
+                println!("Generated seal key at {}", self.key_path.display());
+
+                bytes
+            }
+            Err(err) => {
+                return Err(err).with_context(|| {
+                    format!("Failed to read seal key at {}", self.key_path.display())
+                });
+            }
+        };
+
+        let key = UnboundKey::new(&AES_256_GCM, &bytes)
+            .map_err(|_| anyhow::anyhow!("Invalid seal key at {}", self.key_path.display()))?;
+        Ok(LessSafeKey::new(key))
+    }
+
+    /// Encrypt `data` as `nonce || ciphertext || tag`
+    pub fn seal(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let key = self.key()?;
+
+        let mut nonce = [0; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+
+        let mut ciphertext = data.to_vec();
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(SEAL_AAD),
+            &mut ciphertext,
+        )
+        .map_err(|_| anyhow::anyhow!("Failed to seal data"))?;
+
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    pub fn unseal(&self, sealed: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let key = self.key()?;
+
+        if sealed.len() < NONCE_LEN {
+            anyhow::bail!("Sealed data is too short");
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| anyhow::anyhow!("Invalid nonce"))?;
+
+        let mut plaintext = ciphertext.to_vec();
+        let len = key
+            .open_in_place(nonce, Aad::from(SEAL_AAD), &mut plaintext)
+            .map_err(|_| anyhow::anyhow!("Failed to unseal data; was it sealed with another key?"))?
+            .len();
+        plaintext.truncate(len);
+
+        Ok(plaintext)
+    }
+}
+
+/// Runs the oracle on a regular host, without any hardware protection
+///
+/// Its attestation is a placeholder, so contracts should never accept it outside of testing.
+pub struct Host {
+    sealer: SoftwareSealer,
+    resolver: Resolver,
+}
+
+impl Host {
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(Self {
+            sealer: SoftwareSealer::new(),
+            resolver: Resolver::load(true)?,
+        })
+    }
+}
+
+/// Current Unix time in seconds, according to the operating system
+pub fn system_time() -> anyhow::Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+
+impl Enclave for Host {
+    fn name(&self) -> &'static str {
+        "host"
+    }
+
+    fn resolver(&self) -> Resolver {
+        self.resolver.clone()
+    }
+
+    fn fill_random(&self, buf: &mut [u8]) -> anyhow::Result<()> {
+        OsRng.try_fill_bytes(buf)?;
+        Ok(())
+    }
+
+    fn now(&self) -> anyhow::Result<u64> {
+        system_time()
+    }
+
+    fn attest(&self, _user_data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        Ok(b"Hello World".to_vec())
+    }
+
+    fn seal(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.sealer.seal(data)
+    }
+
+    fn unseal(&self, sealed: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.sealer.unseal(sealed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sealer with a fresh key in a temporary directory
+    fn sealer() -> SoftwareSealer {
+        let dir = std::env::temp_dir().join(format!("snorkle-seal-{}", OsRng.next_u64()));
+        SoftwareSealer {
+            key_path: dir.join("seal.key"),
+        }
+    }
+
+    #[test]
+    fn sealed_data_round_trips() {
+        let sealer = sealer();
+        let sealed = sealer.seal(b"secret").unwrap();
+
+        assert_ne!(&sealed[NONCE_LEN..], b"secret");
+        assert_eq!(sealer.unseal(&sealed).unwrap(), b"secret");
+        // The key was generated on first use and is reused
+        assert!(sealer.key_path.exists());
+        assert_eq!(sealer.unseal(&sealer.seal(b"").unwrap()).unwrap(), b"");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&sealer.key_path)
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn tampered_data_is_rejected() {
+        let sealer = sealer();
+        let sealed = sealer.seal(b"secret").unwrap();
+
+        for index in [0, NONCE_LEN, sealed.len() - 1] {
+            let mut tampered = sealed.clone();
+            tampered[index] ^= 1;
+            assert!(sealer.unseal(&tampered).is_err());
+        }
+        assert!(sealer.unseal(&sealed[..sealed.len() - 1]).is_err());
+        assert!(sealer.unseal(&sealed[..NONCE_LEN - 1]).is_err());
+    }
+
+    #[test]
+    fn data_sealed_with_another_key_is_rejected() {
+        let sealed = sealer().seal(b"secret").unwrap();
+        assert!(sealer().unseal(&sealed).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::untrusted::time::SystemTimeEx;
+use std::vec::Vec;
+
+use anyhow::Context;
+
+use sgx_tcrypto::{rsgx_rijndael128GCM_decrypt, rsgx_rijndael128GCM_encrypt, rsgx_sha256_slice};
+use sgx_trts::trts::rsgx_read_rand;
+use sgx_types::*;
+
+use super::Enclave;
+use crate::http::Resolver;
+
+/// Runs the oracle in an Intel SGX enclave
+///
+/// The enclave cannot trust the host's DNS, so hosts are resolved from a static table or over
+/// DNS-over-HTTPS. Time comes from the untrusted host, which is why fetches are not
+/// considered evidence of *when* a result was known.
+pub struct Sgx {
+    /// Target info of the quoting enclave, which turns reports into quotes
+    target_info: sgx_target_info_t,
+    resolver: Resolver,
+}
+
+impl Sgx {
+    /// Reads the quoting enclave's target info (hex) from `SNORKLE_SGX_TARGET_INFO`
+    ///
+    /// Reports made for any other target cannot be turned into quotes, so it is required.
+    pub fn new() -> anyhow::Result<Self> {
+        let hex = std::env::var("SNORKLE_SGX_TARGET_INFO").with_context(
+            || "SNORKLE_SGX_TARGET_INFO must hold the quoting enclave's target info",
+        )?;
+        let bytes =
+            hex::decode(hex.trim()).with_context(|| "SNORKLE_SGX_TARGET_INFO is not valid hex")?;
+        if bytes.len() != std::mem::size_of::<sgx_target_info_t>() {
+            anyhow::bail!("SNORKLE_SGX_TARGET_INFO has the wrong size");
+        }
+        // SAFETY: the target info is plain data of exactly this size
+        let target_info =
+            unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const sgx_target_info_t) };
+
+        println!(
+            "SGX reports are not turned into quotes; the registration is not remotely verifiable"
+        );
+
+        Ok(Self {
+            target_info,
+            resolver: Resolver::load(false)?,
+        })
+    }
+
+    /// The enclave's seal key for `key_id`, bound to its measurement (MRENCLAVE)
+    ///
+    /// Data sealed before a TCB update cannot be unsealed after it.
+    fn seal_key(&self, key_id: &[u8; SGX_KEYID_SIZE]) -> anyhow::Result<sgx_key_128bit_t> {
+        // SAFETY: the SDK returns a pointer to the enclave's own, static report
+        let report = unsafe { &*sgx_self_report() };
+
+        let request = sgx_key_request_t {
+            key_name: SGX_KEYSELECT_SEAL,
+            key_policy: SGX_KEYPOLICY_MRENCLAVE,
+            isv_svn: report.body.isv_svn,
+            cpu_svn: report.body.cpu_svn,
+            attribute_mask: sgx_attributes_t {
+                flags: TSEAL_DEFAULT_FLAGSMASK,
+                xfrm: 0,
+            },
+            key_id: sgx_key_id_t { id: *key_id },
+            misc_mask: TSEAL_DEFAULT_MISCMASK,
+            ..Default::default()
+        };
+
+        let mut key = sgx_key_128bit_t::default();
+        // SAFETY: both pointers are valid for the duration of the call
+        match unsafe { sgx_get_key(&request, &mut key) } {
+            sgx_status_t::SGX_SUCCESS => Ok(key),
+            status => anyhow::bail!("Failed to derive seal key: {status}"),
+        }
+    }
+}
+
+impl Enclave for Sgx {
+    fn name(&self) -> &'static str {
+        "sgx"
+    }
+
+    fn resolver(&self) -> Resolver {
+        self.resolver.clone()
+    }
+
+    fn fill_random(&self, buf: &mut [u8]) -> anyhow::Result<()> {
+        rsgx_read_rand(buf).map_err(|status| anyhow::anyhow!("RDRAND failed: {status}"))
+    }
+
+    fn now(&self) -> anyhow::Result<u64> {
+        Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+    }
+
+    /// A report for the quoting enclave, whose report data starts with the SHA-256 hash of
+    /// `user_data`
+    ///
+    /// This is a local EREPORT, not a quote: only enclaves on the same machine can verify it,
+    /// so registrations made on SGX (which hash the report) are not remotely verifiable.
+    fn attest(&self, user_data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let hash = rsgx_sha256_slice(user_data)
+            .map_err(|status| anyhow::anyhow!("Failed to hash user data: {status}"))?;
+        let mut report_data = sgx_report_data_t::default();
+        report_data.d[..hash.len()].copy_from_slice(&hash);
+
+        let mut report = sgx_report_t::default();
+        // SAFETY: all pointers are valid for the duration of the call
+        let status = unsafe { sgx_create_report(&self.target_info, &report_data, &mut report) };
+        if status != sgx_status_t::SGX_SUCCESS {
+            anyhow::bail!("Failed to create report: {status}");
+        }
+
+        // SAFETY: the report is plain data
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                &report as *const sgx_report_t as *const u8,
+                std::mem::size_of::<sgx_report_t>(),
+            )
+        };
+        Ok(bytes.to_vec())
+    }
+
+    /// Encrypt `data` as `key_id || iv || mac || ciphertext`
+    fn seal(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut key_id = [0; SGX_KEYID_SIZE];
+        self.fill_random(&mut key_id)?;
+        let mut iv = [0; SGX_AESGCM_IV_SIZE];
+        self.fill_random(&mut iv)?;
+
+        let key = self.seal_key(&key_id)?;
+        let mut ciphertext = vec![0; data.len()];
+        let mut mac = sgx_aes_gcm_128bit_tag_t::default();
+        rsgx_rijndael128GCM_encrypt(&key, data, &iv, &[], &mut ciphertext, &mut mac)
+            .map_err(|status| anyhow::anyhow!("Failed to seal data: {status}"))?;
+
+        Ok([key_id.as_slice(), &iv, &mac, &ciphertext].concat())
+    }
+
+    fn unseal(&self, sealed: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let header = SGX_KEYID_SIZE + SGX_AESGCM_IV_SIZE + SGX_AESGCM_MAC_SIZE;
+        if sealed.len() < header {
+            anyhow::bail!("Sealed data is too short");
+        }
+        let (key_id, rest) = sealed.split_at(SGX_KEYID_SIZE);
+        let (iv, rest) = rest.split_at(SGX_AESGCM_IV_SIZE);
+        let (mac, ciphertext) = rest.split_at(SGX_AESGCM_MAC_SIZE);
+
+        let key = self.seal_key(key_id.try_into()?)?;
+        let mut plaintext = vec![0; ciphertext.len()];
+        rsgx_rijndael128GCM_decrypt(&key, ciphertext, iv, &[], mac.try_into()?, &mut plaintext)
+            .map_err(|status| anyhow::anyhow!("Failed to unseal data: {status}"))?;
+
+        Ok(plaintext)
+    }
+}
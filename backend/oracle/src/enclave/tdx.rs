@@ -0,0 +1,84 @@
+use std::process::Command;
+
+use anyhow::Context;
+
+use base64::prelude::*;
+
+use rand::RngCore;
+use rand::rngs::OsRng;
+
+use super::Enclave;
+use super::host::{SoftwareSealer, system_time};
+use crate::http::Resolver;
+
+// Function to run the `trustauthority-cli quote` command
+fn generate_report(user_data: &str) -> anyhow::Result<String> {
+    // Create the command
+    let output = Command::new("sudo")
+        .arg("trustauthority-cli")
+        .arg("quote")
+        .arg("--user-data")
+        .arg(user_data) // Pass user_data argument
+        .output() // Execute the command
+        .with_context(|| "Failed to execute trustauthority-cli")?;
+
+    // Check if the command executed successfully
+    if !output.status.success() {
+        // If the command failed, return the stderr output as an error
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("trustauthority-cli failed: {error_msg}");
+    }
+
+    // Return the output from the command if successful
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Runs the oracle in an Intel TDX trust domain
+///
+/// The guest kernel provides randomness and time. TDX has no sealing keys of its own,
+/// so data is sealed with a [`SoftwareSealer`], whose key should live on an encrypted volume.
+pub struct Tdx {
+    sealer: SoftwareSealer,
+    resolver: Resolver,
+}
+
+impl Tdx {
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(Self {
+            sealer: SoftwareSealer::new(),
+            resolver: Resolver::load(false)?,
+        })
+    }
+}
+
+impl Enclave for Tdx {
+    fn name(&self) -> &'static str {
+        "tdx"
+    }
+
+    fn resolver(&self) -> Resolver {
+        self.resolver.clone()
+    }
+
+    fn fill_random(&self, buf: &mut [u8]) -> anyhow::Result<()> {
+        OsRng.try_fill_bytes(buf)?;
+        Ok(())
+    }
+
+    fn now(&self) -> anyhow::Result<u64> {
+        system_time()
+    }
+
+    /// A quote from `trustauthority-cli`, whose user data is `user_data` in base64
+    fn attest(&self, user_data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        Ok(generate_report(&BASE64_URL_SAFE.encode(user_data))?.into_bytes())
+    }
+
+    fn seal(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.sealer.seal(data)
+    }
+
+    fn unseal(&self, sealed: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.sealer.unseal(sealed)
+    }
+}
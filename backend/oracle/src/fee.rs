@@ -0,0 +1,90 @@
+use std::str::FromStr;
+
+use snarkvm::ledger::store::helpers::memory::ConsensusMemory;
+use snarkvm::prelude::*;
+
+use snorkle_oracle_interface::fees::estimate_base_fee;
+use snorkle_oracle_interface::{FeeOptions, OracleError};
+
+use super::Oracle;
+
+impl<N: Network> Oracle<N> {
+    /// Estimate the base fee of an authorized transition in microcredits, without proving it
+    pub fn estimate_fee(
+        &self,
+        vm: &VM<N, ConsensusMemory<N>>,
+        authorization: &Authorization<N>,
+    ) -> anyhow::Result<u64> {
+        estimate_base_fee(&vm.process().read(), authorization)
+    }
+
+    /// The amount of microcredits in a `credits.aleo` record
+    fn record_balance(record: &Record<N, Plaintext<N>>) -> anyhow::Result<u64> {
+        let member = Identifier::<N>::from_str("microcredits")?;
+        let (Entry::Constant(value) | Entry::Public(value) | Entry::Private(value)) =
+            record.find(&[member])?;
+
+        match value {
+            Plaintext::Literal(Literal::U64(microcredits), _) => Ok(*microcredits),
+            other => anyhow::bail!("Invalid record balance: {other}"),
+        }
+    }
+
+    /// Make sure the fee can be paid, and parse the fee record (if any)
+    pub fn prepare_fee(
+        &self,
+        fee: &FeeOptions,
+        base_fee: u64,
+    ) -> anyhow::Result<Option<Record<N, Plaintext<N>>>> {
+        let required = base_fee.saturating_add(fee.priority_fee);
+        println!(
+            "Estimated fee is {required} microcredits ({base_fee} base, {} priority)",
+            fee.priority_fee
+        );
+
+        if let Some(max_fee) = fee.max_fee
+            && required > max_fee
+        {
+            return Err(OracleError::FeeTooHigh {
+                estimate: required,
+                max_fee,
+            }
+            .into());
+        }
+
+        let record = match &fee.fee_record {
+            Some(record) => {
+                let record = Record::<N, Plaintext<N>>::from_str(record)?;
+                let available = Self::record_balance(&record)?;
+                if available < required {
+                    return Err(OracleError::InsufficientBalance {
+                        required,
+                        available,
+                    }
+                    .into());
+                }
+                Some(record)
+            }
+            None => {
+                let balance = self.public_balance()?;
+
+                // Earlier games of a batch pay from the same balance
+                let mut reserved = self.reserved_fees.lock().unwrap();
+                let available = balance.saturating_sub(reserved.unwrap_or_default());
+                if available < required {
+                    return Err(OracleError::InsufficientBalance {
+                        required,
+                        available,
+                    }
+                    .into());
+                }
+                if let Some(reserved) = reserved.as_mut() {
+                    *reserved += required;
+                }
+                None
+            }
+        };
+
+        Ok(record)
+    }
+}
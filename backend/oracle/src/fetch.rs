@@ -1,13 +1,178 @@
-use ureq::config::Config;
-
-use ureq::unversioned::transport::DefaultConnector;
+use std::collections::HashMap;
 
 use snarkvm::prelude::Network;
 
+use snorkle_oracle_interface::{GAME_URL, OracleError, PRICE_DECIMALS, PriceData};
+
 use super::Oracle;
-use crate::http::Resolver;
+use crate::chain::NODE_SOURCE;
+use crate::metrics;
+use crate::sources::PricePolicy;
+use crate::tls::SourceAgent;
+
+/// Name of the scores API in the source registry
+const SCORES_SOURCE: &str = "scores";
+
+/// Exchanges the price feed aggregates over, with the function that queries each
+const PRICE_SOURCES: &[(&str, PriceSource)] = &[
+    ("coinbase", fetch_coinbase_price),
+    ("kraken", fetch_kraken_price),
+];
+
+/// Fetch the price of `base` in `quote` as a decimal string
+type PriceSource = fn(&SourceAgent, &str, &str) -> anyhow::Result<String>;
+
+/// Names of all sources the oracle fetches from, which must be in the source registry
+pub fn source_names() -> Vec<&'static str> {
+    [SCORES_SOURCE, NODE_SOURCE]
+        .into_iter()
+        .chain(PRICE_SOURCES.iter().map(|(name, _)| *name))
+        .collect()
+}
+
+/// Make sure the price sources can reach the quorum of `policy`
+pub fn check_price_policy(policy: &PricePolicy) -> anyhow::Result<()> {
+    if policy.quorum > PRICE_SOURCES.len() {
+        anyhow::bail!(
+            "prices.quorum is {}, but there are only {} price sources",
+            policy.quorum,
+            PRICE_SOURCES.len()
+        );
+    }
+
+    Ok(())
+}
+
+fn median(sorted: &[u128]) -> u128 {
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        sorted[mid - 1].midpoint(sorted[mid])
+    } else {
+        sorted[mid]
+    }
+}
+
+/// Take the median of the prices that agree with the median of all `prices`
+///
+/// Returns the price and the names of the sources that agree on it, sorted.
+fn aggregate_prices(
+    pair: &str,
+    mut prices: Vec<(u128, String)>,
+    policy: &PricePolicy,
+) -> Result<(u128, Vec<String>), OracleError> {
+    if prices.is_empty() {
+        return Err(OracleError::PriceUnavailable(pair.to_string()));
+    }
+
+    prices.sort_unstable();
+    let all: Vec<u128> = prices.iter().map(|(price, _)| *price).collect();
+    let reference = median(&all);
+    let max_deviation = reference.saturating_mul(policy.max_deviation.into()) / 10_000;
+    prices.retain(|(price, _)| price.abs_diff(reference) <= max_deviation);
+
+    if prices.len() < policy.quorum {
+        return Err(OracleError::NoPriceQuorum {
+            pair: pair.to_string(),
+            agreeing: prices.len(),
+            quorum: policy.quorum,
+        });
+    }
+
+    let agreeing: Vec<u128> = prices.iter().map(|(price, _)| *price).collect();
+    let mut sources: Vec<String> = prices.into_iter().map(|(_, name)| name).collect();
+    sources.sort();
+
+    Ok((median(&agreeing), sources))
+}
+
+#[derive(serde::Deserialize)]
+struct CoinbaseResponse {
+    data: CoinbasePrice,
+}
+
+#[derive(serde::Deserialize)]
+struct CoinbasePrice {
+    amount: String,
+}
+
+#[derive(serde::Deserialize)]
+struct KrakenResponse {
+    error: Vec<String>,
+    #[serde(default)]
+    result: HashMap<String, KrakenTicker>,
+}
+
+#[derive(serde::Deserialize)]
+struct KrakenTicker {
+    /// The last trade as `[price, volume]`
+    c: Vec<String>,
+}
+
+fn fetch_coinbase_price(agent: &SourceAgent, base: &str, quote: &str) -> anyhow::Result<String> {
+    let url = format!("https://api.coinbase.com/v2/prices/{base}-{quote}/spot");
+    let response: CoinbaseResponse = serde_json::from_slice(&agent.get(&url)?)?;
+
+    Ok(response.data.amount)
+}
+
+fn fetch_kraken_price(agent: &SourceAgent, base: &str, quote: &str) -> anyhow::Result<String> {
+    let url = format!("https://api.kraken.com/0/public/Ticker?pair={base}{quote}");
+    let response: KrakenResponse = serde_json::from_slice(&agent.get(&url)?)?;
+
+    if !response.error.is_empty() {
+        anyhow::bail!("{}", response.error.join(", "));
+    }
 
-const GAME_URL: &str = "https://scores-api-349861721231.northamerica-northeast1.run.app/games";
+    response
+        .result
+        .into_values()
+        .next()
+        .and_then(|ticker| ticker.c.into_iter().next())
+        .ok_or_else(|| anyhow::anyhow!("Empty ticker"))
+}
+
+/// Parse a decimal string into a fixed-point integer with [`PRICE_DECIMALS`] decimal places
+///
+/// Extra decimal places are truncated.
+fn parse_price(amount: &str) -> anyhow::Result<u128> {
+    let (integer, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    let fraction: String = fraction
+        .chars()
+        .chain(std::iter::repeat('0'))
+        .take(PRICE_DECIMALS as usize)
+        .collect();
+
+    let integer: u128 = integer.parse()?;
+    let fraction: u128 = fraction.parse()?;
+
+    integer
+        .checked_mul(10u128.pow(PRICE_DECIMALS))
+        .and_then(|integer| integer.checked_add(fraction))
+        .ok_or_else(|| anyhow::anyhow!("Price {amount} is too large"))
+}
+
+/// Split `BASE/QUOTE` into its assets
+fn parse_pair(pair: &str) -> Option<(&str, &str)> {
+    let (base, quote) = pair.split_once('/')?;
+    let is_asset = |asset: &str| {
+        !asset.is_empty()
+            && asset
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+    };
+
+    (is_asset(base) && is_asset(quote)).then_some((base, quote))
+}
+
+/// A raw response of the data source, with what is needed to prove where it came from
+pub struct FetchRecord {
+    pub url: String,
+    /// Unix time (in seconds) at which the response was received
+    pub timestamp: u64,
+    pub body: Vec<u8>,
+    /// The DER-encoded certificate chain presented by the server, leaf first
+    pub certificates: Vec<Vec<u8>>,
+}
 
 #[allow(dead_code)]
 #[derive(serde::Deserialize)]
@@ -22,30 +187,158 @@ struct GameInfo {
 }
 
 impl<N: Network> Oracle<N> {
-    /// Fetches data from a public API
+    /// An HTTP agent for the source `name`
     ///
-    /// Note: Currently this just contacts example.com and then returns random data
-    pub fn fetch_scores(&self, game_id: &str) -> anyhow::Result<(u8, u8)> {
-        let config = Config::builder().build();
-        let resolver = Resolver::default();
-        let connector = DefaultConnector::new();
+    /// Fails if the source may not be fetched from on this enclave backend.
+    pub fn source_agent(&self, name: &str) -> anyhow::Result<SourceAgent> {
+        let source = self.sources.get(name)?;
+        source.check_pinned(self.enclave.name())?;
 
-        let agent = ureq::Agent::with_parts(config, connector, resolver);
+        SourceAgent::new(source, self.enclave.resolver())
+    }
+
+    /// Fetches the scores of a game from a public API
+    ///
+    /// Also returns the raw response, so that the scores can be proven to come from it.
+    pub fn fetch_scores(&self, game_id: &str) -> anyhow::Result<((u8, u8), FetchRecord)> {
+        let agent = self.source_agent(SCORES_SOURCE)?;
 
+        let timer = metrics::FETCH_DURATION
+            .with_label_values(&["scores"])
+            .start_timer();
+        let body = agent
+            .get(GAME_URL)
+            .inspect_err(|_| metrics::FETCH_ERRORS.inc())?;
+        timer.observe_duration();
         println!("Got response from (mock) API");
-        let mut response = agent.get(GAME_URL).call()?;
-        let data: Vec<GameInfo> = response.body_mut().read_json()?;
+
+        let record = FetchRecord {
+            url: GAME_URL.to_string(),
+            timestamp: self.enclave.now()?,
+            certificates: agent.certificates(),
+            body,
+        };
+        let data: Vec<GameInfo> = serde_json::from_slice(&record.body)?;
 
         for game in data {
             if game.id == game_id {
                 println!("Found game with id={game_id}");
-                let home = game.home_team_score.expect("Game not finished yet");
-                let away = game.away_team_score.expect("Game not finished yet");
+                let (Some(home), Some(away)) = (game.home_team_score, game.away_team_score) else {
+                    return Err(OracleError::GameNotFinished(game_id.to_string()).into());
+                };
 
-                return Ok((home, away));
+                return Ok(((home, away), record));
             }
         }
 
         anyhow::bail!("Game with id={game_id} not found");
     }
+
+    /// Fetches the price of an asset pair from all price sources and takes the median
+    ///
+    /// Sources that fail or do not list the pair are skipped, and those that disagree with
+    /// the others are left out; fails unless a quorum of sources agrees.
+    pub fn fetch_price(&self, pair: &str) -> anyhow::Result<PriceData> {
+        let Some((base, quote)) = parse_pair(pair) else {
+            return Err(OracleError::InvalidPair(pair.to_string()).into());
+        };
+
+        let mut prices = vec![];
+        for (name, fetch) in PRICE_SOURCES {
+            let timer = metrics::FETCH_DURATION
+                .with_label_values(&[name])
+                .start_timer();
+            let price = self
+                .source_agent(name)
+                .and_then(|agent| fetch(&agent, base, quote))
+                .and_then(|amount| parse_price(&amount));
+            timer.observe_duration();
+
+            match price {
+                Ok(price) => {
+                    println!("Got price {price} for {pair} from {name}");
+                    prices.push((price, name.to_string()));
+                }
+                Err(err) => {
+                    metrics::FETCH_ERRORS.inc();
+                    println!("Failed to fetch price for {pair} from {name}: {err:#}");
+                }
+            }
+        }
+
+        let (price, sources) = aggregate_prices(pair, prices, &self.sources.prices)?;
+        let timestamp = self.enclave.now()?;
+
+        Ok(PriceData {
+            pair: pair.to_string(),
+            price,
+            timestamp,
+            sources,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: PricePolicy = PricePolicy {
+        quorum: 2,
+        max_deviation: 100,
+    };
+
+    fn prices(prices: &[(u128, &str)]) -> Vec<(u128, String)> {
+        prices
+            .iter()
+            .map(|(price, name)| (*price, name.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn agreeing_prices_are_averaged() {
+        let (price, sources) =
+            aggregate_prices("BTC/USD", prices(&[(10_050, "b"), (10_000, "a")]), &POLICY).unwrap();
+
+        assert_eq!(price, 10_025);
+        assert_eq!(sources, ["a", "b"]);
+    }
+
+    #[test]
+    fn outliers_are_left_out() {
+        let (price, sources) = aggregate_prices(
+            "BTC/USD",
+            prices(&[(10_000, "a"), (10_010, "b"), (20_000, "c")]),
+            &POLICY,
+        )
+        .unwrap();
+
+        assert_eq!(price, 10_005);
+        assert_eq!(sources, ["a", "b"]);
+    }
+
+    #[test]
+    fn disagreeing_prices_fail_the_quorum() {
+        let err = aggregate_prices("BTC/USD", prices(&[(10_000, "a"), (12_000, "b")]), &POLICY)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            OracleError::NoPriceQuorum {
+                agreeing: 0,
+                quorum: 2,
+                ..
+            }
+        ));
+
+        let err = aggregate_prices("BTC/USD", prices(&[(10_000, "a")]), &POLICY).unwrap_err();
+        assert!(matches!(
+            err,
+            OracleError::NoPriceQuorum { agreeing: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn no_prices_means_the_sources_failed() {
+        let err = aggregate_prices("BTC/USD", vec![], &POLICY).unwrap_err();
+        assert!(matches!(err, OracleError::PriceUnavailable(_)));
+    }
 }
@@ -15,6 +15,8 @@ use snorkle_oracle_interface::{BINCODE_CONFIG, OracleRequest, OracleResponse};
 use snarkvm::prelude::Network;
 
 use super::Oracle;
+use crate::batch::to_oracle_error;
+use crate::metrics;
 
 impl<N: Network> Oracle<N> {
     /// Network loop for communication with the gateway
@@ -37,7 +39,11 @@ impl<N: Network> Oracle<N> {
                 let (msg, _) = decode_from_slice(&data, BINCODE_CONFIG)
                     .with_context(|| "Failed to deserialize data")?;
 
-                let response = self.handle_message(msg)?;
+                // Report errors to the gateway instead of dropping the connection
+                let response = self.handle_message(msg).unwrap_or_else(|err| {
+                    println!("Failed to handle request: {err:#}");
+                    OracleResponse::Error(to_oracle_error(err))
+                });
                 let response = encode_to_vec(&response, BINCODE_CONFIG)?;
 
                 let mut data = BytesMut::new();
@@ -53,20 +59,73 @@ impl<N: Network> Oracle<N> {
     /// Process a message from the gateway
     pub fn handle_message(&self, msg: OracleRequest) -> anyhow::Result<OracleResponse> {
         match msg {
-            OracleRequest::GenerateSubmission { game_id } => {
-                let (game_data, txn) = self.generate_submission(game_id)?;
+            OracleRequest::GenerateSubmission {
+                program,
+                game_id,
+                revision,
+                fee,
+            } => {
+                let program = self.program(program.as_deref())?;
+                let (game_data, proof, txn) =
+                    self.generate_submission(program, game_id, revision, &fee)?;
                 let txn_str = serde_json::to_string(&txn)?;
                 Ok(OracleResponse::Submission {
                     game_data,
                     transaction: txn_str,
+                    proof,
                 })
             }
-            OracleRequest::GetRegistration => {
-                let txn = self.generate_registration()?;
+            OracleRequest::GetRegistration { program, fee } => {
+                let program = self.program(program.as_deref())?;
+                let txn = self.generate_registration(program, &fee)?;
                 let txn_str = serde_json::to_string(&txn)?;
                 Ok(OracleResponse::Registration(txn_str))
             }
+            OracleRequest::GeneratePriceUpdate { program, pair, fee } => {
+                let program = self.program(program.as_deref())?;
+                let (price_data, txn) = self.generate_price_update(program, &pair, &fee)?;
+                let txn_str = serde_json::to_string(&txn)?;
+                Ok(OracleResponse::PriceUpdate {
+                    price_data,
+                    transaction: txn_str,
+                })
+            }
+            OracleRequest::AuthorizeSubmission {
+                program,
+                game_id,
+                revision,
+                fee,
+            } => {
+                let program = self.program(program.as_deref())?;
+                let (game_data, proof, authorization, fee_authorization) =
+                    self.authorize_submission(program, game_id, revision, &fee)?;
+                Ok(OracleResponse::SubmissionAuthorization {
+                    game_data,
+                    authorization: serde_json::to_string(&authorization)?,
+                    fee_authorization: serde_json::to_string(&fee_authorization)?,
+                    proof,
+                })
+            }
+            OracleRequest::GenerateSubmissions {
+                program,
+                games,
+                fee,
+            } => {
+                let program = self.program(program.as_deref())?;
+                let results = self.generate_submissions(program, &games, &fee)?;
+                Ok(OracleResponse::Submissions(results))
+            }
+            OracleRequest::AuthorizeSubmissions {
+                program,
+                games,
+                fee,
+            } => {
+                let program = self.program(program.as_deref())?;
+                let results = self.authorize_submissions(program, &games, &fee)?;
+                Ok(OracleResponse::SubmissionAuthorizations(results))
+            }
             OracleRequest::GetOracleInfo => Ok(OracleResponse::OracleInfo(self.info.clone())),
+            OracleRequest::GetMetrics => Ok(OracleResponse::Metrics(metrics::encode()?)),
         }
     }
 }
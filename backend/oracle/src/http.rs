@@ -1,78 +1,345 @@
 /// Helpers for HTTP requests
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
 
-// Issue queries with hardcoded DNS for SGX
-#[cfg(target_env = "sgx")]
-mod sgx {
-    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-    use std::string::String;
+use anyhow::Context;
 
-    use ureq::http::Uri;
+use serde::Deserialize;
 
-    use ureq::unversioned::resolver::{ArrayVec, ResolvedSocketAddrs};
-    use ureq::unversioned::transport::NextTimeout;
+use ureq::http::Uri;
 
-    #[derive(Debug)]
-    pub struct Resolver {
-        known_hosts: HashMap<String, IpAddr>,
+use ureq::unversioned::resolver::{ArrayVec, DefaultResolver, ResolvedSocketAddrs};
+use ureq::unversioned::transport::NextTimeout;
+
+use crate::dns::DohClient;
+use crate::sources::{Source, parse_pins};
+
+/// Capacity of [`ResolvedSocketAddrs`]
+const MAX_ADDRS: usize = 16;
+
+/// IP addresses of known hosts, by lowercase host name
+pub type StaticHosts = HashMap<String, Vec<IpAddr>>;
+
+/// Parse a table of the form `host=ip[,ip...];host=ip...`
+pub fn parse_static_hosts(table: &str) -> anyhow::Result<StaticHosts> {
+    let mut hosts = HashMap::new();
+
+    for entry in table.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+        let (host, addrs) = entry
+            .split_once('=')
+            .with_context(|| format!("Entry `{entry}` is not of the form host=ip"))?;
+        let addrs = addrs
+            .split(',')
+            .map(|addr| {
+                addr.trim()
+                    .parse()
+                    .with_context(|| format!("Invalid IP address `{addr}` for {host}"))
+            })
+            .collect::<anyhow::Result<Vec<IpAddr>>>()?;
+
+        hosts.insert(host.trim().to_ascii_lowercase(), addrs);
     }
 
-    impl Default for Resovler {
-        fn default() -> Self {
-            Self::new()
-        }
+    Ok(hosts)
+}
+
+/// The resolver configuration, embedded from `resources/resolver.toml`
+///
+/// Like the source registry, it is part of the oracle's measurement.
+const RESOLVER_CONFIG: &str = include_str!("../../resources/resolver.toml");
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ResolverConfig {
+    /// Addresses of hosts that are never looked up
+    #[serde(default)]
+    hosts: HashMap<String, Vec<IpAddr>>,
+    /// Look up all other hosts over DNS-over-HTTPS
+    doh: DohConfig,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DohConfig {
+    /// Endpoint of the server, such as `https://1.1.1.1/dns-query`
+    url: String,
+    /// Pinned keys of the server, as in the source registry
+    pins: Vec<String>,
+    /// Addresses of the server, unless `url` contains one
+    #[serde(default)]
+    addrs: Vec<IpAddr>,
+    #[serde(default = "DohConfig::default_timeout")]
+    timeout: u64,
+}
+
+impl DohConfig {
+    fn default_timeout() -> u64 {
+        5
     }
 
-    impl Resolver {
-        pub fn new() -> Self {
-            // Sets up mapping of known hosts.
-            let mut result = HashMap::default();
-            result.insert(
-                "example.com".to_string(),
-                IpAddr::V4(Ipv4Addr::new(23, 215, 0, 138)),
-            );
-
-            Self {
-                known_hosts: result,
+    fn into_client(self) -> anyhow::Result<DohClient> {
+        let uri: Uri = self
+            .url
+            .parse()
+            .with_context(|| format!("Invalid DNS-over-HTTPS URL `{}`", self.url))?;
+        let host = uri
+            .host()
+            .with_context(|| format!("DNS-over-HTTPS URL `{}` has no host", self.url))?
+            .to_ascii_lowercase();
+        if self.timeout == 0 {
+            anyhow::bail!("DNS-over-HTTPS timeout must be at least one second");
+        }
+
+        // The server cannot resolve its own name
+        let mut hosts = StaticHosts::new();
+        if parse_ip(&host).is_none() {
+            if self.addrs.is_empty() {
+                anyhow::bail!(
+                    "DNS-over-HTTPS server `{host}` needs `addrs`, or an IP address in its URL"
+                );
             }
+            hosts.insert(host.clone(), self.addrs);
         }
+
+        let pins =
+            parse_pins(&self.pins).with_context(|| "Invalid pins for the DNS-over-HTTPS server")?;
+        if pins.is_empty() {
+            anyhow::bail!("DNS-over-HTTPS server `{host}` is not pinned");
+        }
+
+        let source = Source {
+            name: "dns-over-https".to_string(),
+            hosts: vec![host],
+            pins,
+            max_response_size: u16::MAX as u64,
+            timeout: Duration::from_secs(self.timeout),
+        };
+        DohClient::new(self.url, &source, Resolver::fixed(hosts))
     }
+}
 
-    impl ureq::unversioned::resolver::Resolver for Resolver {
-        fn resolve(
-            &self,
-            uri: &Uri,
-            _config: &ureq::config::Config,
-            _timeout: NextTimeout,
-        ) -> Result<ResolvedSocketAddrs, ureq::Error> {
-            let Some(hostname) = uri.host() else {
-                return Err(ureq::Error::HostNotFound);
-            };
-
-            let ip = if let Some(ip) = self.known_hosts.get(hostname) {
-                *ip
-            } else {
-                return Err(ureq::Error::HostNotFound);
-            };
+/// Parse a host that is an IP address, with or without brackets
+fn parse_ip(host: &str) -> Option<IpAddr> {
+    host.trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .ok()
+}
+
+/// How to resolve hosts that are not in the static table
+#[derive(Clone, Debug)]
+enum Fallback {
+    /// Ask the operating system
+    System,
+    /// Ask a (pinned) DNS-over-HTTPS server
+    Doh(Arc<DohClient>),
+    /// Do not resolve them at all
+    None,
+}
+
+/// Resolves the host names of the oracle's requests
+///
+/// Hosts in the static table are never looked up, which suits enclaves that cannot trust the
+/// host's DNS. All other hosts go to the fallback.
+#[derive(Clone, Debug)]
+pub struct Resolver {
+    hosts: Arc<StaticHosts>,
+    fallback: Fallback,
+}
+
+impl Resolver {
+    /// Load the embedded resolver configuration, and the static hosts in
+    /// `SNORKLE_STATIC_HOSTS`
+    ///
+    /// Entries in `SNORKLE_STATIC_HOSTS` take precedence over the embedded ones; as enclaves
+    /// only fetch from pinned sources, they can make connections fail but not redirect them
+    /// there. Hosts that are
+    /// not static are resolved through the operating system if `system_dns` is set, and over
+    /// the embedded DNS-over-HTTPS server otherwise.
+    pub fn load(system_dns: bool) -> anyhow::Result<Self> {
+        let config: ResolverConfig = toml::from_str(RESOLVER_CONFIG)
+            .with_context(|| "Invalid embedded resolver configuration")?;
+        let env_hosts = std::env::var("SNORKLE_STATIC_HOSTS")
+            .ok()
+            .map(|table| parse_static_hosts(&table).with_context(|| "Invalid SNORKLE_STATIC_HOSTS"))
+            .transpose()?;
+
+        let mut hosts: StaticHosts = config
+            .hosts
+            .into_iter()
+            .map(|(host, addrs)| (host.to_ascii_lowercase(), addrs))
+            .collect();
+        hosts.extend(env_hosts.unwrap_or_default());
+
+        let fallback = if system_dns {
+            Fallback::System
+        } else {
+            Fallback::Doh(Arc::new(config.doh.into_client()?))
+        };
+
+        Ok(Self {
+            hosts: Arc::new(hosts),
+            fallback,
+        })
+    }
+
+    /// A resolver that knows only `hosts`, and fails for every other host
+    pub fn fixed(hosts: StaticHosts) -> Self {
+        Self {
+            hosts: Arc::new(hosts),
+            fallback: Fallback::None,
+        }
+    }
+
+    /// Short description for logging
+    pub fn describe(&self) -> String {
+        let fallback = match &self.fallback {
+            Fallback::System => "system DNS".to_string(),
+            Fallback::Doh(client) => format!("DNS-over-HTTPS at {}", client.url()),
+            Fallback::None => "nothing else".to_string(),
+        };
+        format!("{} static host(s), then {fallback}", self.hosts.len())
+    }
+
+    fn lookup(&self, host: &str) -> Result<Vec<IpAddr>, String> {
+        if let Some(ip) = parse_ip(host) {
+            return Ok(vec![ip]);
+        }
+        if let Some(ips) = self.hosts.get(host) {
+            return Ok(ips.clone());
+        }
+
+        match &self.fallback {
+            Fallback::Doh(client) => client
+                .lookup(host)
+                .map_err(|err| format!("Failed to resolve {host} over DNS-over-HTTPS: {err:#}")),
+            Fallback::None => Err(format!(
+                "{host} is not a static host, and no other resolver is configured"
+            )),
+            Fallback::System => unreachable!("System lookups go through ureq's resolver"),
+        }
+    }
+}
+
+/// A resolution failure, with a message that explains it
+fn not_found(msg: String) -> ureq::Error {
+    ureq::Error::Io(std::io::Error::new(std::io::ErrorKind::NotFound, msg))
+}
+
+impl ureq::unversioned::resolver::Resolver for Resolver {
+    fn resolve(
+        &self,
+        uri: &Uri,
+        config: &ureq::config::Config,
+        timeout: NextTimeout,
+    ) -> Result<ResolvedSocketAddrs, ureq::Error> {
+        let Some(host) = uri.host().map(str::to_ascii_lowercase) else {
+            return Err(not_found(format!("URL {uri} has no host")));
+        };
+
+        if matches!(self.fallback, Fallback::System) && !self.hosts.contains_key(&host) {
+            return DefaultResolver::default()
+                .resolve(uri, config, timeout)
+                .map_err(|err| match err {
+                    ureq::Error::HostNotFound => {
+                        not_found(format!("The system's DNS does not know {host}"))
+                    }
+                    err => err,
+                });
+        }
 
-            let addr = SocketAddr::new(ip, uri.port_u16().unwrap_or(443));
-            let mut result = ArrayVec::from_fn(|_| {
-                SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080)
+        let ips = self.lookup(&host).map_err(not_found)?;
+        let port = uri
+            .port_u16()
+            .unwrap_or(if uri.scheme_str() == Some("http") {
+                80
+            } else {
+                443
             });
-            result.push(addr);
 
+        // Placeholders for the unused capacity, like ureq's own resolver; they are not part
+        // of the result
+        let mut result: ResolvedSocketAddrs =
+            ArrayVec::from_fn(|_| SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0));
+        for ip in ips.iter().take(MAX_ADDRS) {
+            result.push(SocketAddr::new(*ip, port));
+        }
+
+        if result.is_empty() {
+            Err(not_found(format!("No addresses for {host}")))
+        } else {
             Ok(result)
         }
     }
 }
 
-#[cfg(target_env = "sgx")]
-pub use sgx::*;
+#[cfg(test)]
+mod tests {
+    use ureq::unversioned::resolver::Resolver as _;
 
-/// Issue queries with hardcoded DNS for SGX
-#[cfg(not(target_env = "sgx"))]
-mod tdx {
-    pub type Resolver = ureq::unversioned::resolver::DefaultResolver;
-}
+    use super::*;
+
+    #[test]
+    fn static_hosts_are_parsed() {
+        let hosts =
+            parse_static_hosts(" API.example.com=1.2.3.4, 5.6.7.8 ; v6.example.com=::1;").unwrap();
+
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(
+            hosts["api.example.com"],
+            [
+                "1.2.3.4".parse::<IpAddr>().unwrap(),
+                "5.6.7.8".parse().unwrap()
+            ]
+        );
+        assert_eq!(hosts["v6.example.com"], ["::1".parse::<IpAddr>().unwrap()]);
+        assert!(parse_static_hosts("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn malformed_static_hosts_are_rejected() {
+        assert!(parse_static_hosts("example.com").is_err());
+        assert!(parse_static_hosts("example.com=not-an-ip").is_err());
+        assert!(parse_static_hosts("example.com=1.2.3.4,").is_err());
+    }
+
+    #[test]
+    fn embedded_resolver_pins_no_roots() {
+        let config: ResolverConfig = toml::from_str(RESOLVER_CONFIG).unwrap();
+        parse_pins(&config.doh.pins).unwrap();
+    }
+
+    #[test]
+    fn unpinned_doh_servers_are_rejected() {
+        let config: ResolverConfig =
+            toml::from_str("[doh]\nurl = \"https://1.1.1.1/dns-query\"\npins = []").unwrap();
+        let err = config.doh.into_client().unwrap_err();
+        assert!(err.to_string().contains("not pinned"));
+    }
+
+    #[test]
+    fn resolver_picks_the_port_of_the_scheme() {
+        let resolver = Resolver::fixed(parse_static_hosts("example.com=1.2.3.4").unwrap());
+        let resolve = |url: &str| {
+            let uri: Uri = url.parse().unwrap();
+            resolver
+                .resolve(
+                    &uri,
+                    &ureq::config::Config::default(),
+                    NextTimeout {
+                        after: ureq::unversioned::transport::time::Duration::NotHappening,
+                        reason: ureq::Timeout::Global,
+                    },
+                )
+                .map(|addrs| addrs.iter().map(SocketAddr::port).collect::<Vec<_>>())
+        };
 
-#[cfg(not(target_env = "sgx"))]
-pub use tdx::*;
+        assert_eq!(resolve("https://example.com/").unwrap(), [443]);
+        assert_eq!(resolve("http://example.com/").unwrap(), [80]);
+        assert_eq!(resolve("https://example.com:8443/").unwrap(), [8443]);
+        assert_eq!(resolve("http://1.1.1.1:53/").unwrap(), [53]);
+        assert!(resolve("https://unknown.example.com/").is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use snarkvm::prelude::*;
+
+use snorkle_oracle_interface::files::write_atomic;
+
+use super::Oracle;
+use crate::programs::OracleProgram;
+
+/// Default location of the key cache; can be overridden with `SNORKLE_KEY_CACHE`
+const DEFAULT_KEY_CACHE: &str = ".snorkle/keys";
+
+/// On-disk cache of the proving and verifying keys of the oracle programs
+///
+/// Keys live in `<cache>/<program id>/<hash of the program source and snarkVM version>/`, so
+/// changing the program or upgrading snarkVM invalidates them.
+impl<N: Network> Oracle<N> {
+    fn key_cache_root() -> PathBuf {
+        std::env::var_os("SNORKLE_KEY_CACHE")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_KEY_CACHE))
+    }
+
+    fn key_cache_dir(&self, program: &OracleProgram<N>) -> anyhow::Result<PathBuf> {
+        let source = format!("{}\n{}", env!("SNARKVM_VERSION"), program.program);
+        let source_hash = crate::hash(source.as_bytes())?;
+
+        Ok(Self::key_cache_root()
+            .join(program.id().to_string())
+            .join(source_hash))
+    }
+
+    /// Load all cached keys of the programs into the VM
+    pub fn load_keys(&self) -> anyhow::Result<()> {
+        for program in &self.programs {
+            self.load_program_keys(program)?;
+        }
+
+        Ok(())
+    }
+
+    /// Load the cached keys of a program into the VM, and remove keys of other versions of it
+    fn load_program_keys(&self, program: &OracleProgram<N>) -> anyhow::Result<()> {
+        let dir = self.key_cache_dir(program)?;
+
+        // Remove keys of outdated versions of the program.
+        if let Some(parent) = dir.parent()
+            && parent.exists()
+        {
+            for entry in std::fs::read_dir(parent)? {
+                let path = entry?.path();
+                if path != dir {
+                    println!("Removing outdated keys at {}", path.display());
+                    std::fs::remove_dir_all(&path)?;
+                }
+            }
+        }
+
+        let process = self.vm.process();
+        let process = process.read();
+
+        for function in program.program.functions().keys() {
+            let prover_path = dir.join(format!("{function}.prover"));
+            let verifier_path = dir.join(format!("{function}.verifier"));
+
+            if !prover_path.exists() || !verifier_path.exists() {
+                continue;
+            }
+
+            // Unreadable keys are synthesized again, and replace the cached ones.
+            let (proving_key, verifying_key) =
+                match (read_key(&prover_path), read_key(&verifier_path)) {
+                    (Ok(proving_key), Ok(verifying_key)) => (proving_key, verifying_key),
+                    (Err(err), _) | (_, Err(err)) => {
+                        println!(
+                            "Ignoring cached keys for `{}/{function}`: {err:#}",
+                            program.id()
+                        );
+                        let _ = std::fs::remove_file(&prover_path);
+                        let _ = std::fs::remove_file(&verifier_path);
+                        continue;
+                    }
+                };
+
+            process.insert_proving_key(program.id(), function, proving_key)?;
+            process.insert_verifying_key(program.id(), function, verifying_key)?;
+            println!("Loaded cached keys for `{}/{function}`", program.id());
+        }
+
+        Ok(())
+    }
+
+    /// Write the keys of `function` to the cache, if they exist and have not been written yet
+    pub fn store_keys(&self, program: &OracleProgram<N>, function: &str) -> anyhow::Result<()> {
+        let _guard = self.key_cache.lock().unwrap();
+
+        let dir = self.key_cache_dir(program)?;
+        let prover_path = dir.join(format!("{function}.prover"));
+        let verifier_path = dir.join(format!("{function}.verifier"));
+
+        if prover_path.exists() && verifier_path.exists() {
+            return Ok(());
+        }
+
+        let process = self.vm.process();
+        let process = process.read();
+
+        let (Ok(proving_key), Ok(verifying_key)) = (
+            process.get_proving_key(program.id(), function),
+            process.get_verifying_key(program.id(), function),
+        ) else {
+            // The keys are only synthesized when the function is first executed.
+            return Ok(());
+        };
+
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create key cache at {}", dir.display()))?;
+        // The verifying key goes last, as the keys are only loaded if both exist.
+        write_atomic(&prover_path, &proving_key.to_bytes_le()?)?;
+        write_atomic(&verifier_path, &verifying_key.to_bytes_le()?)?;
+        println!(
+            "Stored keys for `{}/{function}` at {}",
+            program.id(),
+            dir.display()
+        );
+
+        Ok(())
+    }
+}
+
+/// Read a proving or verifying key from the cache
+fn read_key<K: FromBytes>(path: &Path) -> anyhow::Result<K> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    K::from_bytes_le(&bytes).with_context(|| format!("Invalid key at {}", path.display()))
+}
@@ -5,109 +5,172 @@
 extern crate sgx_tstd as std;
 
 use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpListener};
+use std::path::PathBuf;
+use std::sync::Mutex;
 
 use anyhow::Context;
 
-use rand::rngs::OsRng;
-
 use base64::engine::general_purpose::URL_SAFE as BASE64;
 use base64::prelude::*;
 
-#[cfg(feature = "reuse-vm")]
 use snarkvm::ledger::store::helpers::memory::ConsensusMemory;
 use snarkvm::prelude::*;
 
-use snorkle_oracle_interface::{GameData, OracleInfo};
+use snorkle_oracle_interface::{
+    FeeOptions, FetchProof, GAME_URL, GameData, OracleError, OracleInfo, PriceData, files,
+};
 
-#[cfg(all(target_arch = "x86_64", not(target_env = "sgx")))]
-mod tdx;
+use enclave::{Enclave, EnclaveRng};
+use fetch::FetchRecord;
+use programs::OracleProgram;
+use schema::AleoStruct;
+use sources::Sources;
 
+mod enclave;
 mod http;
 
+mod batch;
+mod chain;
+mod dns;
+mod fee;
 mod fetch;
 mod gateway;
+mod keys;
+mod metrics;
+mod programs;
+mod schema;
+mod sources;
+mod tls;
 mod transaction;
 
-#[cfg(target_env = "sgx")]
-use sgx_crypto::{
-    crypto::{ecc::EccHandle, hash::Sha256},
-    types::*,
-};
+/// Hash arbitrary bytes into a field element (as a string) using BHP1024
+fn hash(bytes: &[u8]) -> anyhow::Result<String> {
+    let mut bits: Vec<bool> = Vec::with_capacity(bytes.len() * 8);
+    for byte in bytes {
+        for i in (0..8).rev() {
+            bits.push((byte >> i) & 1 == 1);
+        }
+    }
+
+    let hasher = &snarkvm::prelude::BHP_1024;
+    hasher.hash(&bits).map(|res| res.to_string())
+}
 
 fn main() -> anyhow::Result<()> {
-    let oracle = Oracle::<TestnetV0>::new()?;
+    let enclave = enclave::open().with_context(|| "Failed to open the enclave")?;
+    println!("Running in {} enclave", enclave.name());
+    println!("Resolving hosts with {}", enclave.resolver().describe());
+
+    let oracle = Oracle::<TestnetV0>::new(enclave)?;
     oracle.run()
 }
 
 struct Oracle<N: Network> {
     key: PrivateKey<N>,
-    program: Program<N>,
-    #[cfg(feature = "reuse-vm")]
+    programs: Vec<OracleProgram<N>>,
+    /// Shared across requests, so that circuit keys are only synthesized once
     vm: VM<N, ConsensusMemory<N>>,
     info: OracleInfo,
+    /// Held while writing to the key cache, which parallel submissions would race on
+    key_cache: Mutex<()>,
+    /// Public fees authorized so far by the batch being processed, if any
+    reserved_fees: Mutex<Option<u64>>,
+    /// The data sources the oracle may fetch from, and how
+    sources: Sources,
+    enclave: Box<dyn Enclave>,
 }
 
 impl<N: Network> Oracle<N> {
-    /// Constructor for SGX (currently broken)
-    #[cfg(target_env = "sgx")]
-    pub fn new() -> anyhow::Result<Self> {
-        todo!();
-    }
+    pub fn new(enclave: Box<dyn Enclave>) -> anyhow::Result<Self> {
+        let sources =
+            Sources::load(&fetch::source_names()).with_context(|| "Failed to load data sources")?;
+        fetch::check_price_policy(&sources.prices)?;
 
-    /// Constructor for TDX and macOS "dummy" oracle
-    #[cfg(not(target_env = "sgx"))]
-    pub fn new() -> anyhow::Result<Self> {
-        //FIXME don't hardcode this
-        const TESTNET_PRIVATE_KEY: &str =
-            "APrivateKey1zkp3TFLRzSPqqhNh9o6csyZ1yPaizmtUzwEWMeTJ9bXxQMA";
-        let program = Self::init_program();
-        println!("Loaded program");
-
-        #[cfg(feature = "reuse-vm")]
-        {
-            let vm = Self::init_vm()?;
-            println!("Created snarkVM instance");
-
-            // Add the oracle program to the process.
-            vm.process()
-                .write()
-                .add_program(&program)
-                .with_context(|| "Failed to add program to VM")?;
-        }
+        let vm = Self::init_vm()?;
+        println!("Created snarkVM instance");
+
+        // Add the oracle programs and their imports to the process.
+        let (programs, default_program) =
+            Self::init_programs(&vm).with_context(|| "Failed to load programs")?;
 
-        // Create the private key.
-        let private_key = PrivateKey::<N>::from_str(TESTNET_PRIVATE_KEY)
-            .expect("Failed to initialize private key");
+        let private_key = Self::load_key(&*enclave)?;
 
         let address = Address::<N>::try_from(&private_key)?;
         println!("Oracle's address is {address}");
 
-        let report = Self::generate_report(&address.to_string())?;
+        let report = BASE64.encode(enclave.attest(address.to_string().as_bytes())?);
+        println!("Created {} report", enclave.name());
 
-        Ok(Self {
+        let oracle = Self {
             info: OracleInfo {
                 address: address.to_string(),
                 report,
+                programs: programs
+                    .iter()
+                    .map(|program| program.info.clone())
+                    .collect(),
+                default_program,
             },
-            program,
-            #[cfg(feature = "reuse-vm")]
+            programs,
             vm,
             key: private_key,
-        })
+            key_cache: Mutex::new(()),
+            reserved_fees: Mutex::new(None),
+            sources,
+            enclave,
+        };
+
+        oracle
+            .load_keys()
+            .with_context(|| "Failed to load cached keys")?;
+
+        Ok(oracle)
     }
 
-    #[cfg(all(not(target_arch = "x86_64"), not(target_env = "sgx")))]
-    fn generate_report(_address: &str) -> anyhow::Result<String> {
-        println!("Created Dummy Oracle!");
-        Ok(BASE64.encode("Hello World"))
+    /// Load the oracle's key from the sealed file in `SNORKLE_SEALED_KEY`, generating and
+    /// sealing a new key if it does not exist yet
+    ///
+    /// Falls back to a fixed development key if `SNORKLE_SEALED_KEY` is not set, but only on
+    /// the host backend; a well-known key must never be attested by an enclave.
+    fn load_key(enclave: &dyn Enclave) -> anyhow::Result<PrivateKey<N>> {
+        let Some(path) = std::env::var_os("SNORKLE_SEALED_KEY").map(PathBuf::from) else {
+            if enclave.name() != "host" {
+                anyhow::bail!(
+                    "SNORKLE_SEALED_KEY must be set on the {} backend",
+                    enclave.name()
+                );
+            }
+
+            const TESTNET_PRIVATE_KEY: &str =
+                "APrivateKey1zkp3TFLRzSPqqhNh9o6csyZ1yPaizmtUzwEWMeTJ9bXxQMA";
+            println!("SNORKLE_SEALED_KEY is not set; using the development key");
+            return PrivateKey::from_str(TESTNET_PRIVATE_KEY);
+        };
+
+        if path.exists() {
+            let sealed = std::fs::read(&path)
+                .with_context(|| format!("Failed to read sealed key at {}", path.display()))?;
+            let key = enclave
+                .unseal(&sealed)
+                .with_context(|| format!("Failed to unseal key at {}", path.display()))?;
+            return PrivateKey::from_str(std::str::from_utf8(&key)?);
+        }
+
+        let key = PrivateKey::<N>::new(&mut EnclaveRng(enclave))?;
+        let sealed = enclave.seal(key.to_string().as_bytes())?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        files::write_atomic(&path, &sealed)
+            .with_context(|| format!("Failed to write sealed key to {}", path.display()))?;
+        println!("Generated a new key and sealed it to {}", path.display());
+
+        Ok(key)
     }
 
-    #[cfg(all(target_arch = "x86_64", not(target_env = "sgx")))]
-    fn generate_report(address: &str) -> anyhow::Result<String> {
-        let address = BASE64.encode(address);
-        let report = BASE64.encode(tdx::generate_report(&address)?);
-        println!("Created TDX Oracle!");
-        Ok(report)
+    /// Randomness for signatures and proofs
+    fn rng(&self) -> EnclaveRng<'_> {
+        EnclaveRng(&*self.enclave)
     }
 
     /// Main loop of the oracle. Can handle one gateway connection
@@ -130,66 +193,171 @@ impl<N: Network> Oracle<N> {
         }
     }
 
-    fn hash(&self, bytes: &[u8]) -> anyhow::Result<String> {
-        let mut bits: Vec<bool> = Vec::with_capacity(bytes.len() * 8);
-        for byte in bytes {
-            for i in (0..8).rev() {
-                bits.push((byte >> i) & 1 == 1);
-            }
-        }
-
-        let hasher = &snarkvm::prelude::BHP_1024;
-        hasher.hash(&bits).map(|res| res.to_string())
-    }
-
-    fn generate_registration(&self) -> anyhow::Result<Transaction<N>> {
+    fn generate_registration(
+        &self,
+        program: &OracleProgram<N>,
+        fee: &FeeOptions,
+    ) -> anyhow::Result<Transaction<N>> {
         let report = self.info.report.as_bytes();
-        let attestation_hash = self.hash(report)?;
+        let attestation_hash = hash(report)?;
 
         let hash_value = Value::<N>::from_str(&attestation_hash)?;
 
-        let txn = self.generate_transaction("register", &[hash_value])?;
+        let txn = self.generate_transaction(program, &program.info.register, &[hash_value], fee)?;
         println!("Registration transaction id is {}", txn.id());
 
         Ok(txn)
     }
 
+    /// Derive the event ID for a game from the data source and the game's ID
+    ///
+    /// Revision 0 is the original submission; later revisions are corrections. Game IDs must
+    /// not contain `#`, so that no game and revision share the preimage of another.
+    fn event_id(&self, game_id: &str, revision: u32) -> anyhow::Result<String> {
+        if game_id.is_empty() || game_id.contains('#') {
+            return Err(OracleError::InvalidGameId(game_id.to_string()).into());
+        }
+
+        let preimage = if revision == 0 {
+            format!("{}/{game_id}", GAME_URL)
+        } else {
+            format!("{}/{game_id}#{revision}", GAME_URL)
+        };
+
+        hash(preimage.as_bytes())
+    }
+
+    /// Fetch the game's score, unless finalize would reject its submission
+    ///
+    /// Also returns the signed proof of the response the score was read from.
+    fn prepare_submission(
+        &self,
+        program: &OracleProgram<N>,
+        game_id: &str,
+        revision: u32,
+    ) -> anyhow::Result<(GameData, FetchProof)> {
+        let event_id = self.event_id(game_id, revision)?;
+
+        // Fail before fetching and proving a transaction that finalize would reject
+        self.check_submission(program, &event_id)?;
+
+        let ((home, away), record) = self.fetch_scores(game_id)?;
+        let proof = self.sign_fetch(&event_id, record)?;
+
+        Ok((
+            GameData {
+                event_id,
+                home_score: home,
+                away_score: away,
+            },
+            proof,
+        ))
+    }
+
+    /// Bundle a response of the data source with the oracle's signature on it
+    fn sign_fetch(&self, event_id: &str, record: FetchRecord) -> anyhow::Result<FetchProof> {
+        let mut proof = FetchProof {
+            event_id: event_id.to_string(),
+            url: record.url,
+            timestamp: record.timestamp,
+            body_hash: FetchProof::hash_body(&record.body),
+            body: record.body,
+            certificates: record.certificates,
+            oracle: self.info.address.clone(),
+            signature: String::new(),
+        };
+        proof.signature = self
+            .key
+            .sign_bytes(&proof.digest(), &mut self.rng())?
+            .to_string();
+
+        Ok(proof)
+    }
+
     /// Generate a new transaction that contains the game's score
-    fn generate_submission(&self, game_id: String) -> anyhow::Result<(GameData, Transaction<N>)> {
-        let (home, away) = self.fetch_scores(&game_id)?;
-        let uid: u128 = rand::random();
-        let event_id = format!("{game_id}_{uid}");
-
-        let game_data = GameData {
-            event_id: self.hash(event_id.as_bytes())?,
-            home_score: home,
-            away_score: away,
+    fn generate_submission(
+        &self,
+        program: &OracleProgram<N>,
+        game_id: String,
+        revision: u32,
+        fee: &FeeOptions,
+    ) -> anyhow::Result<(GameData, FetchProof, Transaction<N>)> {
+        let (game_data, proof) = self.prepare_submission(program, &game_id, revision)?;
+
+        let txn = self.generate_signed_transaction(
+            program,
+            &program.info.submit,
+            game_data.to_value()?,
+            fee,
+        )?;
+
+        Ok((game_data, proof, txn))
+    }
+
+    /// Like [`Self::generate_submission`], but return authorizations of the submission and
+    /// its fee instead of proving them
+    fn authorize_submission(
+        &self,
+        program: &OracleProgram<N>,
+        game_id: String,
+        revision: u32,
+        fee: &FeeOptions,
+    ) -> anyhow::Result<(GameData, FetchProof, Authorization<N>, Authorization<N>)> {
+        let (game_data, proof) = self.prepare_submission(program, &game_id, revision)?;
+
+        let inputs = self.sign_data(game_data.to_value()?)?;
+        let (authorization, fee_authorization) =
+            self.generate_authorization(program, &program.info.submit, &inputs, fee)?;
+
+        Ok((game_data, proof, authorization, fee_authorization))
+    }
+
+    /// Generate a new transaction that contains the current price of an asset pair
+    fn generate_price_update(
+        &self,
+        program: &OracleProgram<N>,
+        pair: &str,
+        fee: &FeeOptions,
+    ) -> anyhow::Result<(PriceData, Transaction<N>)> {
+        let Some(transition) = &program.info.submit_price else {
+            return Err(OracleError::NoPriceFeed(program.info.id.clone()).into());
         };
 
-        // Create the game data.
-        let data_str = format!(
-            r"
-{{
-    id: {},
-    home_team_score: {}u8,
-    away_team_score: {}u8
-}}",
-            game_data.event_id, game_data.home_score, game_data.away_score
-        );
+        // Fail before fetching and proving a transaction that finalize would reject
+        self.check_registration(program)?;
+
+        let price_data = self.fetch_price(pair)?;
 
-        let game_data_value = Value::<N>::from_str(&data_str).expect("Failed to create game data");
+        let txn =
+            self.generate_signed_transaction(program, transition, price_data.to_value()?, fee)?;
 
-        let signature = self.key.sign(&game_data_value.to_fields()?, &mut OsRng)?;
+        Ok((price_data, txn))
+    }
+
+    /// `data` followed by the oracle's signature on it, as taken by signed transitions
+    fn sign_data(&self, data: Value<N>) -> anyhow::Result<[Value<N>; 2]> {
+        let signature = self.key.sign(&data.to_fields()?, &mut self.rng())?;
         let signature = Value::<N>::from_str(&signature.to_string())?;
 
-        let txn = self.generate_transaction("submit_event", &[game_data_value, signature])?;
+        Ok([data, signature])
+    }
 
-        Ok((game_data, txn))
+    /// Sign `data` with the oracle's key and pass both to `transition`
+    fn generate_signed_transaction(
+        &self,
+        program: &OracleProgram<N>,
+        transition: &str,
+        data: Value<N>,
+        fee: &FeeOptions,
+    ) -> anyhow::Result<Transaction<N>> {
+        let inputs = self.sign_data(data)?;
+
+        self.generate_transaction(program, transition, &inputs, fee)
     }
 }
 
 #[cfg(target_env = "sgx")]
-#[no_mangle]
+#[unsafe(no_mangle)]
 pub extern "C" fn t_main() -> sgx_types::sgx_status_t {
     match main() {
         Ok(_) => sgx_types::sgx_status_t::SGX_SUCCESS,
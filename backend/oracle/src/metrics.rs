@@ -0,0 +1,52 @@
+//! Prometheus metrics exported to the gateway
+
+use std::sync::LazyLock;
+
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounter, Opts, Registry, TextEncoder};
+
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
+
+fn register<M: prometheus::core::Collector + Clone + 'static>(metric: M) -> M {
+    REGISTRY.register(Box::new(metric.clone())).unwrap();
+    metric
+}
+
+/// Time spent in `generate_transaction`, by transition
+pub static PROVING_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    let opts = HistogramOpts::new(
+        "snorkle_oracle_proving_duration_seconds",
+        "Time spent generating a transaction",
+    )
+    .buckets(vec![1.0, 2.5, 5.0, 10.0, 20.0, 40.0, 80.0, 160.0]);
+    register(HistogramVec::new(opts, &["transition"]).unwrap())
+});
+
+/// Latency of requests to the data source
+pub static FETCH_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    let opts = HistogramOpts::new(
+        "snorkle_oracle_fetch_duration_seconds",
+        "Latency of requests to the data source",
+    );
+    register(HistogramVec::new(opts, &["source"]).unwrap())
+});
+
+/// Failed requests to the data source
+pub static FETCH_ERRORS: LazyLock<IntCounter> = LazyLock::new(|| {
+    let opts = Opts::new(
+        "snorkle_oracle_fetch_errors_total",
+        "Number of failed requests to the data source",
+    );
+    register(IntCounter::with_opts(opts).unwrap())
+});
+
+/// Encode all metrics in the Prometheus text format
+pub fn encode() -> anyhow::Result<String> {
+    // Make sure every metric is registered, even if it has not been used yet
+    LazyLock::force(&PROVING_DURATION);
+    LazyLock::force(&FETCH_DURATION);
+    LazyLock::force(&FETCH_ERRORS);
+
+    let mut buffer = vec![];
+    TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer)?;
+    Ok(String::from_utf8(buffer)?)
+}
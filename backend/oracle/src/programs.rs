@@ -0,0 +1,270 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use serde::Deserialize;
+
+use snarkvm::ledger::store::helpers::memory::ConsensusMemory;
+use snarkvm::prelude::*;
+
+use snorkle_oracle_interface::{GameData, OracleError, PriceData, ProgramInfo};
+
+use super::Oracle;
+use crate::schema;
+
+/// Registration window of the embedded program
+const DEFAULT_REGISTRATION_WINDOW: u32 = 10_000;
+
+/// A program the oracle can submit to
+pub struct OracleProgram<N: Network> {
+    pub program: Program<N>,
+    pub info: ProgramInfo,
+}
+
+impl<N: Network> OracleProgram<N> {
+    pub fn id(&self) -> &ProgramID<N> {
+        self.program.id()
+    }
+}
+
+/// The program registry, read from the file in `SNORKLE_PROGRAMS`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    /// ID of the program used if a request does not name one
+    default: String,
+    programs: Vec<ManifestEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestEntry {
+    /// Path to the program source, relative to the manifest
+    path: PathBuf,
+    /// Paths to the programs it imports, relative to the manifest
+    #[serde(default)]
+    imports: Vec<PathBuf>,
+    #[serde(default = "default_register")]
+    register: String,
+    #[serde(default = "default_submit")]
+    submit: String,
+    /// The transition that submits price updates, if the program has a price feed
+    submit_price: Option<String>,
+    registration_window: Option<u32>,
+}
+
+fn default_register() -> String {
+    "register".to_string()
+}
+
+fn default_submit() -> String {
+    "submit_event".to_string()
+}
+
+fn read_program<N: Network>(path: &Path) -> anyhow::Result<Program<N>> {
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read program at {}", path.display()))?;
+    Program::from_str(&source).with_context(|| format!("Invalid program at {}", path.display()))
+}
+
+/// Make sure `transition` exists and takes the expected number of inputs
+fn check_transition<N: Network>(
+    program: &Program<N>,
+    transition: &str,
+    num_inputs: usize,
+) -> anyhow::Result<()> {
+    let function = program
+        .get_function(&Identifier::from_str(transition)?)
+        .with_context(|| format!("{} has no transition `{transition}`", program.id()))?;
+
+    if function.inputs().len() != num_inputs {
+        anyhow::bail!(
+            "`{}/{transition}` takes {} inputs, expected {num_inputs}",
+            program.id(),
+            function.inputs().len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Make sure a program that records when oracles registered has a registration window
+///
+/// Without one, the oracle could not tell when its registration expires. Programs that do
+/// not record it (like `proto_snorkle_oracle_001.aleo`) must not have a window.
+fn check_registration_window<N: Network>(
+    program: &Program<N>,
+    registration_window: Option<u32>,
+) -> anyhow::Result<()> {
+    let value_type = program
+        .get_mapping(&Identifier::from_str("registered_oracles")?)
+        .with_context(|| format!("{} has no `registered_oracles` mapping", program.id()))?
+        .value()
+        .plaintext_type()
+        .clone();
+    let timestamp = Identifier::from_str("registration_timestamp")?;
+    let records_timestamp = match value_type {
+        PlaintextType::Struct(name) => program
+            .get_struct(&name)?
+            .members()
+            .contains_key(&timestamp),
+        _ => false,
+    };
+
+    match (records_timestamp, registration_window) {
+        (true, None) => anyhow::bail!(
+            "{} records registration timestamps, so it needs a `registration_window`",
+            program.id()
+        ),
+        (false, Some(_)) => anyhow::bail!(
+            "{} does not record registration timestamps, so it cannot have a `registration_window`",
+            program.id()
+        ),
+        (false, None) => println!("Registrations with {} do not expire", program.id()),
+        (true, Some(_)) => {}
+    }
+
+    Ok(())
+}
+
+impl<N: Network> Oracle<N> {
+    /// Load the programs listed in `SNORKLE_PROGRAMS` into the VM, or the embedded program
+    /// if it is not set
+    ///
+    /// Returns the programs and the ID of the default one.
+    pub fn init_programs(
+        vm: &VM<N, ConsensusMemory<N>>,
+    ) -> anyhow::Result<(Vec<OracleProgram<N>>, String)> {
+        let Some(manifest_path) = std::env::var_os("SNORKLE_PROGRAMS").map(PathBuf::from) else {
+            let program = Program::<N>::from_str(include_str!(
+                "../../resources/proto_snorkle_oracle_002.aleo"
+            ))
+            .with_context(|| "Failed to create the program")?;
+            let entry = ManifestEntry {
+                path: PathBuf::new(),
+                imports: vec![],
+                register: default_register(),
+                submit: default_submit(),
+                submit_price: None,
+                registration_window: Some(DEFAULT_REGISTRATION_WINDOW),
+            };
+
+            let program = Self::add_program(vm, program, &entry)?;
+            let default = program.info.id.clone();
+            return Ok((vec![program], default));
+        };
+
+        let manifest = std::fs::read_to_string(&manifest_path).with_context(|| {
+            format!(
+                "Failed to read program registry at {}",
+                manifest_path.display()
+            )
+        })?;
+        let manifest: Manifest = toml::from_str(&manifest)
+            .with_context(|| format!("Invalid program registry at {}", manifest_path.display()))?;
+        let base = manifest_path.parent().unwrap_or(Path::new("."));
+
+        let mut programs = vec![];
+        for entry in &manifest.programs {
+            for import in &entry.imports {
+                let import = read_program::<N>(&base.join(import))?;
+                let process = vm.process();
+                let mut process = process.write();
+                if !process.contains_program(import.id()) {
+                    process
+                        .add_program(&import)
+                        .with_context(|| format!("Failed to add {} to VM", import.id()))?;
+                }
+            }
+
+            let program = read_program(&base.join(&entry.path))?;
+            if programs
+                .iter()
+                .any(|other: &OracleProgram<N>| other.id() == program.id())
+            {
+                anyhow::bail!("{} is listed more than once", program.id());
+            }
+
+            programs.push(Self::add_program(vm, program, entry)?);
+        }
+
+        if !programs
+            .iter()
+            .any(|program| program.info.id == manifest.default)
+        {
+            anyhow::bail!(
+                "Default program {} is not in the registry",
+                manifest.default
+            );
+        }
+
+        Ok((programs, manifest.default))
+    }
+
+    /// Check the transitions of `program` against the schemas and add it to the VM
+    fn add_program(
+        vm: &VM<N, ConsensusMemory<N>>,
+        program: Program<N>,
+        entry: &ManifestEntry,
+    ) -> anyhow::Result<OracleProgram<N>> {
+        check_transition(&program, &entry.register, 1)?;
+        check_transition(&program, &entry.submit, 2)?;
+        check_registration_window(&program, entry.registration_window)?;
+        schema::check_input::<GameData, N>(&program, &entry.submit, 0)?;
+        if let Some(submit_price) = &entry.submit_price {
+            check_transition(&program, submit_price, 2)?;
+            schema::check_input::<PriceData, N>(&program, submit_price, 0)?;
+        }
+
+        vm.process()
+            .write()
+            .add_program(&program)
+            .with_context(|| format!("Failed to add {} to VM", program.id()))?;
+        println!("Loaded program {}", program.id());
+
+        let info = ProgramInfo {
+            id: program.id().to_string(),
+            imports: program.imports().keys().map(ToString::to_string).collect(),
+            register: entry.register.clone(),
+            submit: entry.submit.clone(),
+            submit_price: entry.submit_price.clone(),
+            registration_window: entry.registration_window,
+        };
+
+        Ok(OracleProgram { program, info })
+    }
+
+    /// Look up a program by ID, or the default program if `id` is `None`
+    pub fn program(&self, id: Option<&str>) -> anyhow::Result<&OracleProgram<N>> {
+        let id = id.unwrap_or(&self.info.default_program);
+
+        self.programs
+            .iter()
+            .find(|program| program.info.id == id)
+            .ok_or_else(|| OracleError::UnknownProgram(id.to_string()).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program(source: &str) -> Program<TestnetV0> {
+        Program::from_str(source).unwrap()
+    }
+
+    #[test]
+    fn registration_windows_match_the_programs() {
+        let expiring = program(include_str!(
+            "../../resources/proto_snorkle_oracle_002.aleo"
+        ));
+        assert!(check_registration_window(&expiring, Some(10_000)).is_ok());
+        assert!(check_registration_window(&expiring, None).is_err());
+
+        let permanent = program(include_str!(
+            "../../resources/proto_snorkle_oracle_001.aleo"
+        ));
+        assert!(check_registration_window(&permanent, None).is_ok());
+        assert!(check_registration_window(&permanent, Some(10_000)).is_err());
+    }
+}
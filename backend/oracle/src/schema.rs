@@ -0,0 +1,256 @@
+use std::str::FromStr;
+
+use snarkvm::prelude::*;
+
+use snorkle_oracle_interface::{GameData, PriceData};
+
+/// A Rust struct that is passed to a transition as an Aleo struct
+///
+/// The schema is checked against the struct definitions of each program on startup, so
+/// that a mismatch does not surface as a failed execution later.
+pub trait AleoStruct {
+    /// Names and types of the members, in the order in which the Aleo struct declares them
+    fn schema() -> Vec<(&'static str, LiteralType)>;
+
+    /// Values of the members, in the order of [`Self::schema`]
+    fn members<N: Network>(&self) -> anyhow::Result<Vec<Literal<N>>>;
+
+    /// Convert into an Aleo struct value
+    fn to_value<N: Network>(&self) -> anyhow::Result<Value<N>> {
+        let schema = Self::schema();
+        let members = self.members::<N>()?;
+
+        if schema.len() != members.len() {
+            anyhow::bail!("Expected {} members, got {}", schema.len(), members.len());
+        }
+
+        let members = schema
+            .into_iter()
+            .zip(members)
+            .map(|((name, literal_type), literal)| {
+                if literal.to_type() != literal_type {
+                    anyhow::bail!(
+                        "Member `{name}` should be a {literal_type}, got {}",
+                        literal.to_type()
+                    );
+                }
+
+                Ok((Identifier::from_str(name)?, Plaintext::from(literal)))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Value::Plaintext(Plaintext::Struct(
+            members,
+            Default::default(),
+        )))
+    }
+}
+
+impl AleoStruct for GameData {
+    fn schema() -> Vec<(&'static str, LiteralType)> {
+        vec![
+            ("id", LiteralType::Field),
+            ("home_team_score", LiteralType::U8),
+            ("away_team_score", LiteralType::U8),
+        ]
+    }
+
+    fn members<N: Network>(&self) -> anyhow::Result<Vec<Literal<N>>> {
+        Ok(vec![
+            Literal::Field(Field::from_str(&self.event_id)?),
+            Literal::U8(U8::new(self.home_score)),
+            Literal::U8(U8::new(self.away_score)),
+        ])
+    }
+}
+
+impl AleoStruct for PriceData {
+    fn schema() -> Vec<(&'static str, LiteralType)> {
+        vec![
+            ("pair", LiteralType::Field),
+            ("price", LiteralType::U128),
+            ("timestamp", LiteralType::U64),
+            ("sources", LiteralType::Field),
+        ]
+    }
+
+    /// The pair and the source set are stored as hashes, so they fit into a field
+    fn members<N: Network>(&self) -> anyhow::Result<Vec<Literal<N>>> {
+        let pair = crate::hash(self.pair.as_bytes())?;
+        let sources = crate::hash(self.sources.join(",").as_bytes())?;
+
+        Ok(vec![
+            Literal::Field(Field::from_str(&pair)?),
+            Literal::U128(U128::new(self.price)),
+            Literal::U64(U64::new(self.timestamp)),
+            Literal::Field(Field::from_str(&sources)?),
+        ])
+    }
+}
+
+/// Make sure input `index` of `transition` is a struct with the members of `T`
+pub fn check_input<T: AleoStruct, N: Network>(
+    program: &Program<N>,
+    transition: &str,
+    index: usize,
+) -> anyhow::Result<()> {
+    let function = program.get_function(&Identifier::from_str(transition)?)?;
+    let Some(input) = function.inputs().get_index(index) else {
+        anyhow::bail!("`{}/{transition}` has no input {index}", program.id());
+    };
+
+    let (ValueType::Constant(PlaintextType::Struct(name))
+    | ValueType::Public(PlaintextType::Struct(name))
+    | ValueType::Private(PlaintextType::Struct(name))) = input.value_type()
+    else {
+        anyhow::bail!(
+            "Input {index} of `{}/{transition}` is a {}, expected a struct",
+            program.id(),
+            input.value_type()
+        );
+    };
+
+    let members = program.get_struct(name)?.members();
+    let schema = T::schema();
+
+    let matches = members.len() == schema.len()
+        && members
+            .iter()
+            .zip(&schema)
+            .all(|((member, member_type), (name, literal_type))| {
+                member.to_string() == *name && *member_type == PlaintextType::Literal(*literal_type)
+            });
+
+    if !matches {
+        let expected = schema
+            .iter()
+            .map(|(name, literal_type)| format!("{name} as {literal_type}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        anyhow::bail!(
+            "Struct `{name}` of {} does not match the schema ({expected})",
+            program.id()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type N = TestnetV0;
+
+    fn latest() -> Program<N> {
+        Program::from_str(include_str!(
+            "../../resources/proto_snorkle_oracle_003.aleo"
+        ))
+        .unwrap()
+    }
+
+    /// A program whose `submit` takes an `EventData` with `members`
+    fn with_members(members: &str) -> Program<N> {
+        Program::from_str(&format!(
+            "program schema_test.aleo;\n\nstruct EventData:\n{members}\n\nfunction submit:\n    input r0 as EventData.private;\n"
+        ))
+        .unwrap()
+    }
+
+    fn game_data() -> GameData {
+        GameData {
+            event_id: "123field".to_string(),
+            home_score: 3,
+            away_score: 1,
+        }
+    }
+
+    #[test]
+    fn schemas_match_the_latest_program() {
+        let program = latest();
+        check_input::<GameData, N>(&program, "submit_event", 0).unwrap();
+        check_input::<PriceData, N>(&program, "submit_price", 0).unwrap();
+    }
+
+    #[test]
+    fn values_round_trip_through_the_program_structs() {
+        let program = latest();
+        let price_data = PriceData {
+            pair: "ALEO/USD".to_string(),
+            price: 1_234_567,
+            timestamp: 1_700_000_000,
+            sources: vec!["coinbase".to_string(), "kraken".to_string()],
+        };
+        let values = [
+            ("EventData", game_data().to_value::<N>().unwrap()),
+            ("PriceData", price_data.to_value::<N>().unwrap()),
+        ];
+
+        for (name, value) in values {
+            // The value parses back into itself
+            assert_eq!(Value::<N>::from_str(&value.to_string()).unwrap(), value);
+
+            let Value::Plaintext(Plaintext::Struct(members, _)) = value else {
+                panic!("{name} is not a struct");
+            };
+            let definition = program
+                .get_struct(&Identifier::from_str(name).unwrap())
+                .unwrap()
+                .members();
+            assert_eq!(members.len(), definition.len());
+            for ((member, plaintext), (expected, member_type)) in members.iter().zip(definition) {
+                assert_eq!(member, expected);
+                let Plaintext::Literal(literal, _) = plaintext else {
+                    panic!("member {member} of {name} is not a literal");
+                };
+                assert_eq!(PlaintextType::Literal(literal.to_type()), *member_type);
+            }
+        }
+
+        let Value::Plaintext(Plaintext::Struct(members, _)) = game_data().to_value::<N>().unwrap()
+        else {
+            unreachable!();
+        };
+        assert_eq!(
+            members[&Identifier::from_str("home_team_score").unwrap()].to_string(),
+            "3u8"
+        );
+    }
+
+    #[test]
+    fn member_type_mismatches_are_rejected() {
+        let program = with_members(
+            "    id as field;\n    home_team_score as u16;\n    away_team_score as u8;",
+        );
+        let err = check_input::<GameData, N>(&program, "submit", 0).unwrap_err();
+        assert!(err.to_string().contains("does not match the schema"));
+    }
+
+    #[test]
+    fn missing_and_extra_members_are_rejected() {
+        let missing = with_members("    id as field;\n    home_team_score as u8;");
+        assert!(check_input::<GameData, N>(&missing, "submit", 0).is_err());
+
+        let extra = with_members(
+            "    id as field;\n    home_team_score as u8;\n    away_team_score as u8;\n    extra as u8;",
+        );
+        assert!(check_input::<GameData, N>(&extra, "submit", 0).is_err());
+
+        let renamed =
+            with_members("    id as field;\n    home_score as u8;\n    away_team_score as u8;");
+        assert!(check_input::<GameData, N>(&renamed, "submit", 0).is_err());
+
+        let matching = with_members(
+            "    id as field;\n    home_team_score as u8;\n    away_team_score as u8;",
+        );
+        check_input::<GameData, N>(&matching, "submit", 0).unwrap();
+    }
+
+    #[test]
+    fn inputs_that_are_not_structs_are_rejected() {
+        let program = latest();
+        let err = check_input::<GameData, N>(&program, "register", 0).unwrap_err();
+        assert!(err.to_string().contains("expected a struct"));
+        assert!(check_input::<GameData, N>(&program, "submit_event", 2).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anyhow::Context;
+
+use base64::prelude::*;
+
+use serde::Deserialize;
+
+use crate::tls::is_root_pin;
+
+/// Prefix of pinned key hashes
+const PIN_PREFIX: &str = "sha256/";
+
+/// The source registry, embedded from `resources/sources.toml`
+///
+/// It is part of the oracle's binary, and so of its measurement; nothing outside the
+/// enclave can change where the oracle fetches from.
+const MANIFEST: &str = include_str!("../../resources/sources.toml");
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    sources: BTreeMap<String, ManifestEntry>,
+    prices: PricePolicy,
+}
+
+/// When price sources agree enough to push a price
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PricePolicy {
+    /// The minimum number of sources whose prices agree
+    pub quorum: usize,
+    /// How far (in basis points) a price may be from the median of all prices to agree
+    pub max_deviation: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestEntry {
+    hosts: Vec<String>,
+    #[serde(default)]
+    pins: Vec<String>,
+    max_response_size: u64,
+    timeout: u64,
+}
+
+/// How the oracle may talk to a data source
+#[derive(Clone, Debug)]
+pub struct Source {
+    pub name: String,
+    /// The only hosts the oracle may connect to for this source
+    pub hosts: Vec<String>,
+    /// SHA-256 hashes of SubjectPublicKeyInfos, one of which must be that of a certificate
+    /// the server presents; unpinned sources are only fetched from on the `host` backend
+    pub pins: Vec<[u8; 32]>,
+    pub max_response_size: u64,
+    pub timeout: Duration,
+}
+
+/// Parse a pin of the form `sha256/<base64>`
+pub fn parse_pin(pin: &str) -> anyhow::Result<[u8; 32]> {
+    let hash = pin
+        .strip_prefix(PIN_PREFIX)
+        .with_context(|| format!("Pin `{pin}` does not start with `{PIN_PREFIX}`"))?;
+    let hash = BASE64_STANDARD
+        .decode(hash)
+        .with_context(|| format!("Pin `{pin}` is not valid base64"))?;
+
+    hash.try_into()
+        .map_err(|_| anyhow::anyhow!("Pin `{pin}` is not a SHA-256 hash"))
+}
+
+/// Parse pins of the form `sha256/<base64>`, which must not be those of well-known roots
+///
+/// Every publicly trusted certificate leads up to one of these roots, so pinning one would
+/// only rule out the other CAs; pins must be of the server's certificate or an intermediate.
+pub fn parse_pins(pins: &[String]) -> anyhow::Result<Vec<[u8; 32]>> {
+    pins.iter()
+        .map(|pin| {
+            let hash = parse_pin(pin)?;
+            if is_root_pin(&hash) {
+                anyhow::bail!(
+                    "Pin `{pin}` is that of a well-known root; pin the server's certificate or an intermediate instead"
+                );
+            }
+            Ok(hash)
+        })
+        .collect()
+}
+
+impl Source {
+    fn new(name: String, entry: ManifestEntry) -> anyhow::Result<Self> {
+        if entry.hosts.is_empty() {
+            anyhow::bail!("Source `{name}` allows no hosts");
+        }
+        if entry.max_response_size == 0 {
+            anyhow::bail!("max_response_size of source `{name}` must be positive");
+        }
+        if entry.timeout == 0 {
+            anyhow::bail!("timeout of source `{name}` must be at least one second");
+        }
+
+        let pins =
+            parse_pins(&entry.pins).with_context(|| format!("Invalid pins for source `{name}`"))?;
+
+        Ok(Self {
+            name,
+            hosts: entry
+                .hosts
+                .into_iter()
+                .map(|host| host.to_ascii_lowercase())
+                .collect(),
+            pins,
+            max_response_size: entry.max_response_size,
+            timeout: Duration::from_secs(entry.timeout),
+        })
+    }
+
+    /// Make sure the source may be fetched from on the `enclave` backend
+    ///
+    /// Only the `host` backend, which has no hardware protection anyway, fetches from
+    /// sources that are not pinned.
+    pub fn check_pinned(&self, enclave: &str) -> anyhow::Result<()> {
+        if self.pins.is_empty() && enclave != "host" {
+            anyhow::bail!(
+                "Source `{}` is not pinned, which only the host backend allows",
+                self.name
+            );
+        }
+        Ok(())
+    }
+
+    /// Is the oracle allowed to connect to `host` for this source?
+    pub fn allows_host(&self, host: &str) -> bool {
+        self.hosts
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(host))
+    }
+}
+
+/// The data sources known to the oracle, by name, and how prices are aggregated
+pub struct Sources {
+    sources: BTreeMap<String, Source>,
+    pub prices: PricePolicy,
+}
+
+impl Sources {
+    /// Load the embedded sources
+    ///
+    /// Fails unless every name in `required` is configured.
+    pub fn load(required: &[&str]) -> anyhow::Result<Self> {
+        Self::parse(MANIFEST, required).with_context(|| "Invalid embedded source registry")
+    }
+
+    fn parse(manifest: &str, required: &[&str]) -> anyhow::Result<Self> {
+        let manifest: Manifest = toml::from_str(manifest)?;
+
+        let sources = manifest
+            .sources
+            .into_iter()
+            .map(|(name, entry)| Ok((name.clone(), Source::new(name, entry)?)))
+            .collect::<anyhow::Result<BTreeMap<_, _>>>()?;
+
+        for name in required {
+            if !sources.contains_key(*name) {
+                anyhow::bail!("No configuration for data source `{name}`");
+            }
+        }
+
+        if manifest.prices.quorum == 0 {
+            anyhow::bail!("prices.quorum must be at least one");
+        }
+
+        Ok(Self {
+            sources,
+            prices: manifest.prices,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> anyhow::Result<&Source> {
+        self.sources
+            .get(name)
+            .with_context(|| format!("No configuration for data source `{name}`"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fetch::source_names;
+    use crate::tls::anchor_pin;
+
+    fn manifest(pins: &str) -> String {
+        format!(
+            r#"
+            [prices]
+            quorum = 1
+            max_deviation = 100
+
+            [sources.scores]
+            hosts = ["example.com"]
+            pins = [{pins}]
+            max_response_size = 1024
+            timeout = 10
+            "#
+        )
+    }
+
+    #[test]
+    fn embedded_sources_load() {
+        Sources::load(&source_names()).unwrap();
+    }
+
+    #[test]
+    fn root_pins_are_rejected() {
+        let root = anchor_pin(&webpki_roots::TLS_SERVER_ROOTS[0]);
+        let pin = format!("\"{PIN_PREFIX}{}\"", BASE64_STANDARD.encode(root));
+
+        let err = Sources::parse(&manifest(&pin), &["scores"]).err().unwrap();
+        assert!(format!("{err:#}").contains("well-known root"));
+    }
+
+    #[test]
+    fn unpinned_sources_are_only_fetched_on_the_host() {
+        let sources = Sources::parse(&manifest(""), &["scores"]).unwrap();
+        let source = sources.get("scores").unwrap();
+
+        source.check_pinned("host").unwrap();
+        let err = source.check_pinned("tdx").unwrap_err();
+        assert!(err.to_string().contains("not pinned"));
+
+        let pin = format!("\"{PIN_PREFIX}{}\"", BASE64_STANDARD.encode([7; 32]));
+        let sources = Sources::parse(&manifest(&pin), &["scores"]).unwrap();
+        sources.get("scores").unwrap().check_pinned("tdx").unwrap();
+    }
+}
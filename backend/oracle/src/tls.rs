@@ -0,0 +1,336 @@
+//! TLS for requests to data sources, whose responses the oracle has to vouch for
+//!
+//! Works like ureq's own rustls connector, but only connects to the hosts of a [`Source`],
+//! checks its pinned keys, and remembers the certificate chain that each server presented,
+//! so that it can be included in a [`FetchProof`](snorkle_oracle_interface::FetchProof).
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, ServerName, TrustAnchor, UnixTime};
+use rustls::{ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, StreamOwned};
+
+use sha2::{Digest, Sha256};
+
+use ureq::RequestBuilder;
+use ureq::config::Config;
+use ureq::typestate::WithoutBody;
+use ureq::unversioned::transport::{
+    Buffers, ConnectionDetails, Connector, Either, LazyBuffers, NextTimeout, TcpConnector,
+    Transport, TransportAdapter,
+};
+
+use crate::http::Resolver;
+use crate::sources::Source;
+
+/// DER-encoded certificates, leaf first
+type CertificateChain = Arc<Mutex<Vec<Vec<u8>>>>;
+
+/// Does the SubjectPublicKeyInfo of `certificate` hash to one of `pins`?
+fn matches_pin(certificate: &CertificateDer<'_>, pins: &[[u8; 32]]) -> bool {
+    let Ok(certificate) = webpki::EndEntityCert::try_from(certificate) else {
+        return false;
+    };
+    let hash: [u8; 32] = Sha256::digest(certificate.subject_public_key_info()).into();
+
+    pins.contains(&hash)
+}
+
+/// The SHA-256 hash of the SubjectPublicKeyInfo of a root, as pinned by sources
+///
+/// webpki stores the SubjectPublicKeyInfo without its outer SEQUENCE, which is put back so
+/// that the hash matches that of the certificate.
+pub fn anchor_pin(anchor: &TrustAnchor<'_>) -> [u8; 32] {
+    let content = anchor.subject_public_key_info.as_ref();
+    let length = content.len().to_be_bytes();
+    let length = &length[length.iter().position(|&byte| byte != 0).unwrap_or(7)..];
+
+    let mut spki = vec![0x30];
+    if content.len() < 0x80 {
+        spki.push(content.len() as u8);
+    } else {
+        spki.push(0x80 | length.len() as u8);
+        spki.extend_from_slice(length);
+    }
+    spki.extend_from_slice(content);
+
+    Sha256::digest(&spki).into()
+}
+
+/// Is `pin` that of one of the webpki roots?
+pub fn is_root_pin(pin: &[u8; 32]) -> bool {
+    webpki_roots::TLS_SERVER_ROOTS
+        .iter()
+        .any(|anchor| anchor_pin(anchor) == *pin)
+}
+
+/// Verifies server certificates against the webpki roots and the source's pins, and
+/// records the last valid chain
+#[derive(Debug)]
+struct SourceVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    /// Not checked if empty; see [`Source::check_pinned`]
+    pins: Vec<[u8; 32]>,
+    chain: CertificateChain,
+}
+
+impl ServerCertVerifier for SourceVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+
+        // Only the certificates the server presents count; roots cannot be pinned
+        if !self.pins.is_empty()
+            && !std::iter::once(end_entity)
+                .chain(intermediates)
+                .any(|certificate| matches_pin(certificate, &self.pins))
+        {
+            return Err(rustls::Error::General(format!(
+                "no certificate presented by {server_name:?} matches a pinned key"
+            )));
+        }
+
+        *self.chain.lock().unwrap() = std::iter::once(end_entity)
+            .chain(intermediates)
+            .map(|certificate| certificate.to_vec())
+            .collect();
+
+        Ok(verified)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Wraps connections to the hosts of a source in TLS using a [`SourceVerifier`]
+struct SourceConnector {
+    config: Arc<ClientConfig>,
+    source: Source,
+}
+
+impl fmt::Debug for SourceConnector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SourceConnector").finish()
+    }
+}
+
+impl<In: Transport> Connector<In> for SourceConnector {
+    type Out = Either<In, SourceTransport>;
+
+    fn connect(
+        &self,
+        details: &ConnectionDetails,
+        chained: Option<In>,
+    ) -> Result<Option<Self::Out>, ureq::Error> {
+        let Some(transport) = chained else {
+            panic!("SourceConnector requires a chained transport");
+        };
+
+        let host = details.uri.host().unwrap_or_default();
+        if !self.source.allows_host(host) {
+            return Err(refused(format!(
+                "host `{host}` is not allowed for data source `{}`",
+                self.source.name
+            )));
+        }
+        if !details.needs_tls() {
+            return Err(refused(format!(
+                "data source `{}` must be fetched over HTTPS",
+                self.source.name
+            )));
+        }
+        if transport.is_tls() {
+            return Ok(Some(Either::A(transport)));
+        }
+
+        let name: ServerName<'_> = host
+            .try_into()
+            .map_err(|_| ureq::Error::Tls("invalid dns name"))?;
+
+        let conn = ClientConnection::new(self.config.clone(), name.to_owned())
+            .map_err(|err| ureq::Error::Io(std::io::Error::other(err)))?;
+        let stream = StreamOwned {
+            conn,
+            sock: TransportAdapter::new(transport.boxed()),
+        };
+
+        let buffers = LazyBuffers::new(
+            details.config.input_buffer_size(),
+            details.config.output_buffer_size(),
+        );
+
+        Ok(Some(Either::B(SourceTransport { buffers, stream })))
+    }
+}
+
+fn refused(msg: String) -> ureq::Error {
+    ureq::Error::Io(std::io::Error::new(
+        std::io::ErrorKind::PermissionDenied,
+        msg,
+    ))
+}
+
+struct SourceTransport {
+    buffers: LazyBuffers,
+    stream: StreamOwned<ClientConnection, TransportAdapter>,
+}
+
+impl fmt::Debug for SourceTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SourceTransport")
+            .field("chained", &self.stream.sock.inner())
+            .finish()
+    }
+}
+
+impl Transport for SourceTransport {
+    fn buffers(&mut self) -> &mut dyn Buffers {
+        &mut self.buffers
+    }
+
+    fn transmit_output(&mut self, amount: usize, timeout: NextTimeout) -> Result<(), ureq::Error> {
+        self.stream.get_mut().set_timeout(timeout);
+
+        let output = &self.buffers.output()[..amount];
+        self.stream.write_all(output)?;
+
+        Ok(())
+    }
+
+    fn await_input(&mut self, timeout: NextTimeout) -> Result<bool, ureq::Error> {
+        self.stream.get_mut().set_timeout(timeout);
+
+        let input = self.buffers.input_append_buf();
+        let amount = self.stream.read(input)?;
+        self.buffers.input_appended(amount);
+
+        Ok(amount > 0)
+    }
+
+    fn is_open(&mut self) -> bool {
+        self.stream.get_mut().get_mut().is_open()
+    }
+
+    fn is_tls(&self) -> bool {
+        true
+    }
+}
+
+/// An HTTP agent for a data source, which records the certificate chain of the server it
+/// talks to
+pub struct SourceAgent {
+    agent: ureq::Agent,
+    max_response_size: u64,
+    chain: CertificateChain,
+}
+
+impl SourceAgent {
+    pub fn new(source: &Source, resolver: Resolver) -> anyhow::Result<Self> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let chain = CertificateChain::default();
+
+        let verifier = SourceVerifier {
+            inner: WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                .build()?,
+            pins: source.pins.clone(),
+            chain: chain.clone(),
+        };
+
+        let connector = ().chain(TcpConnector::default()).chain(SourceConnector {
+            config: Self::client_config(provider, verifier)?,
+            source: source.clone(),
+        });
+        // Redirects could only lead to other hosts or plain HTTP
+        let config = Config::builder()
+            .timeout_global(Some(source.timeout))
+            .max_redirects(0)
+            .build();
+
+        Ok(Self {
+            agent: ureq::Agent::with_parts(config, connector, resolver),
+            max_response_size: source.max_response_size,
+            chain,
+        })
+    }
+
+    /// Fetch the body at `url`, failing if it exceeds the source's size limit
+    pub fn get(&self, url: &str) -> anyhow::Result<Vec<u8>> {
+        self.read(url, self.agent.get(url))
+    }
+
+    /// Like [`SourceAgent::get`], but asks for a response of type `accept`
+    pub fn get_as(&self, url: &str, accept: &str) -> anyhow::Result<Vec<u8>> {
+        self.read(url, self.agent.get(url).header("Accept", accept))
+    }
+
+    fn read(&self, url: &str, request: RequestBuilder<WithoutBody>) -> anyhow::Result<Vec<u8>> {
+        request
+            .call()
+            .and_then(|mut response| {
+                response
+                    .body_mut()
+                    .with_config()
+                    .limit(self.max_response_size)
+                    .read_to_vec()
+            })
+            .with_context(|| format!("Failed to fetch {url}"))
+    }
+
+    fn client_config(
+        provider: Arc<CryptoProvider>,
+        verifier: SourceVerifier,
+    ) -> anyhow::Result<Arc<ClientConfig>> {
+        let config = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(verifier))
+            .with_no_client_auth();
+
+        Ok(Arc::new(config))
+    }
+
+    /// The chain presented by the last server that passed verification
+    pub fn certificates(&self) -> Vec<Vec<u8>> {
+        self.chain.lock().unwrap().clone()
+    }
+}
@@ -1,14 +1,14 @@
-use std::str::FromStr;
-
-use rand::rngs::OsRng;
-
 use anyhow::Context;
 
 use snarkvm::ledger::store::helpers::memory::ConsensusMemory;
 use snarkvm::prelude::store::ConsensusStore;
 use snarkvm::prelude::*;
 
+use snorkle_oracle_interface::FeeOptions;
+
 use super::Oracle;
+use crate::metrics;
+use crate::programs::OracleProgram;
 
 /// Generates a transaction for the oracle's private key, event ID, and game data.
 impl<N: Network> Oracle<N> {
@@ -19,46 +19,41 @@ impl<N: Network> Oracle<N> {
         .with_context(|| "Failed to initialize the VM")
     }
 
-    pub fn init_program() -> Program<N> {
-        Program::<N>::from_str(include_str!(
-            "../../resources/proto_snorkle_oracle_002.aleo"
-        ))
-        .expect("Failed to create the program")
-    }
-
     pub fn generate_transaction(
         &self,
+        program: &OracleProgram<N>,
         transition: &str,
         args: &[Value<N>],
+        fee: &FeeOptions,
     ) -> anyhow::Result<Transaction<N>> {
-        let query = Some(snarkvm::prelude::query::Query::from(
-            "https://api.explorer.provable.com/v1",
-        ));
+        // snarkVM queries the state root and block height with its own HTTP client, which
+        // neither pins the node nor uses the enclave's resolver
+        let query = Some(snarkvm::prelude::query::Query::from(crate::chain::NODE_URL));
 
-        #[cfg(feature = "reuse-vm")]
         let vm = &self.vm;
 
-        #[cfg(not(feature = "reuse-vm"))]
-        let vm = Self::init_vm()?;
-
-        // Add the oracle program to the process.
-        vm.process()
-            .write()
-            .add_program(&self.program)
-            .with_context(|| "Failed to add program to VM")?;
+        // Check the fee before proving.
+        let (authorization, fee_authorization) =
+            self.generate_authorization(program, transition, args, fee)?;
 
         // Create the transaction.
+        let timer = metrics::PROVING_DURATION
+            .with_label_values(&[transition])
+            .start_timer();
         let txn = vm
-            .execute(
-                &self.key,
-                (self.program.id(), transition),
-                args.iter(),
-                None,
-                0,
+            .execute_authorization(
+                authorization,
+                Some(fee_authorization),
                 query,
-                &mut OsRng,
+                &mut self.rng(),
             )
             .with_context(|| "Failed to create a transaction")?;
+        timer.observe_duration();
+
+        // Keys have been synthesized during the first execution; persist them.
+        if let Err(err) = self.store_keys(program, transition) {
+            println!("Failed to store keys: {err:#}");
+        }
 
         #[cfg(feature = "extra-verify")]
         vm.process().read().verify_execution(
@@ -68,4 +63,52 @@ impl<N: Network> Oracle<N> {
 
         Ok(txn)
     }
+
+    /// Authorize a transition and its fee without proving them
+    ///
+    /// The fee is fixed when it is authorized, so the estimate of the base fee is paid in full.
+    pub fn generate_authorization(
+        &self,
+        program: &OracleProgram<N>,
+        transition: &str,
+        args: &[Value<N>],
+        fee: &FeeOptions,
+    ) -> anyhow::Result<(Authorization<N>, Authorization<N>)> {
+        let vm = &self.vm;
+
+        let authorization = vm
+            .authorize(
+                &self.key,
+                program.id(),
+                transition,
+                args.iter(),
+                &mut self.rng(),
+            )
+            .with_context(|| "Failed to authorize the transition")?;
+
+        let base_fee = self.estimate_fee(vm, &authorization)?;
+        let fee_record = self.prepare_fee(fee, base_fee)?;
+        let execution_id = authorization.to_execution_id()?;
+
+        let fee_authorization = match fee_record {
+            Some(record) => vm.authorize_fee_private(
+                &self.key,
+                record,
+                base_fee,
+                fee.priority_fee,
+                execution_id,
+                &mut self.rng(),
+            ),
+            None => vm.authorize_fee_public(
+                &self.key,
+                base_fee,
+                fee.priority_fee,
+                execution_id,
+                &mut self.rng(),
+            ),
+        }
+        .with_context(|| "Failed to authorize the fee")?;
+
+        Ok((authorization, fee_authorization))
+    }
 }
@@ -0,0 +1,111 @@
+//! Proves transactions that were authorized elsewhere
+//!
+//! The oracle signs transitions and their fees inside the enclave, but leaves the slow and
+//! memory hungry proving to a prover outside of it. The prover never sees the oracle's key; it
+//! only needs the programs, which it fetches from the node.
+
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Context;
+
+use rand::rngs::OsRng;
+
+use serde::{Deserialize, Serialize};
+
+use snarkvm::ledger::store::ConsensusStore;
+use snarkvm::ledger::store::helpers::memory::ConsensusMemory;
+use snarkvm::prelude::*;
+
+/// Time until fetching a program from the node fails, so that a hung node does not hold up
+/// the proofs waiting for it
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Body of `POST /prove`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ProveRequest<N: Network> {
+    pub authorization: Authorization<N>,
+    /// Transactions without a fee are only valid if they have no finalize cost
+    pub fee_authorization: Option<Authorization<N>>,
+}
+
+/// Turns authorizations into transactions
+pub struct Prover<N: Network> {
+    vm: VM<N, ConsensusMemory<N>>,
+    /// REST endpoint of the node, including the network, e.g.,
+    /// `https://api.explorer.provable.com/v1/testnet`
+    endpoint: String,
+    agent: ureq::Agent,
+    /// Programs are loaded one at a time, so that concurrent proofs do not add one twice
+    loading: Mutex<()>,
+}
+
+impl<N: Network> Prover<N> {
+    pub fn new(endpoint: &str) -> anyhow::Result<Self> {
+        let vm = VM::<N, ConsensusMemory<N>>::from(
+            ConsensusStore::open(0).with_context(|| "Failed to initialize the consensus store")?,
+        )
+        .with_context(|| "Failed to initialize the VM")?;
+
+        Ok(Self {
+            vm,
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            agent: ureq::Agent::new_with_config(
+                ureq::Agent::config_builder()
+                    .timeout_global(Some(FETCH_TIMEOUT))
+                    .build(),
+            ),
+            loading: Mutex::new(()),
+        })
+    }
+
+    /// Fetch a program and its imports from the node, unless they are loaded already
+    fn load_program(&self, id: &ProgramID<N>) -> anyhow::Result<()> {
+        if self.vm.process().read().contains_program(id) {
+            return Ok(());
+        }
+
+        log::info!("Fetching {id} from the node");
+        let url = format!("{}/program/{id}", self.endpoint);
+        let source: String = self
+            .agent
+            .get(url)
+            .call()
+            .with_context(|| format!("Failed to fetch {id}"))?
+            .body_mut()
+            .read_json()?;
+        let program = Program::<N>::from_str(&source).with_context(|| format!("Invalid {id}"))?;
+
+        for import in program.imports().keys() {
+            self.load_program(import)?;
+        }
+
+        self.vm
+            .process()
+            .write()
+            .add_program(&program)
+            .with_context(|| format!("Failed to load {id}"))
+    }
+
+    /// Prove an authorized transition and its fee
+    pub fn prove(&self, request: ProveRequest<N>) -> anyhow::Result<Transaction<N>> {
+        {
+            let _guard = self.loading.lock().unwrap();
+            for transition in request.authorization.transitions().values() {
+                self.load_program(transition.program_id())?;
+            }
+        }
+
+        let query = snarkvm::prelude::query::Query::from(self.endpoint.as_str());
+        self.vm
+            .execute_authorization(
+                request.authorization,
+                request.fee_authorization,
+                Some(query),
+                &mut OsRng,
+            )
+            .with_context(|| "Failed to prove the transaction")
+    }
+}
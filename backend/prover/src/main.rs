@@ -0,0 +1,146 @@
+//! Proves authorizations from the oracle over HTTP
+//!
+//! Several instances can run behind a load balancer to form a worker pool for the gateway.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::Context;
+
+use axum::{
+    Json, Router,
+    extract::DefaultBodyLimit,
+    http::{HeaderMap, StatusCode, header},
+    routing::post,
+};
+
+use clap::Parser;
+
+use snarkvm::prelude::{TestnetV0, Transaction};
+
+use tokio::sync::Semaphore;
+
+use snorkle_oracle_interface::constant_time_eq;
+
+use snorkle_prover::{ProveRequest, Prover};
+
+type CurrentNetwork = TestnetV0;
+
+/// Command line arguments of the prover
+#[derive(Debug, Parser)]
+#[command(version, about = "Proves transactions authorized by the oracle", long_about = None)]
+struct Args {
+    /// REST endpoint of the node, including the network
+    #[arg(
+        long,
+        env = "SNORKLE_NODE_ENDPOINT",
+        default_value = "https://api.explorer.provable.com/v1/testnet"
+    )]
+    endpoint: String,
+
+    /// Address the HTTP server binds to; only reachable from this host by default
+    #[arg(long, env = "SNORKLE_PROVER_BIND", default_value = "127.0.0.1:3200")]
+    bind: SocketAddr,
+
+    /// Token that clients must send as `Authorization: Bearer <token>`
+    #[arg(long, env = "SNORKLE_PROVER_TOKEN", hide_env_values = true)]
+    token: String,
+
+    /// Number of transactions proven at the same time
+    #[arg(long, env = "SNORKLE_PROVER_WORKERS", default_value_t = 1)]
+    workers: usize,
+
+    /// Number of requests that may wait for a worker; more are rejected
+    #[arg(long, env = "SNORKLE_PROVER_MAX_PENDING", default_value_t = 16)]
+    max_pending: usize,
+
+    /// Largest request body (in bytes) that is accepted
+    #[arg(long, env = "SNORKLE_PROVER_MAX_REQUEST_SIZE", default_value_t = 1 << 20)]
+    max_request_size: usize,
+}
+
+struct Service {
+    prover: Arc<Prover<CurrentNetwork>>,
+    token: String,
+    workers: Semaphore,
+    /// Requests being proven or waiting for a worker
+    pending: Semaphore,
+}
+
+impl Service {
+    async fn prove_handler(
+        &self,
+        headers: HeaderMap,
+        Json(request): Json<ProveRequest<CurrentNetwork>>,
+    ) -> Result<Json<Transaction<CurrentNetwork>>, (StatusCode, String)> {
+        let authorized = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|token| constant_time_eq(token.trim().as_bytes(), self.token.as_bytes()));
+        if !authorized {
+            log::warn!("Rejected request without a valid token");
+            return Err((StatusCode::UNAUTHORIZED, "invalid token".to_string()));
+        }
+
+        let Ok(_pending) = self.pending.try_acquire() else {
+            log::warn!("Rejected request: too many pending requests");
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                "too many pending requests".to_string(),
+            ));
+        };
+        let _permit = self.workers.acquire().await.unwrap();
+
+        let prover = self.prover.clone();
+        let transaction = tokio::task::spawn_blocking(move || prover.prove(request))
+            .await
+            .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+            .map_err(|err| {
+                log::error!("{err:#}");
+                (StatusCode::UNPROCESSABLE_ENTITY, format!("{err:#}"))
+            })?;
+
+        log::info!("Proved transaction {}", transaction.id());
+        Ok(Json(transaction))
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::init();
+
+    let args = Args::parse();
+    if args.workers == 0 {
+        anyhow::bail!("--workers must be at least one");
+    }
+    if args.token.len() < 16 {
+        anyhow::bail!("--token must be at least 16 characters");
+    }
+    if args.max_request_size == 0 {
+        anyhow::bail!("--max-request-size must be positive");
+    }
+
+    let obj = Arc::new(Service {
+        prover: Arc::new(Prover::new(&args.endpoint)?),
+        token: args.token,
+        workers: Semaphore::new(args.workers),
+        pending: Semaphore::new(args.workers + args.max_pending),
+    });
+    let obj1 = obj.clone();
+
+    let app = Router::new()
+        .route(
+            "/prove",
+            post(async move |headers, payload| obj1.prove_handler(headers, payload).await),
+        )
+        .layer(DefaultBodyLimit::max(args.max_request_size));
+
+    let listener = tokio::net::TcpListener::bind(args.bind)
+        .await
+        .with_context(|| format!("Failed to bind to {}", args.bind))?;
+    log::info!("Prover listening at http://{}", args.bind);
+
+    axum::serve(listener, app).await?;
+    Ok(())
+}
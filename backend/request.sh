@@ -1,8 +1,16 @@
 #! /bin/bash
 
-#GAME_ID='"${1}"'
+# Usage: ./request.sh [game_id]
+# Set SNORKLE_API_KEY to the key of a client if the gateway requires one (see `auth.keys`).
+
+GAME_ID="${1:-29d16147-102e-4bd8-99db-7ba3a6815a57}"
+
+AUTH=()
+if [ -n "${SNORKLE_API_KEY}" ]; then
+  AUTH=(-H "Authorization: Bearer ${SNORKLE_API_KEY}")
+fi
 
 curl -X POST http://0.0.0.0:3000/submit \
   -H "Content-Type: application/json" \
-  -d '{ "game_id": "29d16147-102e-4bd8-99db-7ba3a6815a57" }'
-
+  "${AUTH[@]}" \
+  -d "{ \"game_id\": \"${GAME_ID}\" }"
@@ -4,6 +4,8 @@ from time import sleep
 
 from subprocess import Popen
 
+from os import environ
+
 from sys import argv
 
 if len(argv) > 1:
@@ -11,9 +13,10 @@ if len(argv) > 1:
 else:
     path = ""
 
-oracle = Popen([path+"snorkle-oracle"])
+# Without hardware and a sealed key, only the host backend starts (with the development key)
+oracle = Popen([path+"snorkle-oracle"], env={"SNORKLE_ENCLAVE": "host", **environ})
 sleep(2)
-frontend = Popen([path+"snorkle-gateway"])
+frontend = Popen([path+"snorkle-gateway", "--config", "gateway/dev.toml"])
 
 print("Waiting for Ctrl+C")
 while True:
@@ -1,80 +1,447 @@
+use anyhow::Context;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use rand_core::OsRng;
+use snarkvm::circuit::{AleoCanaryV0, AleoTestnetV0, AleoV0};
 use snarkvm::ledger::store::helpers::memory::ConsensusMemory;
 use snarkvm::prelude::store::ConsensusStore;
-use snarkvm::prelude::{Address, Network, PrivateKey, Program, TestnetV0, Transaction, Value, VM};
+use snarkvm::prelude::{
+    Address, CanaryV0, Field, Hash, MainnetV0, Network, PrivateKey, Program, Signature, TestnetV0,
+    ToFields, Transaction, VM, Value,
+};
+use snorkle_oracle_interface::fees::estimate_base_fee;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-pub struct GameData {
-    pub event_id: String,
-    pub away_score: u8,
-    pub home_score: u8,
+/// The scores API that event IDs are derived from, as in the oracle
+const GAME_URL: &str = "https://scores-api-349861721231.northamerica-northeast1.run.app/games";
+
+type OracleVM<N> = VM<N, ConsensusMemory<N>>;
+
+/// Generates, signs, and broadcasts submissions to a snorkle oracle program
+#[derive(Debug, Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    /// File containing the private key of the oracle; read from `ALEO_PRIVATE_KEY` if unset
+    #[arg(long, global = true)]
+    key_file: Option<PathBuf>,
+
+    /// Aleo network to operate on
+    #[arg(long, global = true, value_enum, default_value_t = NetworkName::Testnet)]
+    network: NetworkName,
+
+    /// The oracle program; the embedded proto_snorkle_oracle_001.aleo if unset
+    #[arg(long, global = true)]
+    program: Option<PathBuf>,
+
+    /// Programs imported by `--program`, in dependency order
+    #[arg(long = "import", global = true)]
+    imports: Vec<PathBuf>,
+
+    /// Base URL of the node's REST API (without the network)
+    #[arg(
+        long,
+        global = true,
+        env = "ALEO_ENDPOINT",
+        default_value = "https://api.explorer.provable.com/v1"
+    )]
+    endpoint: String,
+
+    /// What `register` and `submit` produce
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Transaction)]
+    output: OutputFormat,
+
+    /// Write the result to this file instead of stdout
+    #[arg(long, short, global = true)]
+    out: Option<PathBuf>,
+
+    /// Additional fee (in microcredits) on top of the base fee
+    #[arg(long, global = true, default_value_t = 0)]
+    priority_fee: u64,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum NetworkName {
+    Mainnet,
+    Testnet,
+    Canary,
 }
 
-const DEVNET_PRIVATE_KEY: &str = "APrivateKey1zkp8CZNn3yeCseEtxuVPbDCwSyhGW6yZKUYKfgXmcpoGPWH";
+impl NetworkName {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Mainnet => "mainnet",
+            Self::Testnet => "testnet",
+            Self::Canary => "canary",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// A proven transaction as JSON, ready to broadcast
+    Transaction,
+    /// The authorization of the transition and of its public fee as JSON, for delegated proving
+    Authorization,
+}
 
-fn main() {
-    // Define the game data.
-    let game_data = GameData {
-        event_id: "0field".to_string(),
-        away_score: 3,
-        home_score: 2,
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Register the oracle with the program
+    Register {
+        /// The attestation report; its hash is stored on chain
+        #[arg(long, required_unless_present = "attestation_hash")]
+        report: Option<String>,
+        /// The attestation hash, if already computed
+        #[arg(long, conflicts_with = "report")]
+        attestation_hash: Option<String>,
+        /// Name of the register transition
+        #[arg(long, default_value = "register")]
+        transition: String,
+    },
+    /// Sign the result of a game and submit it
+    Submit {
+        #[command(flatten)]
+        event: EventArgs,
+        /// Name of the submit transition
+        #[arg(long, default_value = "submit_event")]
+        transition: String,
+    },
+    /// Sign a message with the oracle's key
+    Sign {
+        #[command(flatten)]
+        message: MessageArgs,
+    },
+    /// Hash a string to a field, as the oracle does for event IDs and attestation reports
+    Hash {
+        /// The string to hash
+        #[arg(required_unless_present = "game_id")]
+        input: Option<String>,
+        /// Print the event ID of this game instead
+        #[arg(long, conflicts_with = "input")]
+        game_id: Option<String>,
+        /// Revision of the event; corrections have a revision above zero
+        #[arg(long, default_value_t = 0, requires = "game_id")]
+        revision: u32,
+    },
+    /// Check that a message was signed by an address
+    VerifySignature {
+        #[arg(long)]
+        address: String,
+        #[arg(long)]
+        signature: String,
+        #[command(flatten)]
+        message: MessageArgs,
+    },
+    /// Broadcast a transaction produced by `register` or `submit`
+    Broadcast {
+        /// File containing the transaction as JSON; read from stdin if `-`
+        file: PathBuf,
+    },
+}
+
+/// The result of a game
+#[derive(Debug, Args)]
+struct EventArgs {
+    /// ID of the game in the scores API; the event ID is derived from it
+    #[arg(long, required_unless_present = "event_id")]
+    game_id: Option<String>,
+    /// Revision of the event; corrections have a revision above zero
+    #[arg(long, default_value_t = 0)]
+    revision: u32,
+    /// Use this event ID instead of deriving it from `--game-id`
+    #[arg(long, conflicts_with = "game_id")]
+    event_id: Option<String>,
+    /// Score of the home team
+    #[arg(long)]
+    home: Option<u8>,
+    /// Score of the away team
+    #[arg(long)]
+    away: Option<u8>,
+}
+
+/// A message to sign or verify: either an Aleo value or the result of a game
+#[derive(Debug, Args)]
+struct MessageArgs {
+    /// An Aleo plaintext value, e.g., `{ id: 1field, home_team_score: 3u8, away_team_score: 2u8 }`
+    #[arg(long, conflicts_with_all = ["game_id", "event_id"])]
+    value: Option<String>,
+    #[command(flatten)]
+    event: EventArgs,
+}
+
+// Hashes bytes to a field with BHP1024, as the oracle does.
+fn hash(bytes: &[u8]) -> anyhow::Result<String> {
+    let bits: Vec<bool> = bytes
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1))
+        .collect();
+
+    let hasher = &snarkvm::prelude::BHP_1024;
+    hasher.hash(&bits).map(|res| res.to_string())
+}
+
+// Derives the event ID of a game, as the oracle does.
+fn event_id(game_id: &str, revision: u32) -> anyhow::Result<String> {
+    let preimage = if revision == 0 {
+        format!("{GAME_URL}/{game_id}")
+    } else {
+        format!("{GAME_URL}/{game_id}#{revision}")
     };
-    // Generate the transaction.
-    let transaction = generate_transaction::<TestnetV0>(DEVNET_PRIVATE_KEY, game_data);
-    // Print the transaction.
-    println!("Transaction: {:?}", transaction);
+
+    hash(preimage.as_bytes())
+}
+
+impl EventArgs {
+    // Creates the game data, as taken by the submit transition.
+    fn game_data<N: Network>(&self) -> anyhow::Result<Value<N>> {
+        let event_id = match (&self.event_id, &self.game_id) {
+            (Some(event_id), _) => event_id.clone(),
+            (None, Some(game_id)) => event_id(game_id, self.revision)?,
+            (None, None) => anyhow::bail!("--game-id or --event-id is required"),
+        };
+        let home = self.home.context("--home is required")?;
+        let away = self.away.context("--away is required")?;
+
+        Value::<N>::from_str(&format!(
+            "{{ id: {event_id}, home_team_score: {home}u8, away_team_score: {away}u8 }}"
+        ))
+        .context("Failed to create game data")
+    }
+}
+
+impl MessageArgs {
+    fn value<N: Network>(&self) -> anyhow::Result<Value<N>> {
+        match &self.value {
+            Some(value) => Value::<N>::from_str(value).context("Invalid value"),
+            None => self.event.game_data(),
+        }
+    }
 }
 
-// Generates a transaction for the oracle's private key, event ID, and game data.
-fn generate_transaction<N: Network>(private_key: &str, game_data: GameData) -> Transaction<N> {
-    // Create the private key.
-    let private_key =
-        PrivateKey::<N>::from_str(private_key).expect("Failed to initialize private key");
-    // Get the oracle address.
-    let _oracle_address = Address::try_from(&private_key).expect("Failed to create oracle address");
-
-    // Create the game data.
-    let game_data = Value::<N>::from_str(&format!(
-        r"
-{{
-    id: {},
-    home_team_score: {},
-    away_team_score: {},
-}}",
-        game_data.event_id, game_data.home_score, game_data.away_score
-    ))
-    .expect("Failed to create game data");
-
-    // Initialize the program.
-    let program =
-        Program::<N>::from_str(include_str!("../resources/proto_snorkle_oracle_001.aleo"))
-            .expect("Failed to create the program");
-
-    // Initialize a VM.
-    let vm = VM::<N, ConsensusMemory<N>>::from(
-        ConsensusStore::open(0).expect("Failed to initialize the consensus store"),
-    )
-    .expect("Failed to initialize the VM");
-
-    // Add the oracle program to the process.
-    vm.process()
-        .write()
-        .add_program(&program)
-        .expect("Failed to add program");
-
-    // Create the transaction.
-    let transaction = vm
-        .execute(
-            &private_key,
-            (program.id(), "submit_event"),
-            [game_data].into_iter(),
-            None,
-            0,
-            None,
-            &mut OsRng,
+impl Cli {
+    // Reads the private key from the key file or the environment.
+    fn private_key<N: Network>(&self) -> anyhow::Result<PrivateKey<N>> {
+        let key = match &self.key_file {
+            Some(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read key file {}", path.display()))?,
+            None => std::env::var("ALEO_PRIVATE_KEY")
+                .context("Pass --key-file or set ALEO_PRIVATE_KEY")?,
+        };
+
+        PrivateKey::<N>::from_str(key.trim()).context("Invalid private key")
+    }
+
+    fn node_url(&self) -> String {
+        format!(
+            "{}/{}",
+            self.endpoint.trim_end_matches('/'),
+            self.network.as_str()
+        )
+    }
+
+    // Initializes a VM with the oracle program and its imports.
+    fn init_vm<N: Network>(&self) -> anyhow::Result<(OracleVM<N>, Program<N>)> {
+        let vm = VM::<N, ConsensusMemory<N>>::from(
+            ConsensusStore::open(0).context("Failed to initialize the consensus store")?,
         )
-        .expect("Failed to create a transaction");
+        .context("Failed to initialize the VM")?;
+
+        let read = |path: &Path| -> anyhow::Result<Program<N>> {
+            let source = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            Program::from_str(&source)
+                .with_context(|| format!("Invalid program {}", path.display()))
+        };
+
+        let program = match &self.program {
+            Some(path) => read(path)?,
+            None => {
+                Program::<N>::from_str(include_str!("../resources/proto_snorkle_oracle_001.aleo"))
+                    .context("Failed to create the program")?
+            }
+        };
+
+        {
+            let process = vm.process();
+            let mut process = process.write();
+            for path in &self.imports {
+                process.add_program(&read(path)?)?;
+            }
+            process.add_program(&program)?;
+        }
+
+        Ok((vm, program))
+    }
+
+    fn write_output(&self, output: &str) -> anyhow::Result<()> {
+        match &self.out {
+            Some(path) => std::fs::write(path, output)
+                .with_context(|| format!("Failed to write {}", path.display())),
+            None => {
+                println!("{output}");
+                Ok(())
+            }
+        }
+    }
+
+    // Creates a transaction, or an authorization for delegated proving.
+    fn execute<N: Network, A: snarkvm::circuit::Aleo<Network = N>>(
+        &self,
+        transition: &str,
+        inputs: Vec<Value<N>>,
+    ) -> anyhow::Result<()> {
+        let private_key = self.private_key::<N>()?;
+        let (vm, program) = self.init_vm::<N>()?;
+
+        let output = match self.output {
+            OutputFormat::Transaction => {
+                let query = snarkvm::prelude::query::Query::from(self.node_url().as_str());
+                let transaction = vm
+                    .execute(
+                        &private_key,
+                        (program.id(), transition),
+                        inputs.iter(),
+                        None,
+                        self.priority_fee,
+                        Some(query),
+                        &mut OsRng,
+                    )
+                    .context("Failed to create a transaction")?;
+                eprintln!("Created transaction {}", transaction.id());
+
+                serde_json::to_string(&transaction)?
+            }
+            OutputFormat::Authorization => {
+                let process = vm.process();
+                let process = process.read();
+
+                let authorization = process.authorize::<A, _>(
+                    &private_key,
+                    program.id(),
+                    transition,
+                    inputs.iter(),
+                    &mut OsRng,
+                )?;
+
+                let base_fee = estimate_base_fee(&process, &authorization)?;
+                let fee_authorization = process.authorize_fee_public::<A, _>(
+                    &private_key,
+                    base_fee,
+                    self.priority_fee,
+                    authorization.to_execution_id()?,
+                    &mut OsRng,
+                )?;
+                eprintln!(
+                    "Authorized {}/{transition} with an estimated base fee of {base_fee} microcredits",
+                    program.id()
+                );
+
+                serde_json::to_string(&serde_json::json!({
+                    "authorization": authorization,
+                    "fee_authorization": fee_authorization,
+                }))?
+            }
+        };
+
+        self.write_output(&output)
+    }
+
+    fn run<N: Network, A: snarkvm::circuit::Aleo<Network = N>>(&self) -> anyhow::Result<()> {
+        match &self.command {
+            Command::Register {
+                report,
+                attestation_hash,
+                transition,
+            } => {
+                let attestation_hash = match (attestation_hash, report) {
+                    (Some(attestation_hash), _) => attestation_hash.clone(),
+                    (None, Some(report)) => hash(report.as_bytes())?,
+                    (None, None) => anyhow::bail!("--report or --attestation-hash is required"),
+                };
+                let input = Value::<N>::from_str(&attestation_hash)?;
+
+                self.execute::<N, A>(transition, vec![input])
+            }
+            Command::Submit { event, transition } => {
+                let private_key = self.private_key::<N>()?;
+                let game_data = event.game_data::<N>()?;
+
+                // Sign the game data, so that the program can check it came from the oracle.
+                let signature = private_key.sign(&game_data.to_fields()?, &mut OsRng)?;
+                let signature = Value::<N>::from_str(&signature.to_string())?;
+
+                self.execute::<N, A>(transition, vec![game_data, signature])
+            }
+            Command::Sign { message } => {
+                let private_key = self.private_key::<N>()?;
+                let signature =
+                    private_key.sign(&message.value::<N>()?.to_fields()?, &mut OsRng)?;
+
+                self.write_output(&signature.to_string())
+            }
+            Command::Hash {
+                input,
+                game_id,
+                revision,
+            } => {
+                let field = match (game_id, input) {
+                    (Some(game_id), _) => event_id(game_id, *revision)?,
+                    (None, Some(input)) => hash(input.as_bytes())?,
+                    (None, None) => anyhow::bail!("pass a string or --game-id"),
+                };
+
+                self.write_output(&field)
+            }
+            Command::VerifySignature {
+                address,
+                signature,
+                message,
+            } => {
+                let address = Address::<N>::from_str(address).context("Invalid address")?;
+                let signature = Signature::<N>::from_str(signature).context("Invalid signature")?;
+                let fields: Vec<Field<N>> = message.value::<N>()?.to_fields()?;
+
+                if !signature.verify(&address, &fields) {
+                    anyhow::bail!("The signature is not valid");
+                }
+                self.write_output("The signature is valid")
+            }
+            Command::Broadcast { file } => {
+                let data = if file.as_os_str() == "-" {
+                    let mut data = String::new();
+                    std::io::stdin().read_to_string(&mut data)?;
+                    data
+                } else {
+                    std::fs::read_to_string(file)
+                        .with_context(|| format!("Failed to read {}", file.display()))?
+                };
+
+                // Make sure it is a transaction for this network before sending it.
+                let transaction: Transaction<N> =
+                    serde_json::from_str(&data).context("Invalid transaction")?;
+
+                let url = format!("{}/transaction/broadcast", self.node_url());
+                let transaction_id: String = ureq::post(url)
+                    .send_json(&transaction)?
+                    .body_mut()
+                    .read_json()?;
+
+                self.write_output(&transaction_id)
+            }
+        }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
 
-    // Return the transaction.
-    transaction
+    match cli.network {
+        NetworkName::Mainnet => cli.run::<MainnetV0, AleoV0>(),
+        NetworkName::Testnet => cli.run::<TestnetV0, AleoTestnetV0>(),
+        NetworkName::Canary => cli.run::<CanaryV0, AleoCanaryV0>(),
+    }
 }